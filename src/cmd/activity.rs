@@ -0,0 +1,104 @@
+use std::path::PathBuf;
+
+use chrono::Utc;
+use skuff::DateExpr;
+use skuff::Dst;
+use skuff::Entity;
+use skuff::Error;
+use skuff::Event;
+use skuff::Id;
+use skuff::TimeExpr;
+use skuff::Zone;
+use skuff::output;
+use skuff::util::*;
+
+/// Register an activity, e.g. `skuff activity Code review +acme -t 09:00 --until 11:30`
+#[derive(clap::Args)]
+pub struct Args {
+    /// What you worked on, followed by any tags such as +acme or +billable
+    #[arg(required = true, num_args = 1..)]
+    words: Vec<String>,
+
+    /// When the activity started
+    #[arg(short, long, value_parser = parse_time, allow_hyphen_values = true)]
+    time: TimeExpr,
+
+    #[arg(short, long, value_parser = parse_date, allow_hyphen_values = true)]
+    date: Option<DateExpr>,
+
+    /// When the activity ended, on the same date [default: now]
+    #[arg(long, value_parser = parse_time, allow_hyphen_values = true)]
+    until: Option<TimeExpr>,
+
+    /// Timezone of --date, --time and --until, e.g. Europe/Oslo or +02:00 [default: system timezone]
+    #[arg(long, value_parser = parse_zone)]
+    tz: Option<Zone>,
+
+    /// Pick the earlier or later occurrence of a time repeated by a daylight saving change
+    #[arg(long, value_enum)]
+    dst: Option<Dst>,
+
+    /// Explain the entry
+    #[arg(short, long)]
+    note: Option<String>,
+
+    #[arg(long)]
+    stream: Option<String>,
+
+    #[arg(long)]
+    storage: Option<PathBuf>,
+}
+
+pub fn command(args: Args, format: output::Format) -> Result<(), Error> {
+    let storage = storage(args.storage)?;
+
+    let (value, tags) = split_tags(&args.words).map_err(Error::InvalidActivity)?;
+    if value.is_empty() {
+        return Err(Error::InvalidActivity(
+            "an activity needs a name besides its tags".to_string(),
+        ));
+    }
+
+    let tz = args.tz.unwrap_or_else(Zone::local);
+    let start = timestamp(args.date, Some(args.time), &tz, args.dst)?;
+    let end = match args.until {
+        Some(until) if !until.is_relative() => timestamp(args.date, Some(until), &tz, args.dst)?,
+        until => timestamp(None, until, &tz, args.dst)?,
+    };
+
+    if end <= start {
+        return Err(Error::InvalidActivity(format!(
+            "the activity ends at {} before it starts at {}",
+            tz.localize(&end).format("%Y-%m-%d %H:%M"),
+            tz.localize(&start).format("%Y-%m-%d %H:%M")
+        )));
+    }
+
+    let event = Event::Create {
+        id: Id::new(),
+        created_at: Utc::now(),
+        entity: Entity::Activity {
+            id: Id::new(),
+            timestamp: start,
+            tz: Some(tz),
+            note: args.note,
+            tags,
+            duration: end - start,
+            value,
+            autoinsert: false,
+        },
+    };
+
+    let written = output::Written {
+        event_id: event.id().clone(),
+        entity_id: event.entity_id().unwrap().clone(),
+    };
+
+    storage.stream_append(event, &args.stream)?;
+
+    if format != output::Format::Text {
+        output::print(&written)?;
+    }
+
+    Ok(())
+}
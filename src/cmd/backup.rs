@@ -0,0 +1,135 @@
+use std::path::PathBuf;
+
+use chrono::DateTime;
+use chrono::Utc;
+use serde::Serialize;
+use skuff::Error;
+use skuff::Zone;
+use skuff::io::Backup;
+use skuff::output;
+use skuff::util::*;
+
+/// List or restore the earlier versions kept of a stream
+///
+/// A version is kept every time the stream is written. The config's `backups` says how many:
+/// by default the last 10, plus one per day for a week and one per week for a month.
+#[derive(clap::Args)]
+pub struct Args {
+    #[command(subcommand)]
+    action: Action,
+
+    #[arg(long, global = true)]
+    stream: Option<String>,
+
+    #[arg(long, global = true)]
+    storage: Option<PathBuf>,
+}
+
+#[derive(clap::Subcommand)]
+enum Action {
+    /// List the backups, newest first
+    Ls,
+    /// Replace the stream with a backup. The replaced version is backed up as well.
+    Restore {
+        /// The backup, as listed by `ls`. A date or a shorter time picks the newest backup
+        /// starting with it, e.g. 2025-09-01 or "2025-09-01 14".
+        when: String,
+    },
+}
+
+pub fn command(args: Args, format: output::Format) -> Result<(), Error> {
+    let storage = storage(args.storage)?;
+    let stream = storage.coalease_stream_name(&args.stream)?;
+    let backups = storage.backups(&stream)?;
+
+    match args.action {
+        Action::Ls => {
+            let mut listed = vec![];
+            for backup in &backups {
+                let events = storage
+                    .read_backup(backup)
+                    .map(|events| events.events().len());
+                match (format, &events) {
+                    (output::Format::Text, Ok(events)) => {
+                        println!("{}  {} events", when(backup), events)
+                    }
+                    (output::Format::Text, Err(err)) => {
+                        println!("{}  unreadable: {:?}", when(backup), err)
+                    }
+                    _ => listed.push(Listed {
+                        taken_at: backup.taken_at,
+                        path: &backup.path,
+                        events: events.ok(),
+                    }),
+                }
+            }
+
+            match format {
+                output::Format::Text => Ok(()),
+                _ => output::print_all(format, &listed),
+            }
+        }
+        Action::Restore { when: prefix } => {
+            let backup = backups
+                .iter()
+                .find(|backup| when(backup).starts_with(&prefix))
+                .ok_or_else(|| Error::BackupNotFound(prefix.clone()))?;
+
+            // Loading the backup verifies it, so a damaged one is never restored
+            let events = storage.read_backup(backup)?;
+            // The stream may be restored because it no longer loads
+            let current = storage.stream(&Some(stream.clone())).ok();
+            storage.stream_replace(&events, &Some(stream.clone()))?;
+            let rewrites = current.is_none_or(|current| events.rewrites(&current));
+
+            if format != output::Format::Text {
+                return output::print(&Restored {
+                    stream: &stream,
+                    taken_at: backup.taken_at,
+                    events: events.events().len(),
+                    head: events.head(),
+                    rewritten: rewrites,
+                });
+            }
+
+            println!(
+                "Restored {} as it was before {} ({} events)",
+                stream,
+                when(backup),
+                events.events().len()
+            );
+            if rewrites {
+                println!("{}", rewritten(events.head()));
+            }
+
+            Ok(())
+        }
+    }
+}
+
+/// A backup in json and ndjson output
+#[derive(Serialize)]
+struct Listed<'a> {
+    taken_at: DateTime<Utc>,
+    path: &'a PathBuf,
+    /// The number of events, None if the backup can't be read
+    events: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct Restored<'a> {
+    stream: &'a str,
+    /// When the restored backup was taken
+    taken_at: DateTime<Utc>,
+    events: usize,
+    head: &'a str,
+    /// Whether hashes noted down before no longer verify the stream
+    rewritten: bool,
+}
+
+fn when(backup: &Backup) -> String {
+    Zone::local()
+        .localize(&backup.taken_at)
+        .format("%Y-%m-%d %H:%M:%S%.3f")
+        .to_string()
+}
@@ -0,0 +1,64 @@
+use std::path::PathBuf;
+
+use serde::Serialize;
+use skuff::Error;
+use skuff::compact;
+use skuff::output;
+use skuff::util::*;
+
+/// Rewrite a stream's history into one create event per entity
+///
+/// Edits and deletes are dropped, so the audit history is lost. The previous stream is kept as a
+/// backup, see `skuff backup`.
+#[derive(clap::Args)]
+pub struct Args {
+    #[arg(long)]
+    stream: Option<String>,
+
+    #[arg(long)]
+    storage: Option<PathBuf>,
+}
+
+pub fn command(args: Args, format: output::Format) -> Result<(), Error> {
+    let storage = storage(args.storage)?;
+
+    let stream = storage.stream(&args.stream)?;
+    let compacted = compact(&stream);
+
+    let backup = storage.stream_replace(&compacted, &args.stream)?;
+
+    let rewrites = compacted.rewrites(&stream);
+
+    if format != output::Format::Text {
+        return output::print(&Compacted {
+            events: stream.events().len(),
+            compacted: compacted.events().len(),
+            backup,
+            head: compacted.head(),
+            rewritten: rewrites,
+        });
+    }
+
+    println!(
+        "Compacted {} events into {}, the previous history is kept in {}",
+        stream.events().len(),
+        compacted.events().len(),
+        backup.display()
+    );
+    if rewrites {
+        println!("{}", rewritten(compacted.head()));
+    }
+
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct Compacted<'a> {
+    events: usize,
+    compacted: usize,
+    backup: PathBuf,
+    /// The last hash of the compacted stream
+    head: &'a str,
+    /// Whether hashes noted down before no longer verify the stream
+    rewritten: bool,
+}
@@ -0,0 +1,44 @@
+use std::path::PathBuf;
+
+use serde::Serialize;
+use skuff::Error;
+use skuff::io;
+use skuff::output;
+use skuff::util::*;
+
+/// Move the storage to another backend
+///
+/// The previous data is kept next to the converted storage as a backup.
+#[derive(clap::Args)]
+pub struct Args {
+    #[arg(long, value_enum)]
+    to: io::Backend,
+
+    #[arg(long)]
+    storage: Option<PathBuf>,
+}
+
+pub fn command(args: Args, format: output::Format) -> Result<(), Error> {
+    let layout = layout(args.storage)?;
+
+    let converted: Vec<Converted> = io::convert(&layout, args.to)?
+        .into_iter()
+        .map(|(stream, events)| Converted { stream, events })
+        .collect();
+
+    if format != output::Format::Text {
+        return output::print_all(format, &converted);
+    }
+
+    for Converted { stream, events } in &converted {
+        println!("Converted {} ({} events)", stream, events);
+    }
+
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct Converted {
+    stream: String,
+    events: usize,
+}
@@ -0,0 +1,44 @@
+use std::path::PathBuf;
+
+use serde::Serialize;
+use skuff::Error;
+use skuff::io;
+use skuff::output;
+use skuff::util::*;
+
+/// Decrypt the streams and configs of an encrypted storage
+#[derive(clap::Args)]
+pub struct Args {
+    /// Derive the key from this file instead of a passphrase, including any trailing newline
+    #[arg(long, value_parser = parse_existing_file)]
+    keyfile: Option<PathBuf>,
+
+    #[arg(long)]
+    storage: Option<PathBuf>,
+}
+
+pub fn command(args: Args, format: output::Format) -> Result<(), Error> {
+    let layout = layout(args.storage)?;
+
+    if !layout.encryption_path().exists() {
+        return Err(Error::DecryptionFailed(
+            "the storage is not encrypted".to_string(),
+        ));
+    }
+
+    let secret = secret(args.keyfile, false)?;
+    let cipher = io::unlock(&layout, &secret)?;
+    let files = io::decrypt(&layout, &cipher)?;
+
+    match format {
+        output::Format::Text => println!("Decrypted {} files", files),
+        _ => output::print(&Decrypted { files })?,
+    }
+
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct Decrypted {
+    files: usize,
+}
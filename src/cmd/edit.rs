@@ -0,0 +1,193 @@
+use std::io::Write as _;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+
+use chrono::Duration;
+use chrono::NaiveDate;
+use chrono::NaiveTime;
+use serde::Serialize;
+use skuff::DateExpr;
+use skuff::Dst;
+use skuff::Error;
+use skuff::Event;
+use skuff::Id;
+use skuff::State;
+use skuff::Zone;
+use skuff::editor;
+use skuff::io::text;
+use skuff::log;
+use skuff::output;
+use skuff::util::*;
+
+/// Correct a day in $VISUAL or $EDITOR, one line per entity
+///
+/// The changes are shown before anything is appended to the stream.
+#[derive(clap::Args)]
+pub struct Args {
+    /// The day to edit [default: today]
+    #[arg(short, long, value_parser = parse_date, allow_hyphen_values = true)]
+    date: Option<DateExpr>,
+
+    /// Show and enter times in this timezone [default: system timezone]
+    #[arg(long, value_parser = parse_zone)]
+    tz: Option<Zone>,
+
+    /// Pick the earlier or later occurrence of a time repeated by a daylight saving change
+    #[arg(long, value_enum)]
+    dst: Option<Dst>,
+
+    /// Append the changes without asking
+    #[arg(short, long)]
+    yes: bool,
+
+    #[arg(long)]
+    stream: Option<String>,
+
+    #[arg(long)]
+    storage: Option<PathBuf>,
+}
+
+pub fn command(args: Args, format: output::Format) -> Result<(), Error> {
+    let encrypted = layout(args.storage.clone())?.encryption_path().exists();
+    let storage = storage(args.storage)?;
+
+    let zone = args.tz.unwrap_or_else(Zone::local);
+    let date = date(args.date, &zone)?.unwrap_or_else(|| zone.now().date_naive());
+    let midnight =
+        |date: NaiveDate| zone.resolve(&date.and_time(NaiveTime::MIN), Some(Dst::Earlier));
+    let state = storage.state_between(
+        &args.stream,
+        midnight(date)?,
+        midnight(date + Duration::days(1))?,
+    )?;
+
+    // Only readable by the user, and removed when dropped
+    let file = tempfile::Builder::new()
+        .prefix("skuff-")
+        .suffix(".txt")
+        .tempfile()
+        .map_err(|err| Error::FailedToWriteFile(err.to_string()))?;
+    text::write(file.path(), &editor::render(&state, date, &zone))?;
+    open_editor(file.path())?;
+
+    let text = text::read(file.path())?;
+    let events = match editor::changes(&text, &state, date, &zone, args.dst) {
+        Ok(events) => events,
+        // A day of encrypted storage is not left behind in plain text
+        Err(Error::InvalidEdit(message)) if encrypted => {
+            return Err(Error::InvalidEdit(format!(
+                "{}, the edited day was not kept since the storage is encrypted",
+                message
+            )));
+        }
+        // Otherwise the edits are kept, so that they aren't lost to a typo
+        Err(Error::InvalidEdit(message)) => {
+            let (_, path) = file
+                .keep()
+                .map_err(|err| Error::FailedToWriteFile(err.to_string()))?;
+            return Err(Error::InvalidEdit(format!(
+                "{}, the edited day is kept in {}",
+                message,
+                path.display()
+            )));
+        }
+        Err(err) => return Err(err),
+    };
+    drop(file);
+
+    // The preview is for the user, scripts get the appended events
+    let say = |line: &str| match format {
+        output::Format::Text => println!("{}", line),
+        _ => eprintln!("{}", line),
+    };
+    let mut edited = Edited {
+        date,
+        appended: false,
+        events: events.iter().map(|event| event.id().clone()).collect(),
+    };
+
+    if events.is_empty() {
+        say("No changes");
+    } else {
+        say(&format!("Changes to {}:", date.format("%A %Y-%m-%d")));
+        for event in &events {
+            say(&format!("  {}", preview(event, &state, &zone)));
+        }
+
+        if args.yes || confirm(&format!("Append {} events? [y/N] ", events.len()))? {
+            storage.stream_extend(events, &args.stream)?;
+            edited.appended = true;
+        } else {
+            say("Nothing was changed");
+        }
+    }
+
+    if format != output::Format::Text {
+        output::print(&edited)?;
+    }
+
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct Edited {
+    date: NaiveDate,
+    /// Whether the events were appended, i.e. the changes were confirmed
+    appended: bool,
+    events: Vec<Id>,
+}
+
+fn open_editor(path: &Path) -> Result<(), Error> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+
+    // Through the shell, so that editors can be given with arguments, e.g. "code --wait"
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$1\"", editor))
+        .arg("sh")
+        .arg(path)
+        .status()
+        .map_err(|err| Error::FailedToOpenFile(format!("{}: {}", editor, err)))?;
+
+    if !status.success() {
+        return Err(Error::FailedToOpenFile(format!(
+            "{} exited with {}",
+            editor, status
+        )));
+    }
+
+    Ok(())
+}
+
+fn preview(event: &Event, state: &State, zone: &Zone) -> String {
+    match event {
+        Event::Create { entity, .. } => format!("add    {}", log::describe(entity, Some(zone))),
+        Event::Edit { entity, .. } => format!("edit   {}", log::describe(entity, Some(zone))),
+        Event::Delete { entity_id, .. } => {
+            let entity = state.iter().find(|entity| entity.id() == entity_id);
+            match entity {
+                Some(entity) => format!("remove {}", log::describe(entity, Some(zone))),
+                None => format!("remove {}", entity_id),
+            }
+        }
+        _ => unreachable!("editing a day only adds, edits and removes entities"),
+    }
+}
+
+/// Ask on stderr, so that the question doesn't end up in json output
+fn confirm(prompt: &str) -> Result<bool, Error> {
+    eprint!("{}", prompt);
+    std::io::stderr()
+        .flush()
+        .map_err(|err| Error::FailedToWriteFile(err.to_string()))?;
+
+    let mut answer = String::new();
+    std::io::stdin()
+        .read_line(&mut answer)
+        .map_err(|err| Error::FailedToReadFile(err.to_string()))?;
+
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
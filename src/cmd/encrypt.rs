@@ -0,0 +1,47 @@
+use std::path::PathBuf;
+
+use serde::Serialize;
+use skuff::Error;
+use skuff::io;
+use skuff::output;
+use skuff::util::*;
+
+/// Encrypt the streams and configs of the storage
+///
+/// The key is derived from the passphrase in SKUFF_PASSPHRASE, the key file in SKUFF_KEYFILE or
+/// ~/.skuff/key, or a passphrase asked for. Every later command needs the same key.
+#[derive(clap::Args)]
+pub struct Args {
+    /// Derive the key from this file instead of a passphrase, including any trailing newline
+    #[arg(long, value_parser = parse_existing_file)]
+    keyfile: Option<PathBuf>,
+
+    #[arg(long)]
+    storage: Option<PathBuf>,
+}
+
+pub fn command(args: Args, format: output::Format) -> Result<(), Error> {
+    let layout = layout(args.storage)?;
+
+    if io::Backend::detect(&layout) != io::Backend::Files {
+        return Err(Error::EncryptionFailed(
+            "only file storage can be encrypted, convert it with `skuff convert --to files`"
+                .to_string(),
+        ));
+    }
+
+    let secret = secret(args.keyfile, true)?;
+    let files = io::encrypt(&layout, &secret)?;
+
+    match format {
+        output::Format::Text => println!("Encrypted {} files", files),
+        _ => output::print(&Encrypted { files })?,
+    }
+
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct Encrypted {
+    files: usize,
+}
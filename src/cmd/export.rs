@@ -0,0 +1,172 @@
+use std::path::PathBuf;
+
+use chrono::Utc;
+use clap::ArgGroup;
+use serde::Serialize;
+use skuff::DateExpr;
+use skuff::Error;
+use skuff::Zone;
+use skuff::export;
+use skuff::export::Format;
+use skuff::io::text;
+use skuff::output;
+use skuff::sessions;
+use skuff::util::*;
+use skuff::within;
+use skuff::worked;
+
+/// Export sessions for use in other tools
+#[derive(clap::Args)]
+#[command(group(ArgGroup::new("kind").required(true).args(["layout", "profile"])))]
+pub struct Args {
+    /// The file format to export to
+    #[arg(long = "as", value_enum)]
+    layout: Option<Format>,
+
+    /// Export an hours-per-day grid laid out by this profile from the config
+    #[arg(long)]
+    profile: Option<String>,
+
+    /// First day to export
+    #[arg(long, value_parser = parse_date, allow_hyphen_values = true)]
+    from: Option<DateExpr>,
+
+    /// Last day to export
+    #[arg(long, value_parser = parse_date, allow_hyphen_values = true)]
+    to: Option<DateExpr>,
+
+    /// Write to this file instead of stdout
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Account for time not covered by an activity [default: stream name]
+    #[arg(long)]
+    account: Option<String>,
+
+    /// Include breaks as separate calendar events (ics only)
+    #[arg(long)]
+    breaks: bool,
+
+    /// Export recorded times as they are, ignoring the rounding rules in the config
+    #[arg(long)]
+    no_rounding: bool,
+
+    /// Only export time with this tag, may be repeated
+    #[arg(long, value_parser = parse_tag_filter)]
+    tag: Vec<String>,
+
+    /// Write times in this timezone instead of the one they were recorded in
+    #[arg(long, value_parser = parse_zone)]
+    tz: Option<Zone>,
+
+    #[arg(long)]
+    config_file: Option<PathBuf>,
+
+    #[arg(long)]
+    stream: Option<String>,
+
+    #[arg(long)]
+    storage: Option<PathBuf>,
+}
+
+pub fn command(args: Args, format: output::Format) -> Result<(), Error> {
+    let storage = storage(args.storage)?;
+
+    let zone = args.tz.unwrap_or_else(Zone::local);
+    let from = date(args.from, &zone)?;
+    let to = date(args.to, &zone)?;
+
+    let config = config(args.config_file, storage.as_ref(), &args.stream)?;
+    let profile = match args.profile {
+        Some(name) => {
+            let mut profile = config
+                .profiles
+                .and_then(|mut profiles| profiles.remove(&name))
+                .ok_or(Error::ProfileNotFound(name))?;
+            if args.no_rounding {
+                profile.rounding = None;
+            }
+            Some(profile)
+        }
+        None => None,
+    };
+
+    // A profile that rounds its cells does so instead of the rounding rules, not on top of them
+    let rounding = config.rounding.filter(|_| !args.no_rounding).filter(|_| {
+        profile
+            .as_ref()
+            .is_none_or(|profile| profile.rounding.is_none())
+    });
+    let rounds = rounding.is_some() || profile.as_ref().is_some_and(|p| p.rounding.is_some());
+
+    let recorded = storage
+        .state(&args.stream)?
+        .tagged(&args.tag.into_iter().collect());
+    let rounded = rounding.map(|rounding| recorded.rounded(&rounding, args.tz.as_ref()));
+
+    let state = rounded.as_ref().unwrap_or(&recorded);
+    let sessions = within(sessions(state), from, to, args.tz.as_ref());
+
+    let name = storage.coalease_stream_name(&args.stream)?;
+    let now = Utc::now();
+
+    let (contents, reported) = match (args.layout, profile) {
+        (Some(Format::Timeclock), _) => {
+            let account = args.account.unwrap_or(name);
+            let contents = export::timeclock(&sessions, &account, args.tz.as_ref());
+            (contents, worked(&sessions, now))
+        }
+        (Some(Format::Ics), _) => (
+            export::ics(&sessions, &name, args.breaks, now),
+            worked(&sessions, now),
+        ),
+        (None, Some(profile)) => {
+            let dates = sessions
+                .iter()
+                .map(|session| session.date(args.tz.as_ref()));
+            let from = from
+                .or(dates.clone().min())
+                .unwrap_or(zone.now().date_naive());
+            let to = to.or(dates.max()).unwrap_or(from);
+
+            let grid = export::grid(&sessions, &profile, from, to, args.tz.as_ref())?;
+            (grid.text, grid.total)
+        }
+        (None, None) => unreachable!("clap requires --as or --profile"),
+    };
+
+    let before = worked(
+        &within(skuff::sessions(&recorded), from, to, args.tz.as_ref()),
+        now,
+    );
+    if rounds {
+        eprintln!("{}", rounding_note(before, reported));
+    }
+
+    if let Some(path) = &args.output {
+        text::write(path, &contents)?;
+    }
+
+    match format {
+        output::Format::Text if args.output.is_none() => print!("{}", contents),
+        output::Format::Text => {}
+        _ => output::print(&Exported {
+            contents: args.output.is_none().then_some(contents),
+            output: args.output,
+            recorded: before.num_seconds(),
+            reported: reported.num_seconds(),
+        })?,
+    }
+
+    Ok(())
+}
+
+/// What is exported in json output. The contents are left out when they were written to a file.
+#[derive(Serialize)]
+struct Exported {
+    output: Option<PathBuf>,
+    contents: Option<String>,
+    /// Seconds recorded and reported, which differ when rounded
+    recorded: i64,
+    reported: i64,
+}
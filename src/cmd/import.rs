@@ -0,0 +1,135 @@
+use std::path::PathBuf;
+
+use serde::Serialize;
+use skuff::Dst;
+use skuff::Entity;
+use skuff::Error;
+use skuff::Event;
+use skuff::Id;
+use skuff::Zone;
+use skuff::import::Import;
+use skuff::import::Source;
+use skuff::io::text;
+use skuff::log;
+use skuff::output;
+use skuff::util::*;
+
+/// Import history from another time tracker
+#[derive(clap::Args)]
+pub struct Args {
+    #[arg(long, value_enum)]
+    from: Source,
+
+    #[arg(value_parser = parse_existing_file)]
+    file: PathBuf,
+
+    /// Show what would be imported without changing the stream
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Timezone the imported entries were recorded in [default: system timezone]
+    #[arg(long, value_parser = parse_zone)]
+    tz: Option<Zone>,
+
+    /// Pick the earlier or later occurrence of a time repeated by a daylight saving change
+    #[arg(long, value_enum)]
+    dst: Option<Dst>,
+
+    #[arg(long)]
+    stream: Option<String>,
+
+    #[arg(long)]
+    storage: Option<PathBuf>,
+}
+
+pub fn command(args: Args, format: output::Format) -> Result<(), Error> {
+    let storage = storage(args.storage)?;
+
+    let tz = args.tz.unwrap_or_else(Zone::local);
+    let contents = text::read(&args.file)?;
+    let intervals = skuff::import::parse(args.from, &contents, &tz, args.dst)?;
+
+    let state = storage.state(&args.stream)?;
+    let import = Import::new(&intervals, &state, &tz)?;
+
+    let mut summary = Summary {
+        dry_run: args.dry_run,
+        intervals: intervals.len(),
+        logins: 0,
+        logouts: 0,
+        activities: 0,
+        duplicates: import.duplicates,
+        open: import.open,
+        events: import.events.iter().map(|e| e.id().clone()).collect(),
+    };
+
+    for event in &import.events {
+        if let Event::Create { entity, .. } = event {
+            match entity {
+                Entity::Login { .. } => summary.logins += 1,
+                Entity::Logout { .. } => summary.logouts += 1,
+                _ => summary.activities += 1,
+            }
+
+            if args.dry_run && format == output::Format::Text {
+                println!("{}", log::describe(entity, Some(&tz)));
+            }
+        }
+    }
+
+    if !args.dry_run {
+        storage.stream_extend(import.events, &args.stream)?;
+    }
+
+    match format {
+        output::Format::Text => summary.print(),
+        _ => output::print(&summary)?,
+    }
+
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct Summary {
+    dry_run: bool,
+    intervals: usize,
+    logins: usize,
+    logouts: usize,
+    activities: usize,
+    duplicates: usize,
+    open: usize,
+    events: Vec<Id>,
+}
+
+impl Summary {
+    fn print(&self) {
+        let verb = if self.dry_run {
+            "Would import"
+        } else {
+            "Imported"
+        };
+        println!(
+            "{} {} entities from {} intervals: {} logins, {} logouts, {} activities",
+            verb,
+            self.events.len(),
+            self.intervals,
+            self.logins,
+            self.logouts,
+            self.activities
+        );
+
+        if self.duplicates > 0 {
+            println!(
+                "Skipped {} intervals already in the stream",
+                self.duplicates
+            );
+        }
+
+        if self.open > 0 {
+            println!(
+                "{} intervals are still running and have no logout",
+                self.open
+            );
+        }
+    }
+}
@@ -1,27 +1,47 @@
 use std::path::PathBuf;
-
-use chrono::NaiveDate;
-use chrono::NaiveTime;
+use std::str::FromStr as _;
 
 use chrono::Utc;
+use skuff::DateExpr;
+use skuff::Dst;
 use skuff::Entity;
 use skuff::Error;
 use skuff::Event;
 use skuff::Id;
+use skuff::TimeExpr;
+use skuff::Zone;
+use skuff::output;
 use skuff::util::*;
 
 /// Register an "in" event
 #[derive(clap::Args)]
 pub struct Args {
-    #[arg(short, long, value_parser = parse_time, default_value_t = clock())]
-    time: NaiveTime,
+    #[arg(short, long, value_parser = parse_time, allow_hyphen_values = true)]
+    time: Option<TimeExpr>,
+
+    #[arg(short, long, value_parser = parse_date, allow_hyphen_values = true)]
+    date: Option<DateExpr>,
+
+    /// Timezone of --date and --time, e.g. Europe/Oslo or +02:00 [default: system timezone]
+    #[arg(long, value_parser = parse_zone)]
+    tz: Option<Zone>,
 
-    #[arg(short, long, value_parser = parse_date, default_value_t = today())]
-    date: NaiveDate,
+    /// Pick the earlier or later occurrence of a time repeated by a daylight saving change
+    #[arg(long, value_enum)]
+    dst: Option<Dst>,
 
+    /// Change this entry instead of adding one, keeping whatever is not given
     #[arg(short, long, value_parser = Id::from_str)]
     edit: Option<Id>,
 
+    /// Explain the entry, e.g. "left early for the dentist"
+    #[arg(short, long)]
+    note: Option<String>,
+
+    /// Tags for the session, e.g. +acme +billable
+    #[arg(value_parser = parse_tag)]
+    tags: Vec<String>,
+
     #[arg(long)]
     config_file: Option<PathBuf>,
 
@@ -32,29 +52,54 @@ pub struct Args {
     storage: Option<PathBuf>,
 }
 
-pub fn command(args: Args) -> Result<(), Error> {
+pub fn command(args: Args, format: output::Format) -> Result<(), Error> {
     let storage = storage(args.storage)?;
 
     let event = match args.edit {
-        Some(id) => Event::Edit {
-            id: Id::new(),
-            created_at: Utc::now(),
-            entity: Entity::Login {
-                id,
-                timestamp: from_naive(&args.date, &args.time),
-            },
-        },
-        None => Event::Create {
-            id: Id::new(),
-            created_at: Utc::now(),
-            entity: Entity::Login {
+        Some(id) => {
+            let entity = storage
+                .state(&args.stream)?
+                .get(&id)
+                .cloned()
+                .ok_or_else(|| Error::EntityDoesNotExist(id.clone()))?;
+            if !matches!(entity, Entity::Login { .. }) {
+                return Err(Error::InvalidEdit(format!("{} is not a login", id)));
+            }
+
+            Event::Edit {
+                id: Id::new(),
+                created_at: Utc::now(),
+                entity: edit(
+                    entity, args.date, args.time, args.tz, args.dst, args.note, args.tags,
+                )?,
+            }
+        }
+        None => {
+            let tz = args.tz.unwrap_or_else(Zone::local);
+            Event::Create {
                 id: Id::new(),
-                timestamp: from_naive(&args.date, &args.time),
-            },
-        },
+                created_at: Utc::now(),
+                entity: Entity::Login {
+                    id: Id::new(),
+                    timestamp: timestamp(args.date, args.time, &tz, args.dst)?,
+                    tz: Some(tz),
+                    note: args.note,
+                    tags: args.tags.iter().cloned().collect(),
+                },
+            }
+        }
+    };
+
+    let written = output::Written {
+        event_id: event.id().clone(),
+        entity_id: event.entity_id().unwrap().clone(),
     };
 
     storage.stream_append(event, &args.stream)?;
 
+    if format != output::Format::Text {
+        output::print(&written)?;
+    }
+
     Ok(())
 }
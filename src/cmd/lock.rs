@@ -0,0 +1,73 @@
+use std::path::PathBuf;
+
+use chrono::Duration;
+use chrono::NaiveDate;
+use chrono::NaiveTime;
+use chrono::Utc;
+use serde::Serialize;
+use skuff::DateExpr;
+use skuff::Dst;
+use skuff::Error;
+use skuff::Event;
+use skuff::Id;
+use skuff::Zone;
+use skuff::output;
+use skuff::util::*;
+
+/// Lock the record up to and including a day, e.g. once its hours were submitted
+///
+/// Entities timestamped in a locked period can no longer be added, edited or removed, until the
+/// period is unlocked again.
+#[derive(clap::Args)]
+pub struct Args {
+    /// Last day to lock
+    #[arg(long, value_parser = parse_date, allow_hyphen_values = true)]
+    until: DateExpr,
+
+    /// The timezone the day is in
+    #[arg(long, value_parser = parse_zone)]
+    tz: Option<Zone>,
+
+    #[arg(long)]
+    stream: Option<String>,
+
+    #[arg(long)]
+    storage: Option<PathBuf>,
+}
+
+pub fn command(args: Args, format: output::Format) -> Result<(), Error> {
+    let storage = storage(args.storage)?;
+
+    let zone = args.tz.unwrap_or_else(Zone::local);
+    let last = args.until.resolve(zone.now().date_naive())?;
+    let until = zone.resolve(
+        &(last + Duration::days(1)).and_time(NaiveTime::MIN),
+        Some(Dst::Earlier),
+    )?;
+
+    let event = Event::Lock {
+        id: Id::new(),
+        created_at: Utc::now(),
+        until,
+    };
+    let locked = Locked {
+        event_id: event.id().clone(),
+        until: last,
+    };
+
+    storage.stream_append(event, &args.stream)?;
+
+    match format {
+        output::Format::Text => println!("Locked through {}", last),
+        _ => output::print(&locked)?,
+    }
+
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct Locked {
+    event_id: Id,
+    /// The last locked day
+    until: NaiveDate,
+}
@@ -1,29 +1,81 @@
 use std::path::PathBuf;
 
-use pager::Pager;
+use chrono::DateTime;
+use chrono::Duration;
+use chrono::NaiveDate;
+use chrono::NaiveTime;
+use chrono::Utc;
+use skuff::DateExpr;
+use skuff::Dst;
 use skuff::Error;
+use skuff::Zone;
 use skuff::log;
-use skuff::replay;
+use skuff::output;
 use skuff::util::*;
 
-/// Create a new skuff-stream
+/// Show the recorded entities
 #[derive(clap::Args)]
 pub struct Args {
+    #[arg(long)]
+    config_file: Option<PathBuf>,
+
     #[arg(long)]
     storage: Option<PathBuf>,
 
     #[arg(value_parser = validate_stream)]
     stream: Option<String>,
+
+    /// Only show entities with this tag, may be repeated
+    #[arg(long, value_parser = parse_tag_filter)]
+    tag: Vec<String>,
+
+    /// First day to show
+    #[arg(long, value_parser = parse_date, allow_hyphen_values = true)]
+    from: Option<DateExpr>,
+
+    /// Last day to show
+    #[arg(long, value_parser = parse_date, allow_hyphen_values = true)]
+    to: Option<DateExpr>,
+
+    /// Show all timestamps in this timezone instead of the one they were recorded in
+    #[arg(long, value_parser = parse_zone)]
+    tz: Option<Zone>,
 }
 
-pub fn command(args: Args) -> Result<(), Error> {
+pub fn command(
+    args: Args,
+    format: output::Format,
+    terminal: output::Terminal,
+) -> Result<(), Error> {
     let storage = storage(args.storage)?;
 
-    let stream = storage.stream(&args.stream)?;
-    let state = replay(&stream);
+    let zone = args.tz.unwrap_or_else(Zone::local);
+    let from = date(args.from, &zone)?;
+    let to = date(args.to, &zone)?;
+
+    let state = match (from, to) {
+        (None, None) => storage.state(&args.stream)?,
+        (from, to) => {
+            let midnight =
+                |date: NaiveDate| zone.resolve(&date.and_time(NaiveTime::MIN), Some(Dst::Earlier));
+            let from = from.map(midnight).transpose()?;
+            let to = to.map(|to| midnight(to + Duration::days(1))).transpose()?;
+
+            storage.state_between(
+                &args.stream,
+                from.unwrap_or(DateTime::<Utc>::MIN_UTC),
+                to.unwrap_or(DateTime::<Utc>::MAX_UTC),
+            )?
+        }
+    }
+    .with_tags(&args.tag.into_iter().collect());
+
+    if format != output::Format::Text {
+        return output::print_all(format, state.iter());
+    }
 
-    Pager::with_pager("more -R").setup();
-    log::print(&state);
+    let config = config(args.config_file, storage.as_ref(), &args.stream)?;
+    terminal.setup(&config);
 
-    Ok(())
+    log::print(&state, args.tz.as_ref())
 }
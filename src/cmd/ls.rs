@@ -1,20 +1,33 @@
 use std::path::PathBuf;
 
 use skuff::Error;
+use skuff::output;
 use skuff::util::*;
 
-/// Create a new skuff-stream
+/// List skuff-streams
 #[derive(clap::Args)]
 pub struct Args {
     #[arg(long)]
     storage: Option<PathBuf>,
 }
 
-pub fn command(args: Args) -> Result<(), Error> {
+pub fn command(args: Args, format: output::Format) -> Result<(), Error> {
     let storage = storage(args.storage)?;
     let streams = storage.streams()?;
     let current = storage.current_stream()?;
 
+    if format != output::Format::Text {
+        let listed: Vec<output::Listed> = streams
+            .iter()
+            .map(|stream| output::Listed {
+                name: stream,
+                current: Some(stream) == current.as_ref(),
+            })
+            .collect();
+
+        return output::print_all(format, &listed);
+    }
+
     for stream in &streams {
         let prefix = if Some(stream) == current.as_ref() {
             "* "
@@ -0,0 +1,126 @@
+use std::path::PathBuf;
+
+use serde::Serialize;
+use skuff::Conflict;
+use skuff::Error;
+use skuff::Stream;
+use skuff::Zone;
+use skuff::io;
+use skuff::merge;
+use skuff::output;
+use skuff::util::*;
+
+/// Merge a diverged copy of a stream into it, e.g. a conflict copy left by a file sync tool
+///
+/// Entities changed differently in both copies are listed, and nothing is written unless
+/// --keep-latest says to keep the latest change to each of them.
+#[derive(clap::Args)]
+pub struct Args {
+    /// The other copy of the stream
+    #[arg(value_parser = parse_existing_file)]
+    file: PathBuf,
+
+    /// Resolve conflicts by keeping the latest change to each entity
+    #[arg(long)]
+    keep_latest: bool,
+
+    /// Show what would be merged without changing the stream
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Show times in this timezone instead of the one they were recorded in
+    #[arg(long, value_parser = parse_zone)]
+    tz: Option<Zone>,
+
+    #[arg(long)]
+    stream: Option<String>,
+
+    #[arg(long)]
+    storage: Option<PathBuf>,
+}
+
+pub fn command(args: Args, format: output::Format) -> Result<(), Error> {
+    let layout = layout(args.storage.clone())?;
+    let storage = storage(args.storage)?;
+
+    // A copy of an encrypted stream is encrypted as well
+    let buf = std::fs::read(&args.file).map_err(|err| Error::FailedToReadFile(err.to_string()))?;
+    let buf = io::FileStorage::open(layout)?.decode(&buf)?;
+
+    let ours = storage.stream(&args.stream)?;
+    let theirs = Stream::from_buffer(&buf)?;
+    let merge = merge(&ours, &theirs)?;
+
+    // Conflicts that stop the merge are reported on stderr in json output
+    let text = format == output::Format::Text;
+    if !merge.conflicts.is_empty() && (text || !args.keep_latest) {
+        let say = |line: &str| match text {
+            true => println!("{}", line),
+            false => eprintln!("{}", line),
+        };
+        say("Changed differently in both copies:");
+        for conflict in &merge.conflicts {
+            say(&format!("  {}", conflict.describe(args.tz.as_ref())));
+        }
+
+        if !args.keep_latest {
+            return Err(Error::MergeConflict(format!(
+                "{} conflicting entities, fix them in one copy or pass --keep-latest",
+                merge.conflicts.len()
+            )));
+        }
+    }
+
+    let mut merged = Merged {
+        dry_run: args.dry_run,
+        added: merge.added,
+        conflicts: &merge.conflicts,
+        backup: None,
+        head: merge.stream.head(),
+        rewritten: false,
+    };
+
+    if !args.dry_run && merge.added > 0 {
+        merged.backup = Some(storage.stream_replace(&merge.stream, &args.stream)?);
+        merged.rewritten = merge.stream.rewrites(&ours) || merge.stream.rewrites(&theirs);
+    }
+
+    if !text {
+        return output::print(&merged);
+    }
+
+    let verb = if args.dry_run {
+        "Would merge"
+    } else {
+        "Merged"
+    };
+    println!(
+        "{} {} events from {}",
+        verb,
+        merge.added,
+        args.file.display()
+    );
+
+    if let Some(backup) = &merged.backup {
+        println!("The previous history is kept in {}", backup.display());
+    }
+    if merged.rewritten {
+        println!("{}", rewritten(merged.head));
+    }
+
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct Merged<'a> {
+    dry_run: bool,
+    /// Events that were only in the other copy
+    added: usize,
+    /// Entities changed differently in both copies, of which the latest change was kept
+    conflicts: &'a [Conflict],
+    /// Where the previous history is kept, if the stream was replaced
+    backup: Option<PathBuf>,
+    head: &'a str,
+    /// Whether hashes noted down before no longer verify the stream
+    rewritten: bool,
+}
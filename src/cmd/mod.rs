@@ -1,7 +1,25 @@
+pub mod activity;
+pub mod backup;
+pub mod compact;
+pub mod convert;
+pub mod decrypt;
+pub mod edit;
+pub mod encrypt;
+pub mod export;
+pub mod import;
 pub mod r#in;
+pub mod lock;
 pub mod log;
 pub mod ls;
+pub mod merge;
 pub mod new;
 pub mod out;
+pub mod report;
 pub mod rm;
+pub mod status;
+pub mod submit;
 pub mod switch;
+pub mod sync;
+pub mod unlock;
+pub mod verify;
+pub mod week;
@@ -1,6 +1,7 @@
 use std::path::PathBuf;
 
 use skuff::Error;
+use skuff::output;
 use skuff::util::*;
 
 /// Create a new skuff-stream
@@ -16,14 +17,23 @@ pub struct Args {
     storage: Option<PathBuf>,
 }
 
-pub fn command(args: Args) -> Result<(), Error> {
+pub fn command(args: Args, format: output::Format) -> Result<(), Error> {
     let storage = storage(args.storage)?;
 
-    let _ = storage.stream_create(&args.stream)?;
-    println!("Created stream: {}", args.stream);
-
+    storage.stream_create(&args.stream)?;
     if args.set_current {
         storage.set_current_stream(&args.stream)?;
+    }
+
+    if format != output::Format::Text {
+        return output::print(&output::Listed {
+            name: &args.stream,
+            current: args.set_current,
+        });
+    }
+
+    println!("Created stream: {}", args.stream);
+    if args.set_current {
         println!("Current stream is: {}", args.stream);
     }
 
@@ -0,0 +1,138 @@
+use std::path::PathBuf;
+
+use chrono::Datelike as _;
+use chrono::Duration;
+use chrono::Months;
+use chrono::NaiveDate;
+use chrono::Utc;
+use skuff::Error;
+use skuff::State;
+use skuff::Zone;
+use skuff::io::text;
+use skuff::output;
+use skuff::report;
+use skuff::report::Report;
+use skuff::sessions;
+use skuff::util::*;
+use skuff::within;
+use skuff::worked;
+
+/// Summarize a month for sign-off
+#[derive(clap::Args)]
+pub struct Args {
+    /// The month to report, as YYYY-MM [default: the current month]
+    #[arg(long, value_parser = parse_month)]
+    month: Option<NaiveDate>,
+
+    /// How to lay out the report
+    #[arg(long = "as", value_enum, default_value_t)]
+    layout: report::Format,
+
+    /// Write to this file instead of stdout
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Report recorded times as they are, ignoring the rounding rules in the config
+    #[arg(long)]
+    no_rounding: bool,
+
+    /// Only count time with this tag, may be repeated
+    #[arg(long, value_parser = parse_tag_filter)]
+    tag: Vec<String>,
+
+    /// Count days in this timezone instead of the one they were recorded in
+    #[arg(long, value_parser = parse_zone)]
+    tz: Option<Zone>,
+
+    #[arg(long)]
+    config_file: Option<PathBuf>,
+
+    /// Include this stream, may be repeated [default: the current stream]
+    #[arg(long, value_parser = validate_stream)]
+    stream: Vec<String>,
+
+    /// Include every stream
+    #[arg(long, conflicts_with = "stream")]
+    all: bool,
+
+    #[arg(long)]
+    storage: Option<PathBuf>,
+}
+
+pub fn command(
+    args: Args,
+    format: output::Format,
+    terminal: output::Terminal,
+) -> Result<(), Error> {
+    let storage = storage(args.storage)?;
+
+    let month = args.month.unwrap_or_else(|| {
+        let today = args.tz.unwrap_or_else(Zone::local).now().date_naive();
+        today.with_day(1).unwrap()
+    });
+
+    // Each stream is rounded by the rules in its own config
+    let replayed =
+        replay_streams_with_config(args.config_file, storage.as_ref(), &args.stream, args.all)?;
+    let tags = args.tag.into_iter().collect();
+    let tagged: Vec<(String, State)> = replayed
+        .iter()
+        .map(|(name, recorded, _)| (name.clone(), recorded.tagged(&tags)))
+        .collect();
+    let streams: Vec<(String, State)> = tagged
+        .iter()
+        .zip(&replayed)
+        .map(|((name, tagged), (_, _, config))| {
+            match config.rounding.filter(|_| !args.no_rounding) {
+                Some(rounding) => (name.clone(), tagged.rounded(&rounding, args.tz.as_ref())),
+                None => (name.clone(), tagged.clone()),
+            }
+        })
+        .collect();
+    let rounds = !args.no_rounding
+        && replayed
+            .iter()
+            .any(|(_, _, config)| config.rounding.is_some());
+    let config = replayed
+        .first()
+        .map(|(_, _, config)| config)
+        .ok_or(Error::NoStreamSet)?;
+
+    let mut recorded = vec![];
+    for (name, _) in &streams {
+        recorded.push((name.clone(), storage.stream(&Some(name.clone()))?));
+    }
+
+    let norm = shared_norm(&replayed)?;
+    let now = Utc::now();
+    let report = Report::new(&streams, &recorded, month, &norm, args.tz.as_ref(), now);
+
+    if rounds {
+        let last = month + Months::new(1) - Duration::days(1);
+        let recorded = tagged
+            .iter()
+            .map(|(_, state)| {
+                let sessions = within(sessions(state), Some(month), Some(last), args.tz.as_ref());
+                worked(&sessions, now)
+            })
+            .sum();
+
+        eprintln!("{}", rounding_note(recorded, report.worked()));
+    }
+    let contents = match format {
+        output::Format::Text => report.render(args.layout),
+        output::Format::Json => output::to_string(&report.document())? + "\n",
+        output::Format::Ndjson => output::to_lines(&report.days)?,
+    };
+
+    match args.output {
+        Some(path) => text::write(&path, &contents)?,
+        None if format != output::Format::Text => print!("{}", contents),
+        None => {
+            terminal.setup(config);
+            print!("{}", contents);
+        }
+    }
+
+    Ok(())
+}
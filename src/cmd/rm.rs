@@ -1,9 +1,11 @@
 use std::path::PathBuf;
+use std::str::FromStr as _;
 
 use chrono::Utc;
 use skuff::Error;
 use skuff::Event;
 use skuff::Id;
+use skuff::output;
 use skuff::util::*;
 
 /// Remove an event
@@ -22,7 +24,7 @@ pub struct Args {
     storage: Option<PathBuf>,
 }
 
-pub fn command(args: Args) -> Result<(), Error> {
+pub fn command(args: Args, format: output::Format) -> Result<(), Error> {
     let storage = storage(args.storage)?;
 
     let event = Event::Delete {
@@ -31,7 +33,16 @@ pub fn command(args: Args) -> Result<(), Error> {
         entity_id: args.id,
     };
 
+    let written = output::Written {
+        event_id: event.id().clone(),
+        entity_id: event.entity_id().unwrap().clone(),
+    };
+
     storage.stream_append(event, &args.stream)?;
 
+    if format != output::Format::Text {
+        output::print(&written)?;
+    }
+
     Ok(())
 }
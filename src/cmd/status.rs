@@ -0,0 +1,132 @@
+use std::path::PathBuf;
+
+use chrono::DateTime;
+use chrono::Datelike;
+use chrono::Duration;
+use chrono::Utc;
+use colored::Colorize as _;
+use serde::Serialize;
+use skuff::Error;
+use skuff::Id;
+use skuff::Zone;
+use skuff::output;
+use skuff::sessions;
+use skuff::submissions;
+use skuff::util::*;
+use skuff::within;
+use skuff::worked;
+
+/// Show whether you are clocked in and how much you have worked
+#[derive(clap::Args)]
+pub struct Args {
+    /// Count days in this timezone instead of the one they were recorded in
+    #[arg(long, value_parser = parse_zone)]
+    tz: Option<Zone>,
+
+    #[arg(long)]
+    stream: Option<String>,
+
+    #[arg(long)]
+    storage: Option<PathBuf>,
+}
+
+#[derive(Serialize)]
+struct Status {
+    stream: String,
+    /// Start of the running session, if clocked in
+    since: Option<DateTime<Utc>>,
+    /// Seconds worked today
+    today: i64,
+    /// Seconds worked this week, from Monday
+    week: i64,
+    /// Submitted periods that changed since they were submitted
+    changed: Vec<Changed>,
+}
+
+#[derive(Serialize)]
+struct Changed {
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    submitted_at: DateTime<Utc>,
+    /// Seconds worked when the period was submitted
+    submitted: i64,
+    /// Seconds worked now
+    worked: i64,
+    /// The events that changed the period
+    events: Vec<Id>,
+}
+
+pub fn command(
+    args: Args,
+    format: output::Format,
+    terminal: output::Terminal,
+) -> Result<(), Error> {
+    let storage = storage(args.storage)?;
+
+    let zone = args.tz.unwrap_or_else(Zone::local);
+    let now = Utc::now();
+    let today = zone.localize(&now).date_naive();
+    let monday = today - Duration::days(today.weekday().num_days_from_monday().into());
+
+    let state = storage.state(&args.stream)?;
+    let since = sessions(&state)
+        .last()
+        .filter(|session| session.end().is_none())
+        .map(|session| session.start());
+
+    let tz = args.tz.as_ref();
+    let changed: Vec<_> = submissions(&storage.stream(&args.stream)?, now)
+        .into_iter()
+        .filter(|submission| submission.is_changed())
+        .collect();
+
+    let status = Status {
+        stream: storage.coalease_stream_name(&args.stream)?,
+        since,
+        today: worked(&within(sessions(&state), Some(today), Some(today), tz), now).num_seconds(),
+        week: worked(
+            &within(sessions(&state), Some(monday), Some(today), tz),
+            now,
+        )
+        .num_seconds(),
+        changed: changed
+            .iter()
+            .map(|submission| Changed {
+                from: submission.from,
+                to: submission.to,
+                submitted_at: submission.submitted_at,
+                submitted: submission.submitted.num_seconds(),
+                worked: submission.worked.num_seconds(),
+                events: submission
+                    .changes
+                    .iter()
+                    .map(|change| change.event.id().clone())
+                    .collect(),
+            })
+            .collect(),
+    };
+
+    if format != output::Format::Text {
+        return output::print(&status);
+    }
+
+    terminal.colors(&config(None, storage.as_ref(), &args.stream)?);
+
+    println!("Stream: {}", status.stream);
+    match status.since {
+        Some(since) => println!("Clocked in since {}", zone.localize(&since).format("%H:%M")),
+        None => println!("Clocked out"),
+    }
+    println!("Today: {}", hhmm(Duration::seconds(status.today)));
+    println!("This week: {}", hhmm(Duration::seconds(status.week)));
+
+    for submission in &changed {
+        println!();
+        println!("{} {}", "Warning:".yellow(), submission.describe(tz));
+        for change in &submission.changes {
+            println!("  {}", change.describe(tz));
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,95 @@
+use std::path::PathBuf;
+
+use chrono::Datelike;
+use chrono::Duration;
+use chrono::NaiveDate;
+use chrono::NaiveTime;
+use chrono::Utc;
+use chrono::Weekday;
+use serde::Serialize;
+use skuff::Dst;
+use skuff::Error;
+use skuff::Event;
+use skuff::Id;
+use skuff::Zone;
+use skuff::output;
+use skuff::util::*;
+use skuff::worked_between;
+
+/// Mark a week as entered into the official system
+///
+/// The time worked is kept with the mark. `skuff status` and reports warn when the week changes
+/// afterwards. Submit the week again to accept the changes.
+#[derive(clap::Args)]
+pub struct Args {
+    /// ISO week number [default: the current week]
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..=53))]
+    iso_week: Option<u32>,
+
+    /// ISO week-numbering year [default: the current year]
+    #[arg(long)]
+    year: Option<i32>,
+
+    /// The timezone the week is in
+    #[arg(long, value_parser = parse_zone)]
+    tz: Option<Zone>,
+
+    #[arg(long)]
+    stream: Option<String>,
+
+    #[arg(long)]
+    storage: Option<PathBuf>,
+}
+
+pub fn command(args: Args, format: output::Format) -> Result<(), Error> {
+    let storage = storage(args.storage)?;
+
+    let zone = args.tz.unwrap_or_else(Zone::local);
+    let today = zone.now().date_naive();
+    let year = args.year.unwrap_or(today.iso_week().year());
+    let week = args.iso_week.unwrap_or(today.iso_week().week());
+    let monday = NaiveDate::from_isoywd_opt(year, week, Weekday::Mon)
+        .ok_or_else(|| Error::InvalidTimeExpression(format!("{} has no week {}", year, week)))?;
+
+    let midnight =
+        |date: NaiveDate| zone.resolve(&date.and_time(NaiveTime::MIN), Some(Dst::Earlier));
+    let from = midnight(monday)?;
+    let to = midnight(monday + Duration::days(7))?;
+
+    let now = Utc::now();
+    let worked = worked_between(&storage.state(&args.stream)?, from, to, now);
+
+    let event = Event::Submit {
+        id: Id::new(),
+        created_at: now,
+        from,
+        to,
+        worked,
+    };
+    let submitted = Submitted {
+        event_id: event.id().clone(),
+        year,
+        week,
+        worked: worked.num_seconds(),
+    };
+
+    storage.stream_append(event, &args.stream)?;
+
+    match format {
+        output::Format::Text => {
+            println!("Submitted week {}, {} with {}", week, year, hhmm(worked))
+        }
+        _ => output::print(&submitted)?,
+    }
+
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct Submitted {
+    event_id: Id,
+    year: i32,
+    week: u32,
+    /// Seconds worked in the week
+    worked: i64,
+}
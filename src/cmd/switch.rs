@@ -1,6 +1,7 @@
 use std::path::PathBuf;
 
 use skuff::Error;
+use skuff::output;
 use skuff::util::*;
 
 /// Switch skuff-stream
@@ -13,10 +14,17 @@ pub struct Args {
     storage: Option<PathBuf>,
 }
 
-pub fn command(args: Args) -> Result<(), Error> {
+pub fn command(args: Args, format: output::Format) -> Result<(), Error> {
     let storage = storage(args.storage)?;
     storage.set_current_stream(&args.stream)?;
 
+    if format != output::Format::Text {
+        return output::print(&output::Listed {
+            name: &args.stream,
+            current: true,
+        });
+    }
+
     println!("Switched to stream: {}", args.stream);
 
     Ok(())
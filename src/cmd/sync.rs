@@ -0,0 +1,63 @@
+use std::path::PathBuf;
+
+use skuff::Error;
+use skuff::io;
+use skuff::io::Synced;
+use skuff::output;
+use skuff::util::*;
+
+/// Sync the storage with a git remote, combining streams recorded on several machines
+///
+/// The storage is turned into a git repository on first use. From then on every change is
+/// committed.
+#[derive(clap::Args)]
+pub struct Args {
+    /// Path or URL of the bare repository to sync with, remembered for later syncs
+    #[arg(long)]
+    remote: Option<String>,
+
+    #[arg(long)]
+    storage: Option<PathBuf>,
+}
+
+pub fn command(args: Args, format: output::Format) -> Result<(), Error> {
+    let layout = layout(args.storage)?;
+
+    if io::Backend::detect(&layout) != io::Backend::Files {
+        return Err(Error::GitFailed(
+            "only file storage can be synced, convert it with `skuff convert --to files`"
+                .to_string(),
+        ));
+    }
+
+    let synced = io::sync(&layout, args.remote.as_deref())?;
+    if format != output::Format::Text {
+        return output::print(&synced);
+    }
+
+    match synced {
+        Synced::UpToDate => println!("Already up to date"),
+        Synced::Pushed => println!("Pushed local changes"),
+        Synced::FastForwarded => println!("Pulled remote changes"),
+        Synced::Merged {
+            streams,
+            conflicts,
+            rewritten: chains,
+        } => {
+            println!("Merged events in {}", streams.join(", "));
+
+            for (stream, head) in chains {
+                println!("{}: {}", stream, rewritten(&head));
+            }
+
+            if !conflicts.is_empty() {
+                println!("Changed differently on both sides, the latest change was kept:");
+                for (stream, conflict) in conflicts {
+                    println!("  {} {}", stream, conflict.describe(None));
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
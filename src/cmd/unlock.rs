@@ -0,0 +1,79 @@
+use std::path::PathBuf;
+
+use chrono::Duration;
+use chrono::NaiveDate;
+use chrono::NaiveTime;
+use chrono::Utc;
+use serde::Serialize;
+use skuff::DateExpr;
+use skuff::Dst;
+use skuff::Error;
+use skuff::Event;
+use skuff::Id;
+use skuff::Zone;
+use skuff::output;
+use skuff::util::*;
+
+/// Unlock a locked period, so that it can be changed again
+#[derive(clap::Args)]
+pub struct Args {
+    /// First day to unlock, days before it stay locked. Everything is unlocked without it.
+    #[arg(long, value_parser = parse_date, allow_hyphen_values = true)]
+    from: Option<DateExpr>,
+
+    /// The timezone the day is in
+    #[arg(long, value_parser = parse_zone)]
+    tz: Option<Zone>,
+
+    #[arg(long)]
+    stream: Option<String>,
+
+    #[arg(long)]
+    storage: Option<PathBuf>,
+}
+
+pub fn command(args: Args, format: output::Format) -> Result<(), Error> {
+    let storage = storage(args.storage)?;
+
+    let zone = args.tz.unwrap_or_else(Zone::local);
+    let from = date(args.from, &zone)?;
+    let until = from
+        .map(|from| zone.resolve(&from.and_time(NaiveTime::MIN), Some(Dst::Earlier)))
+        .transpose()?;
+
+    let event = Event::Unlock {
+        id: Id::new(),
+        created_at: Utc::now(),
+        until,
+    };
+    let event_id = event.id().clone();
+
+    storage.stream_append(event, &args.stream)?;
+
+    // Unlocking from a day after the lock leaves it as it was
+    let locked_through = storage
+        .stream(&args.stream)?
+        .locked_until()
+        .map(|until| zone.localize(&until).date_naive() - Duration::days(1));
+
+    match (format, locked_through) {
+        (output::Format::Text, Some(last)) => println!("Locked through {}", last),
+        (output::Format::Text, None) => println!("Unlocked"),
+        _ => output::print(&Unlocked {
+            event_id,
+            from,
+            locked_through,
+        })?,
+    }
+
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct Unlocked {
+    event_id: Id,
+    /// The first unlocked day, None if everything was unlocked
+    from: Option<NaiveDate>,
+    /// The last day that is still locked
+    locked_through: Option<NaiveDate>,
+}
@@ -0,0 +1,90 @@
+use std::path::PathBuf;
+
+use serde::Serialize;
+use skuff::Error;
+use skuff::output;
+use skuff::util::*;
+
+/// Check that no recorded event was altered or removed
+///
+/// Every event is chained to the one before it by a hash. The first event that doesn't match its
+/// hash is reported. The last hash covers the whole history: note it down, and a later
+/// `verify --hash` with it proves nothing recorded before it has changed.
+#[derive(clap::Args)]
+pub struct Args {
+    /// Check that the history covered by a hash noted down earlier is unchanged
+    #[arg(long)]
+    hash: Option<String>,
+
+    #[arg(long)]
+    stream: Option<String>,
+
+    #[arg(long)]
+    storage: Option<PathBuf>,
+}
+
+pub fn command(args: Args, format: output::Format) -> Result<(), Error> {
+    let storage = storage(args.storage)?;
+
+    // Loading the stream verifies it
+    let stream = storage.stream(&args.stream)?;
+
+    let unchained = stream
+        .links()
+        .iter()
+        .filter(|link| link.hash.is_none())
+        .count();
+
+    let covered = match &args.hash {
+        Some(hash) => Some(stream.covered_by(hash).ok_or_else(|| {
+            Error::BrokenChain(format!(
+                "{} is not the hash of any event, the history before it was changed",
+                hash
+            ))
+        })?),
+        None => None,
+    };
+
+    let verified = Verified {
+        events: stream.events().len(),
+        unchained,
+        head: stream.head(),
+        hash: args.hash.as_deref(),
+        covered,
+    };
+
+    match format {
+        output::Format::Text => verified.print(),
+        _ => output::print(&verified)?,
+    }
+
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct Verified<'a> {
+    events: usize,
+    /// Events recorded before chaining, only covered by later hashes
+    unchained: usize,
+    head: &'a str,
+    /// The hash given with --hash, and the number of events it covers
+    hash: Option<&'a str>,
+    covered: Option<usize>,
+}
+
+impl Verified<'_> {
+    fn print(&self) {
+        println!("Verified {} events", self.events);
+        if self.unchained > 0 {
+            println!(
+                "{} events were recorded before chaining and are only covered by later hashes",
+                self.unchained
+            );
+        }
+        println!("Last hash: {}", self.head);
+
+        if let (Some(hash), Some(events)) = (self.hash, self.covered) {
+            println!("The first {} events are unchanged since {}", events, hash);
+        }
+    }
+}
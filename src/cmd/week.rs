@@ -0,0 +1,130 @@
+use std::path::PathBuf;
+
+use chrono::Datelike;
+use chrono::Duration;
+use chrono::NaiveDate;
+use chrono::Utc;
+use chrono::Weekday;
+use skuff::Error;
+use skuff::State;
+use skuff::Week;
+use skuff::Zone;
+use skuff::output;
+use skuff::sessions;
+use skuff::util::*;
+use skuff::within;
+use skuff::worked;
+
+/// Show a week as a timesheet grid
+#[derive(clap::Args)]
+pub struct Args {
+    /// ISO week number [default: the current week]
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..=53))]
+    iso_week: Option<u32>,
+
+    /// ISO week-numbering year [default: the current year]
+    #[arg(long)]
+    year: Option<i32>,
+
+    /// Show recorded times as they are, ignoring the rounding rules in the config
+    #[arg(long)]
+    no_rounding: bool,
+
+    /// Only count time with this tag, may be repeated
+    #[arg(long, value_parser = parse_tag_filter)]
+    tag: Vec<String>,
+
+    /// Count days in this timezone instead of the one they were recorded in
+    #[arg(long, value_parser = parse_zone)]
+    tz: Option<Zone>,
+
+    #[arg(long)]
+    config_file: Option<PathBuf>,
+
+    /// Include this stream, may be repeated [default: the current stream]
+    #[arg(long, value_parser = validate_stream)]
+    stream: Vec<String>,
+
+    /// Include every stream
+    #[arg(long, conflicts_with = "stream")]
+    all: bool,
+
+    #[arg(long)]
+    storage: Option<PathBuf>,
+}
+
+pub fn command(
+    args: Args,
+    format: output::Format,
+    terminal: output::Terminal,
+) -> Result<(), Error> {
+    let storage = storage(args.storage)?;
+
+    let today = args.tz.unwrap_or_else(Zone::local).now().date_naive();
+    let year = args.year.unwrap_or(today.iso_week().year());
+    let week = args.iso_week.unwrap_or(today.iso_week().week());
+    let monday = NaiveDate::from_isoywd_opt(year, week, Weekday::Mon)
+        .ok_or_else(|| Error::InvalidTimeExpression(format!("{} has no week {}", year, week)))?;
+
+    // Each stream is rounded by the rules in its own config
+    let replayed =
+        replay_streams_with_config(args.config_file, storage.as_ref(), &args.stream, args.all)?;
+    let tags = args.tag.into_iter().collect();
+    let tagged: Vec<(String, State)> = replayed
+        .iter()
+        .map(|(name, recorded, _)| (name.clone(), recorded.tagged(&tags)))
+        .collect();
+    let streams: Vec<(String, State)> = tagged
+        .iter()
+        .zip(&replayed)
+        .map(|((name, tagged), (_, _, config))| {
+            match config.rounding.filter(|_| !args.no_rounding) {
+                Some(rounding) => (name.clone(), tagged.rounded(&rounding, args.tz.as_ref())),
+                None => (name.clone(), tagged.clone()),
+            }
+        })
+        .collect();
+    let rounds = !args.no_rounding
+        && replayed
+            .iter()
+            .any(|(_, _, config)| config.rounding.is_some());
+    let config = replayed
+        .first()
+        .map(|(_, _, config)| config)
+        .ok_or(Error::NoStreamSet)?;
+
+    let norm = shared_norm(&replayed)?;
+    let now = Utc::now();
+    let week = Week::new(&streams, monday, &norm, args.tz.as_ref(), now);
+
+    if rounds {
+        let sunday = monday + Duration::days(6);
+        let recorded = tagged
+            .iter()
+            .map(|(_, state)| {
+                let sessions = within(
+                    sessions(state),
+                    Some(monday),
+                    Some(sunday),
+                    args.tz.as_ref(),
+                );
+                worked(&sessions, now)
+            })
+            .sum();
+        let reported = week.days.iter().map(|day| day.worked).sum();
+
+        eprintln!("{}", rounding_note(recorded, reported));
+    }
+
+    if format != output::Format::Text {
+        return output::print_all(format, &week.days(args.tz.as_ref()));
+    }
+
+    terminal.setup(config);
+    let color = colored::control::SHOULD_COLORIZE.should_colorize();
+    println!("Week {}, {}", monday.iso_week().week(), year);
+    println!();
+    print!("{}", week.render(args.tz.as_ref(), color));
+
+    Ok(())
+}
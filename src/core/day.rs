@@ -0,0 +1,117 @@
+use chrono::DateTime;
+use chrono::Duration;
+use chrono::FixedOffset;
+use chrono::NaiveDate;
+use chrono::Utc;
+use serde::Serialize;
+
+use super::Norm;
+use super::Session;
+use super::Zone;
+
+/// Summary of one calendar day. In json output durations are in seconds.
+#[derive(Serialize)]
+pub struct Day {
+    pub date: NaiveDate,
+    /// The first login of the day
+    #[serde(rename = "in")]
+    pub first_in: Option<DateTime<FixedOffset>>,
+    /// The last logout of the day, or None if the day has no finished sessions
+    #[serde(rename = "out")]
+    pub last_out: Option<DateTime<FixedOffset>>,
+    /// Whether a session started this day is still running
+    pub running: bool,
+    #[serde(with = "super::duration_seconds")]
+    pub breaks: Duration,
+    #[serde(with = "super::duration_seconds")]
+    pub worked: Duration,
+    #[serde(with = "super::duration_seconds")]
+    pub norm: Duration,
+    /// Notes recorded during the day's sessions
+    pub notes: Vec<String>,
+}
+
+impl Day {
+    pub fn diff(&self) -> Duration {
+        self.worked - self.norm
+    }
+
+    /// The first login as HH:MM, or empty
+    pub fn in_time(&self) -> String {
+        self.first_in
+            .map(|timestamp| timestamp.format("%H:%M").to_string())
+            .unwrap_or_default()
+    }
+
+    /// The last logout as HH:MM, "now" if a session is running, or empty
+    pub fn out_time(&self) -> String {
+        match (self.last_out, self.running) {
+            (_, true) => "now".to_string(),
+            (Some(timestamp), false) => timestamp.format("%H:%M").to_string(),
+            (None, false) => String::new(),
+        }
+    }
+
+    /// Whether nothing was recorded or expected on this day
+    pub fn is_empty(&self) -> bool {
+        self.first_in.is_none() && self.norm.is_zero()
+    }
+
+    /// Whether work was expected on this day but none was recorded
+    pub fn is_absent(&self) -> bool {
+        self.first_in.is_none() && !self.norm.is_zero()
+    }
+}
+
+/// Summarize every day from `from` to `to`, both inclusive. Sessions count towards the day they
+/// started, in the zone they were recorded in unless `tz` overrides it. Running sessions count
+/// until `now`.
+pub fn days(
+    sessions: &[Session],
+    from: NaiveDate,
+    to: NaiveDate,
+    norm: &Norm,
+    tz: Option<&Zone>,
+    now: DateTime<Utc>,
+) -> Vec<Day> {
+    let mut days: Vec<Day> = from
+        .iter_days()
+        .take_while(|date| *date <= to)
+        .map(|date| Day {
+            date,
+            first_in: None,
+            last_out: None,
+            running: false,
+            breaks: Duration::zero(),
+            worked: Duration::zero(),
+            norm: norm.on(date),
+            notes: vec![],
+        })
+        .collect();
+
+    for session in sessions {
+        let index = (session.date(tz) - from).num_days();
+        let Some(day) = usize::try_from(index).ok().and_then(|i| days.get_mut(i)) else {
+            continue;
+        };
+
+        let zone = session.zone(tz);
+        let start = zone.localize(&session.start());
+        day.first_in = Some(day.first_in.map_or(start, |first| first.min(start)));
+
+        match session.end() {
+            Some(end) => {
+                let end = zone.localize(&end);
+                day.last_out = Some(day.last_out.map_or(end, |last| last.max(end)));
+            }
+            None => day.running = true,
+        }
+
+        day.breaks += session.break_time();
+        day.worked += session.worked(now);
+        day.notes
+            .extend(session.notes().into_iter().map(String::from));
+    }
+
+    days
+}
@@ -0,0 +1,652 @@
+/// Editor module
+///
+/// A day as a block of text that can be corrected in an editor, one line per entity. The edited
+/// text is compared with the day as recorded, and only the entities whose line changed are
+/// edited, added or removed.
+use std::collections::BTreeSet;
+use std::collections::HashSet;
+
+use chrono::DateTime;
+use chrono::Duration;
+use chrono::NaiveDate;
+use chrono::NaiveTime;
+use chrono::Utc;
+
+use super::Dst;
+use super::Entity;
+use super::Error;
+use super::Event;
+use super::Id;
+use super::State;
+use super::Zone;
+use super::util::hhmm;
+use super::util::parse_tag;
+
+/// Entities are shown by the start of their id, which is enough to tell apart the ones of a day
+const SHORT_ID: usize = 8;
+
+/// The id of a line that adds an entity
+const NEW: &str = "-";
+
+/// The entities of a day in `state` as lines of text, with times on the wall clock in `zone`
+pub fn render(state: &State, date: NaiveDate, zone: &Zone) -> String {
+    let mut text = format!("# {} ({})\n", date.format("%A %Y-%m-%d"), zone);
+    text += "#\n";
+    text += "# One entity per line: id, type and time, then the duration of a break or activity\n";
+    text += "# as h:mm, the name of an activity, tags as +tag and a note. Names and notes are in\n";
+    text += "# \"quotes\", with quotes in them written as \\\".\n";
+    text += "# Change a line to edit its entity, remove it to delete the entity, or add a line\n";
+    text += "# with - as id to add one, e.g.\n";
+    text += "#\n";
+    text += "# - break 12:00 0:30 \"lunch\"\n";
+    text += "\n";
+
+    for entity in state.iter() {
+        text += &line(entity, zone);
+        text += "\n";
+    }
+
+    text
+}
+
+/// The events that turn the entities of a day in `state` into those of the edited `text`. Times
+/// repeated by a daylight saving change are resolved with `dst`.
+pub fn changes(
+    text: &str,
+    state: &State,
+    date: NaiveDate,
+    zone: &Zone,
+    dst: Option<Dst>,
+) -> Result<Vec<Event>, Error> {
+    let entities: Vec<&Entity> = state.iter().collect();
+    let mut kept = HashSet::new();
+    let mut events = vec![];
+
+    for (number, text) in text.lines().enumerate() {
+        let text = text.trim();
+        if text.is_empty() || text.starts_with('#') {
+            continue;
+        }
+
+        let invalid = |err: String| Error::InvalidEdit(format!("line {}: {}", number + 1, err));
+        let (original, entity) = parse(text, &entities, date, zone, dst).map_err(invalid)?;
+
+        let event = match original {
+            None => Event::Create {
+                id: Id::new(),
+                created_at: Utc::now(),
+                entity,
+            },
+            Some(original) => {
+                if !kept.insert(original.id()) {
+                    return Err(invalid(format!(
+                        "{} is on more than one line",
+                        short(original)
+                    )));
+                }
+                if *original == entity {
+                    continue;
+                }
+
+                Event::Edit {
+                    id: Id::new(),
+                    created_at: Utc::now(),
+                    entity,
+                }
+            }
+        };
+        events.push(event);
+    }
+
+    for entity in entities.iter().filter(|entity| !kept.contains(entity.id())) {
+        events.push(Event::Delete {
+            id: Id::new(),
+            created_at: Utc::now(),
+            entity_id: entity.id().clone(),
+        });
+    }
+
+    Ok(events)
+}
+
+fn short(entity: &Entity) -> String {
+    entity.id().to_string()[..SHORT_ID].to_string()
+}
+
+fn kind(entity: &Entity) -> &'static str {
+    match entity {
+        Entity::Login { .. } => "login",
+        Entity::Logout { .. } => "logout",
+        Entity::Break { .. } => "break",
+        Entity::Activity { .. } => "activity",
+    }
+}
+
+fn line(entity: &Entity, zone: &Zone) -> String {
+    let mut words = vec![
+        short(entity),
+        kind(entity).to_string(),
+        zone.localize(entity.timestamp())
+            .format("%H:%M")
+            .to_string(),
+    ];
+
+    match entity {
+        Entity::Break { duration, .. } => words.push(hhmm(*duration)),
+        Entity::Activity {
+            duration, value, ..
+        } => {
+            words.push(hhmm(*duration));
+            words.push(quote(value));
+        }
+        Entity::Login { .. } | Entity::Logout { .. } => {}
+    }
+
+    words.extend(entity.tags().iter().map(|tag| format!("+{}", tag)));
+    words.extend(entity.note().map(quote));
+
+    words.join(" ")
+}
+
+/// Quoted like JSON, so that names and notes can hold quotes, line breaks, runs of spaces and a
+/// leading +
+fn quote(text: &str) -> String {
+    serde_json::to_string(text).unwrap()
+}
+
+/// A word of a line, or a text in "quotes"
+enum Word<'a> {
+    Bare(&'a str),
+    Quoted(String),
+}
+
+fn split(text: &str) -> Result<Vec<Word<'_>>, String> {
+    let mut words = vec![];
+    let mut rest = text.trim_start();
+
+    while !rest.is_empty() {
+        if rest.starts_with('"') {
+            let mut quoted = serde_json::Deserializer::from_str(rest).into_iter::<String>();
+            let Some(Ok(text)) = quoted.next() else {
+                return Err(
+                    "a text in \"quotes\" must be closed, with quotes in it written as \\\""
+                        .to_string(),
+                );
+            };
+            words.push(Word::Quoted(text));
+            rest = &rest[quoted.byte_offset()..];
+        } else {
+            let end = rest
+                .find(|c: char| c.is_whitespace() || c == '"')
+                .unwrap_or(rest.len());
+            words.push(Word::Bare(&rest[..end]));
+            rest = &rest[end..];
+        }
+
+        rest = rest.trim_start();
+    }
+
+    Ok(words)
+}
+
+/// The entity on a line, and the recorded entity it edits unless the line adds one. Fields that
+/// read as they did keep their recorded value, which may be more precise than the text.
+fn parse<'a>(
+    text: &str,
+    entities: &[&'a Entity],
+    date: NaiveDate,
+    zone: &Zone,
+    dst: Option<Dst>,
+) -> Result<(Option<&'a Entity>, Entity), String> {
+    let head = text[..text.find('"').unwrap_or(text.len())].trim();
+    let mut words = split(text)?.into_iter().peekable();
+    let mut next = |what: &str| match words.next() {
+        Some(Word::Bare(word)) => Ok(word),
+        _ => Err(format!("expected {} after '{}'", what, head)),
+    };
+
+    let id = next("an id")?;
+    let original = match id {
+        NEW => None,
+        id => {
+            let matching: Vec<&'a Entity> = entities
+                .iter()
+                .copied()
+                .filter(|entity| entity.id().to_string().starts_with(id))
+                .collect();
+            match matching.as_slice() {
+                [entity] => Some(*entity),
+                [] => return Err(format!("no entity of the day has id {}", id)),
+                _ => {
+                    return Err(format!(
+                        "more than one entity has an id starting with {}",
+                        id
+                    ));
+                }
+            }
+        }
+    };
+
+    let kind = next("a type")?.to_string();
+    if let Some(original) = original.filter(|original| self::kind(original) != kind) {
+        return Err(format!(
+            "{} is a {} and can't become a {}, remove its line and add one with - as id instead",
+            short(original),
+            self::kind(original),
+            kind
+        ));
+    }
+    let time = next("a time")?;
+    let (timestamp, tz) = match original {
+        Some(original)
+            if zone
+                .localize(original.timestamp())
+                .format("%H:%M")
+                .to_string()
+                == time =>
+        {
+            (*original.timestamp(), original.tz())
+        }
+        _ => (timestamp(time, date, zone, dst)?, Some(*zone)),
+    };
+
+    let mut duration = || -> Result<Duration, String> {
+        let text = next("a duration as h:mm")?;
+        match original {
+            Some(Entity::Break { duration, .. } | Entity::Activity { duration, .. })
+                if hhmm(*duration) == text =>
+            {
+                Ok(*duration)
+            }
+            _ => parse_duration(text),
+        }
+    };
+
+    let autoinsert = match original {
+        Some(Entity::Break { autoinsert, .. } | Entity::Activity { autoinsert, .. }) => *autoinsert,
+        _ => false,
+    };
+    let id = original.map_or_else(Id::new, |original| original.id().clone());
+
+    let entity = match kind.as_str() {
+        "login" => {
+            let (tags, note) = tags_and_note(words)?;
+
+            Entity::Login {
+                id,
+                timestamp,
+                tz,
+                note,
+                tags,
+            }
+        }
+        "logout" => {
+            let (tags, note) = tags_and_note(words)?;
+            if !tags.is_empty() {
+                return Err("a logout has no tags, they go on the login".to_string());
+            }
+
+            Entity::Logout {
+                id,
+                timestamp,
+                tz,
+                note,
+            }
+        }
+        "break" => {
+            let duration = duration()?;
+            let (tags, note) = tags_and_note(words)?;
+
+            Entity::Break {
+                id,
+                timestamp,
+                tz,
+                note,
+                tags,
+                duration,
+                autoinsert,
+            }
+        }
+        "activity" => {
+            let duration = duration()?;
+            let value = match words.next_if(|word| matches!(word, Word::Quoted(_))) {
+                Some(Word::Quoted(name)) => name,
+                // Typed without quotes, the name is the words up to the tags or the note
+                _ => {
+                    let mut name = vec![];
+                    while let Some(Word::Bare(word)) = words
+                        .next_if(|word| matches!(word, Word::Bare(word) if !word.starts_with('+')))
+                    {
+                        name.push(word);
+                    }
+                    name.join(" ")
+                }
+            };
+            if value.is_empty() {
+                return Err("an activity needs a name besides its tags".to_string());
+            }
+            let (tags, note) = tags_and_note(words)?;
+
+            Entity::Activity {
+                id,
+                timestamp,
+                tz,
+                note,
+                tags,
+                duration,
+                value,
+                autoinsert,
+            }
+        }
+        kind => {
+            return Err(format!(
+                "unknown type '{}', expected login, logout, break or activity",
+                kind
+            ));
+        }
+    };
+
+    Ok((original, entity))
+}
+
+fn timestamp(
+    text: &str,
+    date: NaiveDate,
+    zone: &Zone,
+    dst: Option<Dst>,
+) -> Result<DateTime<Utc>, String> {
+    let time = NaiveTime::parse_from_str(text, "%H:%M")
+        .map_err(|_| format!("invalid time '{}', expected hh:mm", text))?;
+
+    zone.resolve(&date.and_time(time), dst)
+        .map_err(|err| match err {
+            Error::AmbiguousLocalTime(msg) | Error::NonexistentLocalTime(msg) => msg,
+            _ => format!("{} can't be resolved in {}", text, zone),
+        })
+}
+
+fn parse_duration(text: &str) -> Result<Duration, String> {
+    let invalid = || format!("invalid duration '{}', expected h:mm", text);
+
+    let (hours, minutes) = text.split_once(':').ok_or_else(invalid)?;
+    let hours: i64 = hours.parse().map_err(|_| invalid())?;
+    let minutes: i64 = minutes.parse().map_err(|_| invalid())?;
+    if hours < 0 || !(0..60).contains(&minutes) || minutes.to_string().len() > 2 {
+        return Err(invalid());
+    }
+
+    hours
+        .checked_mul(60)
+        .and_then(|hours| hours.checked_add(minutes))
+        .and_then(Duration::try_minutes)
+        .ok_or_else(invalid)
+}
+
+/// The tags and the note that end a line
+fn tags_and_note<'a>(
+    words: impl Iterator<Item = Word<'a>>,
+) -> Result<(BTreeSet<String>, Option<String>), String> {
+    let mut words: Vec<Word> = words.collect();
+    let note = match words.pop() {
+        Some(Word::Quoted(note)) => Some(note),
+        Some(word) => {
+            words.push(word);
+            None
+        }
+        None => None,
+    };
+
+    let tags = words
+        .into_iter()
+        .map(|word| match word {
+            Word::Bare(word) if word.starts_with('+') => parse_tag(word),
+            Word::Bare(word) => Err(format!(
+                "unexpected '{}', only tags and a note can follow",
+                word
+            )),
+            Word::Quoted(_) => Err(
+                "the note must be a single text in \"quotes\" at the end of the line".to_string(),
+            ),
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok((tags, note))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone as _;
+
+    use super::*;
+    use crate::Stream;
+    use crate::replay;
+
+    fn utc() -> Zone {
+        "UTC".parse().unwrap()
+    }
+
+    fn date() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 9, 1).unwrap()
+    }
+
+    fn day() -> State {
+        let at = |hour, minute, second| {
+            Utc.with_ymd_and_hms(2025, 9, 1, hour, minute, second)
+                .unwrap()
+        };
+        let entities = [
+            Entity::Login {
+                id: Id::new(),
+                timestamp: at(8, 0, 42),
+                tz: Some(utc()),
+                note: None,
+                tags: ["acme".to_string()].into(),
+            },
+            Entity::Activity {
+                id: Id::new(),
+                timestamp: at(9, 0, 0),
+                tz: Some(utc()),
+                note: Some("with \"Kari\"".to_string()),
+                tags: BTreeSet::new(),
+                duration: Duration::minutes(90),
+                value: "Code review".to_string(),
+                autoinsert: false,
+            },
+            Entity::Logout {
+                id: Id::new(),
+                timestamp: at(16, 0, 0),
+                tz: Some(utc()),
+                note: None,
+            },
+        ];
+
+        let mut stream = Stream::new();
+        for entity in entities {
+            stream
+                .push(Event::Create {
+                    id: Id::new(),
+                    created_at: Utc::now(),
+                    entity,
+                })
+                .unwrap();
+        }
+        replay(&stream)
+    }
+
+    #[test]
+    fn unchanged_text_changes_nothing() {
+        let state = day();
+        let text = render(&state, date(), &utc());
+
+        assert!(text.contains(" activity 09:00 1:30 \"Code review\" \"with \\\"Kari\\\"\"\n"));
+        assert!(
+            changes(&text, &state, date(), &utc(), None)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn any_activity_name_survives_unchanged() {
+        let mut stream = Stream::new();
+        for name in ["two  spaces", "+not a tag", "say \"hi\"", " padded "] {
+            stream
+                .push(Event::Create {
+                    id: Id::new(),
+                    created_at: Utc::now(),
+                    entity: Entity::Activity {
+                        id: Id::new(),
+                        timestamp: Utc.with_ymd_and_hms(2025, 9, 1, 9, 0, 0).unwrap(),
+                        tz: Some(utc()),
+                        note: Some("note".to_string()),
+                        tags: ["acme".to_string()].into(),
+                        duration: Duration::minutes(30),
+                        value: name.to_string(),
+                        autoinsert: false,
+                    },
+                })
+                .unwrap();
+        }
+        let state = replay(&stream);
+        let text = render(&state, date(), &utc());
+
+        assert!(
+            changes(&text, &state, date(), &utc(), None)
+                .unwrap()
+                .is_empty()
+        );
+
+        // Names may still be typed without quotes
+        let events = changes(
+            "- activity 10:00 0:30 Code review +acme",
+            &state,
+            date(),
+            &utc(),
+            None,
+        )
+        .unwrap();
+        assert!(matches!(
+            &events[0],
+            Event::Create { entity: Entity::Activity { value, .. }, .. } if value == "Code review"
+        ));
+    }
+
+    #[test]
+    fn edited_lines_become_events() {
+        let state = day();
+        let entities: Vec<&Entity> = state.iter().collect();
+        let text = render(&state, date(), &utc())
+            .replace(" login 08:00 +acme", " login 07:45 +acme")
+            .replace(" logout 16:00", " logout 16:00 \"done\"")
+            .lines()
+            .filter(|line| !line.contains(" activity "))
+            .chain(["- break 12:00 0:30 +away"])
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let events = changes(&text, &state, date(), &utc(), None).unwrap();
+
+        assert_eq!(events.len(), 4);
+        match &events[0] {
+            Event::Edit { entity, .. } => {
+                assert_eq!(entity.id(), entities[0].id());
+                assert_eq!(
+                    *entity.timestamp(),
+                    Utc.with_ymd_and_hms(2025, 9, 1, 7, 45, 0).unwrap()
+                );
+            }
+            _ => panic!("expected an edit"),
+        }
+        match &events[1] {
+            Event::Edit { entity, .. } => {
+                assert_eq!(entity.note(), Some("done"));
+                assert_eq!(entity.timestamp(), entities[2].timestamp());
+            }
+            _ => panic!("expected an edit"),
+        }
+        match &events[2] {
+            Event::Create {
+                entity: Entity::Break { duration, tags, .. },
+                ..
+            } => {
+                assert_eq!(*duration, Duration::minutes(30));
+                assert!(tags.contains("away"));
+            }
+            _ => panic!("expected a new break"),
+        }
+        assert!(matches!(
+            &events[3],
+            Event::Delete { entity_id, .. } if entity_id == entities[1].id()
+        ));
+    }
+
+    #[test]
+    fn invalid_lines_are_reported() {
+        let state = day();
+
+        for (text, error) in [
+            ("- lunch 12:00", "line 1: unknown type 'lunch'"),
+            ("- break 12:00", "line 1: expected a duration"),
+            ("- login 25:00", "line 1: invalid time '25:00'"),
+            ("- login 08:00 early", "line 1: unexpected 'early'"),
+            (
+                "- login 08:00 \"unclosed",
+                "line 1: a text in \"quotes\" must be closed",
+            ),
+            ("- login 08:00 \"a\" +late", "line 1: the note must be"),
+            (
+                "- break 12:00 999999999999999:00",
+                "line 1: invalid duration",
+            ),
+            (
+                "00000000 login 08:00",
+                "line 1: no entity of the day has id 00000000",
+            ),
+        ] {
+            match changes(text, &state, date(), &utc(), None) {
+                Err(Error::InvalidEdit(message)) => {
+                    assert!(message.starts_with(error), "{}", message)
+                }
+                Err(other) => panic!("expected InvalidEdit for {}, got {:?}", text, other),
+                Ok(_) => panic!("expected InvalidEdit for {}", text),
+            }
+        }
+    }
+
+    #[test]
+    fn the_type_of_an_entity_stays() {
+        let state = day();
+        let text = render(&state, date(), &utc()).replace(" logout 16:00", " break 16:00 0:30");
+
+        match changes(&text, &state, date(), &utc(), None) {
+            Err(Error::InvalidEdit(message)) => {
+                assert!(
+                    message.contains("is a logout and can't become a break"),
+                    "{}",
+                    message
+                )
+            }
+            _ => panic!("expected InvalidEdit"),
+        }
+    }
+
+    #[test]
+    fn repeated_times_need_dst() {
+        // Clocks in Oslo go back from 03:00 to 02:00 on the last Sunday of October
+        let oslo: Zone = "Europe/Oslo".parse().unwrap();
+        let date = NaiveDate::from_ymd_opt(2025, 10, 26).unwrap();
+        let state = replay(&Stream::new());
+
+        match changes("- login 02:30", &state, date, &oslo, None) {
+            Err(Error::InvalidEdit(message)) => assert!(message.contains("--dst"), "{}", message),
+            _ => panic!("expected InvalidEdit"),
+        }
+
+        let events = changes("- login 02:30", &state, date, &oslo, Some(Dst::Later)).unwrap();
+        assert!(matches!(
+            &events[0],
+            Event::Create { entity, .. }
+                if *entity.timestamp() == Utc.with_ymd_and_hms(2025, 10, 26, 1, 30, 0).unwrap()
+        ));
+    }
+}
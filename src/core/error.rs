@@ -17,4 +17,25 @@ pub enum Error {
     NoStreamSet,
     SerializeFailed(String),
     DeserializeFailed(String),
+    AmbiguousLocalTime(String),
+    NonexistentLocalTime(String),
+    InvalidTimeExpression(String),
+    ImportFailed(String),
+    ProfileNotFound(String),
+    InvalidProfile(String),
+    InvalidActivity(String),
+    DatabaseFailed(String),
+    ConversionFailed(String),
+    GitFailed(String),
+    MergeConflict(String),
+    KeyMissing(String),
+    EncryptionFailed(String),
+    DecryptionFailed(String),
+    BrokenChain(String),
+    BackupFailed(String),
+    BackupNotFound(String),
+    UnsupportedVersion(String),
+    PeriodLocked(String),
+    InvalidEdit(String),
+    ConflictingConfig(String),
 }
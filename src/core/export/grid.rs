@@ -0,0 +1,250 @@
+use std::collections::BTreeMap;
+
+use chrono::Duration;
+use chrono::NaiveDate;
+
+use crate::Error;
+use crate::Profile;
+use crate::Separator;
+use crate::Session;
+use crate::Zone;
+
+/// A rendered grid and the total of the hours in it
+#[derive(Debug)]
+pub struct Grid {
+    pub text: String,
+    /// The sum of the cells as written, i.e. after the profile's rounding
+    pub total: Duration,
+}
+
+/// Render the hours booked per row and day, in the layout of CATS and similar timesheet grids
+///
+/// Each row starts with the values that identify it in the profile, such as a WBS element and an
+/// attendance type, followed by one column per day from `from` to `to` with the hours booked that
+/// day. Every cell is rounded according to the profile.
+pub fn grid(
+    sessions: &[Session],
+    profile: &Profile,
+    from: NaiveDate,
+    to: NaiveDate,
+    tz: Option<&Zone>,
+) -> Result<Grid, Error> {
+    let mut rows: BTreeMap<&[String], BTreeMap<NaiveDate, Duration>> = BTreeMap::new();
+
+    for session in sessions {
+        let date = session.date(tz);
+        if date < from || to < date {
+            continue;
+        }
+
+        for segment in session.segments() {
+            let row = row(profile, segment.activity)?;
+            *rows.entry(row).or_default().entry(date).or_default() += segment.end - segment.start;
+        }
+    }
+
+    if let Some(rounding) = profile.rounding {
+        for duration in rows.values_mut().flat_map(BTreeMap::values_mut) {
+            *duration = rounding.apply(*duration);
+        }
+    }
+
+    let separator = profile.separator.unwrap_or(Separator::Tab).as_char();
+    let days: Vec<NaiveDate> = from.iter_days().take_while(|day| *day <= to).collect();
+
+    let mut lines = vec![];
+
+    let header = profile
+        .columns
+        .iter()
+        .cloned()
+        .chain(days.iter().map(|day| day.format("%d.%m.%Y").to_string()));
+    lines.push(join(header, separator));
+
+    for (row, hours) in &rows {
+        let cells = days.iter().map(|day| match hours.get(day) {
+            Some(duration) => format_hours(profile, *duration),
+            None => String::new(),
+        });
+        lines.push(join(row.iter().cloned().chain(cells), separator));
+    }
+
+    Ok(Grid {
+        text: lines.iter().map(|line| format!("{}\n", line)).collect(),
+        total: rows.values().flat_map(BTreeMap::values).sum(),
+    })
+}
+
+fn row<'a>(profile: &'a Profile, activity: Option<&str>) -> Result<&'a [String], Error> {
+    let row = activity
+        .and_then(|activity| profile.activities.get(activity))
+        .or(profile.default.as_ref())
+        .ok_or_else(|| match activity {
+            Some(activity) => Error::InvalidProfile(format!(
+                "activity '{}' has no row and the profile has no default",
+                activity
+            )),
+            None => Error::InvalidProfile(
+                "time outside activities needs a default row in the profile".to_string(),
+            ),
+        })?;
+
+    if row.len() != profile.columns.len() {
+        return Err(Error::InvalidProfile(format!(
+            "row {:?} does not match columns {:?}",
+            row, profile.columns
+        )));
+    }
+
+    Ok(row)
+}
+
+fn format_hours(profile: &Profile, duration: Duration) -> String {
+    let hours = format!("{:.2}", duration.num_seconds() as f64 / 3600.0);
+
+    match profile.decimal_comma {
+        Some(true) => hours.replace('.', ","),
+        _ => hours,
+    }
+}
+
+fn join(fields: impl Iterator<Item = String>, separator: char) -> String {
+    fields
+        .map(|field| {
+            if field.contains([separator, '"', '\n']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(&separator.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::*;
+    use crate::Entity;
+    use crate::Id;
+    use crate::Rounding;
+    use crate::RoundingMode;
+    use crate::RoundingScope;
+    use crate::core::fixtures::*;
+    use crate::replay;
+    use crate::sessions;
+
+    fn profile() -> Profile {
+        Profile {
+            columns: vec!["WBS element".to_string(), "Att./abs. type".to_string()],
+            default: Some(vec!["C-1000".to_string(), "0800".to_string()]),
+            activities: BTreeMap::from([(
+                "Review".to_string(),
+                vec!["P-1234-01".to_string(), "0800".to_string()],
+            )]),
+            separator: Some(Separator::Semicolon),
+            decimal_comma: Some(true),
+            rounding: Some(Rounding {
+                granularity: 15,
+                mode: RoundingMode::Nearest,
+                per: RoundingScope::Day,
+            }),
+        }
+    }
+
+    #[test]
+    fn hours_per_row_and_day() {
+        let tz = Some("UTC".parse().unwrap());
+        let stream = stream(vec![
+            Entity::Login {
+                id: Id::new(),
+                timestamp: at(1, 8, 0),
+                tz,
+                note: None,
+                tags: BTreeSet::new(),
+            },
+            Entity::Activity {
+                id: Id::new(),
+                timestamp: at(1, 9, 0),
+                tz,
+                note: None,
+                tags: BTreeSet::new(),
+                duration: Duration::minutes(100),
+                value: "Review".to_string(),
+                autoinsert: false,
+            },
+            Entity::Logout {
+                id: Id::new(),
+                timestamp: at(1, 16, 7),
+                tz,
+                note: None,
+            },
+            Entity::Login {
+                id: Id::new(),
+                timestamp: at(3, 8, 0),
+                tz,
+                note: None,
+                tags: BTreeSet::new(),
+            },
+            Entity::Logout {
+                id: Id::new(),
+                timestamp: at(3, 12, 0),
+                tz,
+                note: None,
+            },
+        ]);
+        let state = replay(&stream);
+
+        let from = NaiveDate::from_ymd_opt(2025, 9, 1).unwrap();
+        let to = NaiveDate::from_ymd_opt(2025, 9, 3).unwrap();
+        let grid = grid(&sessions(&state), &profile(), from, to, None).unwrap();
+
+        let expected = "\
+WBS element;Att./abs. type;01.09.2025;02.09.2025;03.09.2025
+C-1000;0800;6,50;;4,00
+P-1234-01;0800;1,75;;
+";
+        assert_eq!(grid.text, expected);
+        assert_eq!(grid.total, Duration::minutes(735));
+    }
+
+    #[test]
+    fn unmapped_activity_without_default() {
+        let stream = stream(vec![
+            Entity::Login {
+                id: Id::new(),
+                timestamp: at(1, 8, 0),
+                tz: None,
+                note: None,
+                tags: BTreeSet::new(),
+            },
+            Entity::Activity {
+                id: Id::new(),
+                timestamp: at(1, 8, 0),
+                tz: None,
+                note: None,
+                tags: BTreeSet::new(),
+                duration: Duration::hours(1),
+                value: "Support".to_string(),
+                autoinsert: false,
+            },
+            Entity::Logout {
+                id: Id::new(),
+                timestamp: at(1, 9, 0),
+                tz: None,
+                note: None,
+            },
+        ]);
+        let state = replay(&stream);
+
+        let mut profile = profile();
+        profile.default = None;
+
+        let day = NaiveDate::from_ymd_opt(2025, 9, 1).unwrap();
+        match grid(&sessions(&state), &profile, day, day, None).unwrap_err() {
+            Error::InvalidProfile(msg) => assert!(msg.contains("Support"), "{}", msg),
+            other => panic!("expected InvalidProfile, got {:?}", other),
+        }
+    }
+}
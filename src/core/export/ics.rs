@@ -0,0 +1,248 @@
+use chrono::DateTime;
+use chrono::Utc;
+
+use crate::Entity;
+use crate::Session;
+
+const FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// Render sessions as an iCalendar (RFC 5545) calendar
+///
+/// Every finished session becomes an event named `name`, and every activity an event of its own.
+/// Notes become event descriptions.
+/// With `breaks`, breaks are included as events too. Event UIDs are derived from entity ids, so
+/// importing an updated export into a calendar replaces the earlier events instead of duplicating
+/// them.
+pub fn ics(sessions: &[Session], name: &str, breaks: bool, now: DateTime<Utc>) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//skuff//skuff//EN".to_string(),
+        format!("X-WR-CALNAME:{}", escape(name)),
+    ];
+
+    for session in sessions {
+        let Some(end) = session.end() else {
+            continue;
+        };
+
+        let notes: Vec<&str> = [Some(session.login), session.logout]
+            .into_iter()
+            .flatten()
+            .filter_map(Entity::note)
+            .collect();
+
+        lines.extend(event(
+            session.login,
+            session.start(),
+            end,
+            name,
+            &notes.join("\n"),
+            now,
+        ));
+
+        for activity in &session.activities {
+            if let Entity::Activity {
+                timestamp,
+                duration,
+                value,
+                ..
+            } = activity
+            {
+                lines.extend(event(
+                    activity,
+                    *timestamp,
+                    *timestamp + *duration,
+                    value,
+                    activity.note().unwrap_or_default(),
+                    now,
+                ));
+            }
+        }
+
+        if !breaks {
+            continue;
+        }
+
+        for pause in &session.breaks {
+            if let Entity::Break {
+                timestamp,
+                duration,
+                ..
+            } = pause
+            {
+                lines.extend(event(
+                    pause,
+                    *timestamp,
+                    *timestamp + *duration,
+                    "Break",
+                    pause.note().unwrap_or_default(),
+                    now,
+                ));
+            }
+        }
+    }
+
+    lines.push("END:VCALENDAR".to_string());
+
+    lines
+        .iter()
+        .map(|line| fold(line) + "\r\n")
+        .collect::<String>()
+}
+
+fn event(
+    entity: &Entity,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    summary: &str,
+    description: &str,
+    now: DateTime<Utc>,
+) -> Vec<String> {
+    let mut lines = vec![
+        "BEGIN:VEVENT".to_string(),
+        format!("UID:{}@skuff", entity.id()),
+        format!("DTSTAMP:{}", now.format(FORMAT)),
+        format!("DTSTART:{}", start.format(FORMAT)),
+        format!("DTEND:{}", end.format(FORMAT)),
+        format!("SUMMARY:{}", escape(summary)),
+    ];
+
+    if !description.is_empty() {
+        lines.push(format!("DESCRIPTION:{}", escape(description)));
+    }
+
+    lines.push("END:VEVENT".to_string());
+    lines
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Lines longer than 75 octets are folded onto continuation lines starting with a space
+fn fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut width = 0;
+
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            folded += "\r\n ";
+            width = 1;
+        }
+        folded.push(c);
+        width += c.len_utf8();
+    }
+
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use chrono::Duration;
+
+    use super::*;
+    use crate::Id;
+    use crate::core::fixtures::*;
+    use crate::replay;
+    use crate::sessions;
+
+    #[test]
+    fn sessions_activities_and_breaks() {
+        let login = Id::new();
+        let activity = Id::new();
+        let pause = Id::new();
+
+        let state = replay(&stream([
+            Entity::Login {
+                id: login.clone(),
+                timestamp: at(1, 7, 0),
+                tz: None,
+                note: None,
+                tags: BTreeSet::new(),
+            },
+            Entity::Activity {
+                id: activity.clone(),
+                timestamp: at(1, 8, 0),
+                tz: None,
+                note: None,
+                tags: BTreeSet::new(),
+                duration: Duration::hours(1),
+                value: "Review, design".to_string(),
+                autoinsert: false,
+            },
+            Entity::Break {
+                id: pause.clone(),
+                timestamp: at(1, 11, 0),
+                tz: None,
+                note: None,
+                tags: BTreeSet::new(),
+                duration: Duration::minutes(30),
+                autoinsert: false,
+            },
+            Entity::Logout {
+                id: Id::new(),
+                timestamp: at(1, 15, 0),
+                tz: None,
+                note: None,
+            },
+            Entity::Login {
+                id: Id::new(),
+                timestamp: at(1, 16, 0),
+                tz: None,
+                note: None,
+                tags: BTreeSet::new(),
+            },
+        ]));
+
+        let expected = [
+            "BEGIN:VCALENDAR",
+            "VERSION:2.0",
+            "PRODID:-//skuff//skuff//EN",
+            "X-WR-CALNAME:work",
+            "BEGIN:VEVENT",
+            &format!("UID:{}@skuff", login),
+            "DTSTAMP:20250901T120000Z",
+            "DTSTART:20250901T070000Z",
+            "DTEND:20250901T150000Z",
+            "SUMMARY:work",
+            "END:VEVENT",
+            "BEGIN:VEVENT",
+            &format!("UID:{}@skuff", activity),
+            "DTSTAMP:20250901T120000Z",
+            "DTSTART:20250901T080000Z",
+            "DTEND:20250901T090000Z",
+            "SUMMARY:Review\\, design",
+            "END:VEVENT",
+            "BEGIN:VEVENT",
+            &format!("UID:{}@skuff", pause),
+            "DTSTAMP:20250901T120000Z",
+            "DTSTART:20250901T110000Z",
+            "DTEND:20250901T113000Z",
+            "SUMMARY:Break",
+            "END:VEVENT",
+            "END:VCALENDAR",
+            "",
+        ]
+        .join("\r\n");
+
+        assert_eq!(ics(&sessions(&state), "work", true, at(1, 12, 0)), expected);
+    }
+
+    #[test]
+    fn long_lines_are_folded() {
+        let line = "x".repeat(160);
+        let folded = fold(&line);
+
+        let lines: Vec<&str> = folded.split("\r\n").collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0].len(), 75);
+        assert!(lines[1].starts_with(' '));
+        assert_eq!(lines.concat().replace(' ', ""), line);
+    }
+}
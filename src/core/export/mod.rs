@@ -0,0 +1,19 @@
+/// Export module
+///
+/// This module renders recorded sessions in the formats of other tools, so hours can be
+/// transferred or cross-checked without retyping them.
+mod grid;
+mod ics;
+mod timeclock;
+
+pub use grid::*;
+pub use ics::*;
+pub use timeclock::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    /// ledger/hledger timeclock
+    Timeclock,
+    /// iCalendar, for overlaying sessions on a calendar
+    Ics,
+}
@@ -0,0 +1,170 @@
+use chrono::DateTime;
+use chrono::Utc;
+
+use crate::Session;
+use crate::Zone;
+
+const FORMAT: &str = "%Y/%m/%d %H:%M:%S";
+
+/// Render sessions as ledger/hledger timeclock entries
+///
+/// Every stretch of work between breaks becomes a clock-in/clock-out pair. Stretches covered by
+/// an activity are booked to an account named after it, the rest to `account`. Times are written
+/// in the zone each session was recorded in, unless `tz` overrides it. Notes on the login and
+/// logout are written as comments before and after the session.
+pub fn timeclock(sessions: &[Session], account: &str, tz: Option<&Zone>) -> String {
+    let mut out = String::new();
+
+    for (n, session) in sessions.iter().enumerate() {
+        let zone = session.zone(tz);
+        let local = |t: DateTime<Utc>| zone.localize(&t).format(FORMAT).to_string();
+
+        if session.end().is_none() {
+            // A clock-in without a clock-out is only allowed at the very end of the file, where
+            // ledger takes it to mean "still working"
+            let prefix = if n + 1 == sessions.len() { "" } else { "; " };
+            out += &format!(
+                "{}i {} {}\n",
+                prefix,
+                local(session.start()),
+                sanitize(account)
+            );
+            continue;
+        }
+
+        if let Some(note) = session.login.note() {
+            out += &comment(note);
+        }
+
+        for segment in session.segments() {
+            out += &format!(
+                "i {} {}\n",
+                local(segment.start),
+                sanitize(segment.activity.unwrap_or(account))
+            );
+            out += &format!("o {}\n", local(segment.end));
+        }
+
+        if let Some(note) = session.logout.and_then(|logout| logout.note()) {
+            out += &comment(note);
+        }
+    }
+
+    out
+}
+
+/// Notes are written as comment lines, one per line of the note
+fn comment(note: &str) -> String {
+    note.lines().map(|line| format!("; {}\n", line)).collect()
+}
+
+/// Two or more spaces end the account name in the timeclock format
+fn sanitize(account: &str) -> String {
+    account.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use chrono::Duration;
+
+    use super::*;
+    use crate::Entity;
+    use crate::Id;
+    use crate::core::fixtures::*;
+    use crate::replay;
+    use crate::sessions;
+
+    #[test]
+    fn breaks_and_activities() {
+        let tz = Some("Europe/Oslo".parse().unwrap());
+        let stream = stream(vec![
+            Entity::Login {
+                id: Id::new(),
+                timestamp: at(1, 6, 0),
+                tz,
+                note: None,
+                tags: BTreeSet::new(),
+            },
+            Entity::Activity {
+                id: Id::new(),
+                timestamp: at(1, 7, 0),
+                tz,
+                note: None,
+                tags: BTreeSet::new(),
+                duration: Duration::hours(1),
+                value: "client:review  meeting".to_string(),
+                autoinsert: false,
+            },
+            Entity::Break {
+                id: Id::new(),
+                timestamp: at(1, 10, 0),
+                tz,
+                note: None,
+                tags: BTreeSet::new(),
+                duration: Duration::minutes(30),
+                autoinsert: false,
+            },
+            Entity::Logout {
+                id: Id::new(),
+                timestamp: at(1, 14, 0),
+                tz,
+                note: None,
+            },
+            Entity::Login {
+                id: Id::new(),
+                timestamp: at(1, 15, 0),
+                tz,
+                note: None,
+                tags: BTreeSet::new(),
+            },
+        ]);
+        let state = replay(&stream);
+
+        let expected = "\
+i 2025/09/01 08:00:00 work
+o 2025/09/01 09:00:00
+i 2025/09/01 09:00:00 client:review meeting
+o 2025/09/01 10:00:00
+i 2025/09/01 10:00:00 work
+o 2025/09/01 12:00:00
+i 2025/09/01 12:30:00 work
+o 2025/09/01 16:00:00
+i 2025/09/01 17:00:00 work
+";
+
+        assert_eq!(timeclock(&sessions(&state), "work", None), expected);
+    }
+
+    #[test]
+    fn notes_become_comments() {
+        let tz = Some("UTC".parse().unwrap());
+        let stream = stream(vec![
+            Entity::Login {
+                id: Id::new(),
+                timestamp: at(1, 8, 0),
+                tz,
+                note: Some("on-call incident".to_string()),
+                tags: BTreeSet::new(),
+            },
+            Entity::Logout {
+                id: Id::new(),
+                timestamp: at(1, 13, 0),
+                tz,
+                note: Some("left early\nfor the dentist".to_string()),
+            },
+        ]);
+        let state = replay(&stream);
+
+        let expected = "\
+; on-call incident
+i 2025/09/01 08:00:00 work
+o 2025/09/01 13:00:00
+; left early
+; for the dentist
+";
+
+        assert_eq!(timeclock(&sessions(&state), "work", None), expected);
+    }
+}
@@ -0,0 +1,342 @@
+/// Time expressions
+///
+/// The small grammar accepted by --time and --date. Expressions are parsed up front, by clap, but
+/// resolved later against the current date and time in the relevant timezone, so that relative
+/// expressions such as "yesterday" or "-20m" mean the same thing regardless of where the machine
+/// thinks it is.
+use std::str::FromStr;
+
+use chrono::Datelike;
+use chrono::Duration;
+use chrono::NaiveDate;
+use chrono::NaiveTime;
+use chrono::Weekday;
+
+use super::Error;
+
+const TIME_HELP: &str =
+    "expected HH:MM[:SS], HHMM, 'now', or a relative time such as -20m, +1h or '1h30m ago'";
+
+const DATE_HELP: &str = "expected YYYY-MM-DD, DD.MM[.YYYY], 'today', 'yesterday', a weekday such \
+     as 'mon', or a relative day such as -2 or -1w";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeExpr {
+    Now,
+    Clock(NaiveTime),
+    /// Offset from the current time
+    Relative(Duration),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateExpr {
+    Absolute(NaiveDate),
+    /// Day and month in the current year
+    DayMonth {
+        day: u32,
+        month: u32,
+    },
+    /// Offset in days from today
+    Relative(i64),
+    /// The most recent such weekday, today included
+    Weekday(Weekday),
+}
+
+impl TimeExpr {
+    pub fn is_relative(&self) -> bool {
+        matches!(self, TimeExpr::Now | TimeExpr::Relative(_))
+    }
+}
+
+impl DateExpr {
+    pub fn resolve(&self, today: NaiveDate) -> Result<NaiveDate, Error> {
+        match *self {
+            DateExpr::Absolute(date) => Ok(date),
+            DateExpr::DayMonth { day, month } => NaiveDate::from_ymd_opt(today.year(), month, day)
+                .ok_or_else(|| {
+                    Error::InvalidTimeExpression(format!(
+                        "{:02}.{:02} does not exist in {}",
+                        day,
+                        month,
+                        today.year()
+                    ))
+                }),
+            DateExpr::Relative(days) => Duration::try_days(days)
+                .and_then(|offset| today.checked_add_signed(offset))
+                .ok_or_else(|| {
+                    Error::InvalidTimeExpression(format!(
+                        "{} days from today is out of range",
+                        days
+                    ))
+                }),
+            DateExpr::Weekday(weekday) => {
+                let back = (7 + today.weekday().num_days_from_monday()
+                    - weekday.num_days_from_monday())
+                    % 7;
+                Ok(today - Duration::days(back as i64))
+            }
+        }
+    }
+}
+
+impl FromStr for TimeExpr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let input = s.trim().to_lowercase();
+        let invalid = || format!("invalid time '{}': {}", s, TIME_HELP);
+
+        if input == "now" {
+            return Ok(TimeExpr::Now);
+        }
+
+        if let Some(rest) = input.strip_suffix("ago") {
+            let duration = parse_duration(rest).ok_or_else(invalid)?;
+            return Ok(TimeExpr::Relative(-duration));
+        }
+
+        if let Some(rest) = input.strip_prefix('-') {
+            let duration = parse_duration(rest).ok_or_else(invalid)?;
+            return Ok(TimeExpr::Relative(-duration));
+        }
+
+        if let Some(rest) = input.strip_prefix('+') {
+            let duration = parse_duration(rest).ok_or_else(invalid)?;
+            return Ok(TimeExpr::Relative(duration));
+        }
+
+        if parse_duration(&input).is_some() {
+            return Err(format!(
+                "invalid time '{}': relative times need a direction, e.g. -{} or '{} ago'",
+                s, input, input
+            ));
+        }
+
+        if let Ok(time) = NaiveTime::from_str(&input) {
+            return Ok(TimeExpr::Clock(time));
+        }
+
+        if let Ok(time) = NaiveTime::parse_from_str(&input, "%H:%M") {
+            return Ok(TimeExpr::Clock(time));
+        }
+
+        parse_compact_clock(&input)
+            .map(TimeExpr::Clock)
+            .ok_or_else(invalid)
+    }
+}
+
+impl FromStr for DateExpr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let input = s.trim().to_lowercase();
+        let invalid = || format!("invalid date '{}': {}", s, DATE_HELP);
+
+        match input.as_str() {
+            "today" => return Ok(DateExpr::Relative(0)),
+            "yesterday" => return Ok(DateExpr::Relative(-1)),
+            "tomorrow" => return Ok(DateExpr::Relative(1)),
+            _ => {}
+        }
+
+        if let Ok(weekday) = Weekday::from_str(&input) {
+            return Ok(DateExpr::Weekday(weekday));
+        }
+
+        if let Some(rest) = input.strip_prefix('-') {
+            return parse_days(rest)
+                .map(|days| DateExpr::Relative(-days))
+                .ok_or_else(invalid);
+        }
+
+        if let Some(rest) = input.strip_prefix('+') {
+            return parse_days(rest).map(DateExpr::Relative).ok_or_else(invalid);
+        }
+
+        if let Ok(date) = NaiveDate::from_str(&input) {
+            return Ok(DateExpr::Absolute(date));
+        }
+
+        if let Ok(date) = NaiveDate::parse_from_str(&input, "%d.%m.%Y") {
+            return Ok(DateExpr::Absolute(date));
+        }
+
+        let (day, month) = input.split_once('.').ok_or_else(invalid)?;
+        let day: u32 = day.parse().map_err(|_| invalid())?;
+        let month: u32 = month.trim_end_matches('.').parse().map_err(|_| invalid())?;
+
+        if !(1..=31).contains(&day) || !(1..=12).contains(&month) {
+            return Err(invalid());
+        }
+
+        Ok(DateExpr::DayMonth { day, month })
+    }
+}
+
+/// Parse durations such as "20m", "1h30m", "1 h 30 min" or "90 minutes"
+fn parse_duration(s: &str) -> Option<Duration> {
+    let mut rest = s.trim();
+    let mut total = Duration::zero();
+
+    if rest.is_empty() {
+        return None;
+    }
+
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let n: i64 = rest[..digits].parse().ok()?;
+        rest = rest[digits..].trim_start();
+
+        let letters = rest
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(rest.len());
+        let part = match &rest[..letters] {
+            "h" | "hr" | "hrs" | "hour" | "hours" => Duration::try_hours(n)?,
+            "m" | "min" | "mins" | "minute" | "minutes" => Duration::try_minutes(n)?,
+            "s" | "sec" | "secs" | "second" | "seconds" => Duration::try_seconds(n)?,
+            _ => return None,
+        };
+        total = total.checked_add(&part)?;
+        rest = rest[letters..].trim_start();
+    }
+
+    Some(total)
+}
+
+/// Parse day offsets such as "2", "2d" or "1w"
+fn parse_days(s: &str) -> Option<i64> {
+    if let Some(weeks) = s.strip_suffix('w') {
+        return weeks.parse::<i64>().ok()?.checked_mul(7);
+    }
+
+    s.strip_suffix('d').unwrap_or(s).parse().ok()
+}
+
+/// Parse clock times without separator: "9" is 09:00, "815" is 08:15 and "0815" is 08:15
+fn parse_compact_clock(s: &str) -> Option<NaiveTime> {
+    if s.is_empty() || s.len() > 4 || !s.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let (hour, minute) = match s.len() {
+        1 | 2 => (s, "0"),
+        _ => s.split_at(s.len() - 2),
+    };
+
+    NaiveTime::from_hms_opt(hour.parse().ok()?, minute.parse().ok()?, 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(s: &str) -> TimeExpr {
+        s.parse().unwrap()
+    }
+
+    fn date(s: &str) -> DateExpr {
+        s.parse().unwrap()
+    }
+
+    fn clock(h: u32, m: u32, s: u32) -> TimeExpr {
+        TimeExpr::Clock(NaiveTime::from_hms_opt(h, m, s).unwrap())
+    }
+
+    fn ymd(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn clock_times() {
+        assert_eq!(time("08:15"), clock(8, 15, 0));
+        assert_eq!(time("08:15:30"), clock(8, 15, 30));
+        assert_eq!(time("0815"), clock(8, 15, 0));
+        assert_eq!(time("815"), clock(8, 15, 0));
+        assert_eq!(time("9"), clock(9, 0, 0));
+        assert_eq!(time("17"), clock(17, 0, 0));
+    }
+
+    #[test]
+    fn relative_times() {
+        assert_eq!(time("now"), TimeExpr::Now);
+        assert_eq!(time("-20m"), TimeExpr::Relative(Duration::minutes(-20)));
+        assert_eq!(time("+1h"), TimeExpr::Relative(Duration::hours(1)));
+        assert_eq!(time("1h ago"), TimeExpr::Relative(Duration::hours(-1)));
+        assert_eq!(
+            time("1h30m ago"),
+            TimeExpr::Relative(Duration::minutes(-90))
+        );
+        assert_eq!(
+            time("1 hour 5 minutes ago"),
+            TimeExpr::Relative(Duration::minutes(-65))
+        );
+    }
+
+    #[test]
+    fn invalid_times() {
+        for s in ["25:00", "2460", "12345", "", "-", "ago", "-20x", "soon"] {
+            assert!(s.parse::<TimeExpr>().is_err(), "'{}' should not parse", s);
+        }
+
+        let err = "20m".parse::<TimeExpr>().unwrap_err();
+        assert!(err.contains("-20m"), "unhelpful error: {}", err);
+    }
+
+    #[test]
+    fn absolute_dates() {
+        let today = ymd(2025, 9, 3);
+
+        assert_eq!(date("2025-08-01").resolve(today).unwrap(), ymd(2025, 8, 1));
+        assert_eq!(date("01.08").resolve(today).unwrap(), ymd(2025, 8, 1));
+        assert_eq!(date("01.08.2024").resolve(today).unwrap(), ymd(2024, 8, 1));
+    }
+
+    #[test]
+    fn relative_dates() {
+        // A wednesday
+        let today = ymd(2025, 9, 3);
+
+        assert_eq!(date("today").resolve(today).unwrap(), today);
+        assert_eq!(date("yesterday").resolve(today).unwrap(), ymd(2025, 9, 2));
+        assert_eq!(date("-2").resolve(today).unwrap(), ymd(2025, 9, 1));
+        assert_eq!(date("-2d").resolve(today).unwrap(), ymd(2025, 9, 1));
+        assert_eq!(date("-1w").resolve(today).unwrap(), ymd(2025, 8, 27));
+        assert_eq!(date("+1").resolve(today).unwrap(), ymd(2025, 9, 4));
+    }
+
+    #[test]
+    fn weekdays_resolve_backwards() {
+        // A wednesday
+        let today = ymd(2025, 9, 3);
+
+        assert_eq!(date("mon").resolve(today).unwrap(), ymd(2025, 9, 1));
+        assert_eq!(date("Wednesday").resolve(today).unwrap(), today);
+        assert_eq!(date("thu").resolve(today).unwrap(), ymd(2025, 8, 28));
+    }
+
+    #[test]
+    fn invalid_dates() {
+        for s in ["32.01", "01.13", "someday", "-x", "2025-13-01", ""] {
+            assert!(s.parse::<DateExpr>().is_err(), "'{}' should not parse", s);
+        }
+
+        let today = ymd(2025, 9, 3);
+        assert!(date("29.02").resolve(today).is_err());
+        assert!(date("-9999999999").resolve(today).is_err());
+        assert!("+9999999999999999999w".parse::<DateExpr>().is_err());
+    }
+
+    #[test]
+    fn out_of_range_durations() {
+        for s in [
+            "-9999999999999h",
+            "+9999999999999999999m",
+            "5000000000000h ago",
+        ] {
+            assert!(s.parse::<TimeExpr>().is_err(), "'{}' should not parse", s);
+        }
+    }
+}
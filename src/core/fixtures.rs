@@ -0,0 +1,29 @@
+//! Helpers shared by the unit tests
+use chrono::DateTime;
+use chrono::TimeZone as _;
+use chrono::Utc;
+
+use super::Entity;
+use super::Event;
+use super::Id;
+use super::Stream;
+
+/// A UTC timestamp in September 2025
+pub fn at(day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2025, 9, day, hour, minute, 0).unwrap()
+}
+
+/// A stream that creates `entities` in order
+pub fn stream(entities: impl IntoIterator<Item = Entity>) -> Stream {
+    let mut stream = Stream::new();
+    for entity in entities {
+        stream
+            .push(Event::Create {
+                id: Id::new(),
+                created_at: Utc::now(),
+                entity,
+            })
+            .unwrap();
+    }
+    stream
+}
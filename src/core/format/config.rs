@@ -1,6 +1,20 @@
+use std::collections::BTreeMap;
+
+use chrono::DateTime;
+use chrono::Datelike as _;
+use chrono::Duration;
+use chrono::NaiveDate;
+use chrono::Utc;
+use chrono::Weekday;
 use serde::Deserialize;
 use serde::Serialize;
 
+use crate::Error;
+use crate::Zone;
+use crate::output::Color;
+
+use super::CONFIG;
+
 #[derive(Serialize, Deserialize)]
 pub enum StreamOrder {
     LastUsed,
@@ -10,16 +24,202 @@ pub enum StreamOrder {
 #[derive(Serialize, Deserialize)]
 pub struct Config {
     pub stream_order: Option<StreamOrder>,
+    pub profiles: Option<BTreeMap<String, Profile>>,
+    pub rounding: Option<Rounding>,
+    pub color: Option<Color>,
+    /// Command to page text output through. An empty string disables paging.
+    pub pager: Option<String>,
+    pub norm: Option<Norm>,
+    pub backups: Option<Backups>,
+}
+
+/// How many earlier versions of each stream are kept under the storage root
+///
+/// Besides the `last` versions, the latest version of each of the last `daily` days and `weekly`
+/// weeks is kept.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Backups {
+    pub last: usize,
+    pub daily: usize,
+    pub weekly: usize,
+}
+
+/// The working time expected of you, against which overtime and undertime are computed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Norm {
+    /// Minutes expected on every working day
+    pub minutes: u32,
+    /// The working days, Monday to Friday unless given
+    #[serde(default = "Norm::weekdays")]
+    pub days: Vec<Weekday>,
+}
+
+/// An export profile, describing how hours are laid out for transfer into a timesheet system
+///
+/// Every activity is booked to a row, identified by the values of `columns`, such as a WBS
+/// element and an attendance type. Time not covered by a listed activity is booked to `default`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Profile {
+    pub columns: Vec<String>,
+    pub default: Option<Vec<String>>,
+    #[serde(default)]
+    pub activities: BTreeMap<String, Vec<String>>,
+    pub separator: Option<Separator>,
+    pub decimal_comma: Option<bool>,
+    pub rounding: Option<Rounding>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Separator {
+    Tab,
+    Comma,
+    Semicolon,
+}
+
+/// Round reported time to a multiple of `granularity` minutes
+///
+/// Rounding per interval rounds the in and out time of every session, while rounding per day
+/// rounds the total worked each day. Stored timestamps are never changed.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Rounding {
+    pub granularity: u32,
+    #[serde(default)]
+    pub mode: RoundingMode,
+    #[serde(default)]
+    pub per: RoundingScope,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoundingMode {
+    Up,
+    Down,
+    #[default]
+    Nearest,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoundingScope {
+    Interval,
+    #[default]
+    Day,
+}
+
+impl Separator {
+    pub fn as_char(&self) -> char {
+        match self {
+            Separator::Tab => '\t',
+            Separator::Comma => ',',
+            Separator::Semicolon => ';',
+        }
+    }
+}
+
+impl Rounding {
+    pub fn apply(&self, duration: Duration) -> Duration {
+        let step = i64::from(self.granularity) * 60;
+        if step == 0 {
+            return duration;
+        }
+
+        let secs = duration.num_seconds();
+        let down = secs.div_euclid(step) * step;
+        let rounded = match self.mode {
+            RoundingMode::Down => down,
+            RoundingMode::Up if down == secs => down,
+            RoundingMode::Up => down + step,
+            RoundingMode::Nearest if (secs - down) * 2 >= step => down + step,
+            RoundingMode::Nearest => down,
+        };
+
+        Duration::seconds(rounded)
+    }
+
+    /// Round a timestamp as seen on the wall clock in `zone`
+    pub fn apply_to_timestamp(&self, timestamp: DateTime<Utc>, zone: &Zone) -> DateTime<Utc> {
+        let offset = Duration::seconds(zone.localize(&timestamp).offset().local_minus_utc().into());
+        let since_epoch = Duration::seconds(timestamp.timestamp()) + offset;
+
+        DateTime::UNIX_EPOCH + self.apply(since_epoch) - offset
+    }
+}
+
+impl Norm {
+    fn weekdays() -> Vec<Weekday> {
+        vec![
+            Weekday::Mon,
+            Weekday::Tue,
+            Weekday::Wed,
+            Weekday::Thu,
+            Weekday::Fri,
+        ]
+    }
+
+    /// The time expected on `date`
+    pub fn on(&self, date: NaiveDate) -> Duration {
+        if self.days.contains(&date.weekday()) {
+            Duration::minutes(self.minutes.into())
+        } else {
+            Duration::zero()
+        }
+    }
+}
+
+/// A 37.5 hour week
+impl Default for Norm {
+    fn default() -> Self {
+        Self {
+            minutes: 450,
+            days: Self::weekdays(),
+        }
+    }
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             stream_order: Some(StreamOrder::LastUsed),
+            profiles: None,
+            rounding: None,
+            color: None,
+            pager: None,
+            norm: None,
+            backups: None,
+        }
+    }
+}
+
+impl Default for Backups {
+    fn default() -> Self {
+        Self {
+            last: 10,
+            daily: 7,
+            weekly: 4,
         }
     }
 }
+/// A config as it is stored, with the version of its layout
+#[derive(Serialize, Deserialize)]
+struct _Config {
+    version: u64,
+    #[serde(flatten)]
+    config: Config,
+}
+
 impl Config {
+    /// Load a config of any version
+    pub fn from_buffer(buf: &[u8]) -> Result<Self, Error> {
+        let value =
+            serde_json::from_slice(buf).map_err(|err| Error::DeserializeFailed(err.to_string()))?;
+        let config: _Config = serde_json::from_value(CONFIG.upgrade(value)?)
+            .map_err(|err| Error::DeserializeFailed(err.to_string()))?;
+
+        Ok(config.config)
+    }
+
     pub fn coalesce(
         explicit: Option<Config>,
         local: Option<Config>,
@@ -39,6 +239,138 @@ impl Config {
 
         Self {
             stream_order: rhs.stream_order.or(self.stream_order),
+            profiles: match (self.profiles, rhs.profiles) {
+                // Profiles are merged by name, so a local config can add one without hiding the
+                // global ones
+                (Some(mut profiles), Some(rhs)) => {
+                    profiles.extend(rhs);
+                    Some(profiles)
+                }
+                (profiles, rhs) => rhs.or(profiles),
+            },
+            rounding: rhs.rounding.or(self.rounding),
+            color: rhs.color.or(self.color),
+            pager: rhs.pager.or(self.pager),
+            norm: rhs.norm.or(self.norm),
+            backups: rhs.backups.or(self.backups),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rounding(granularity: u32, mode: RoundingMode) -> Rounding {
+        Rounding {
+            granularity,
+            mode,
+            per: RoundingScope::Day,
+        }
+    }
+
+    #[test]
+    fn round_to_quarter_hours() {
+        let d = |h: i64, m: i64| Duration::hours(h) + Duration::minutes(m);
+
+        let nearest = rounding(15, RoundingMode::Nearest);
+        assert_eq!(nearest.apply(d(7, 37)), d(7, 30));
+        assert_eq!(nearest.apply(d(7, 38)), d(7, 45));
+        assert_eq!(nearest.apply(d(7, 45)), d(7, 45));
+
+        let up = rounding(15, RoundingMode::Up);
+        assert_eq!(up.apply(d(7, 31)), d(7, 45));
+        assert_eq!(up.apply(d(7, 30)), d(7, 30));
+
+        let down = rounding(15, RoundingMode::Down);
+        assert_eq!(down.apply(d(7, 44)), d(7, 30));
+    }
+
+    #[test]
+    fn zero_granularity_keeps_duration() {
+        let duration = Duration::seconds(1234);
+        assert_eq!(
+            rounding(0, RoundingMode::Up).apply(duration),
+            Duration::seconds(1234)
+        );
+    }
+
+    #[test]
+    fn round_timestamp_on_the_wall_clock() {
+        use chrono::TimeZone as _;
+
+        // Kathmandu is UTC+05:45, so wall-clock half hours are not UTC half hours
+        let zone: Zone = "Asia/Kathmandu".parse().unwrap();
+        let timestamp = Utc.with_ymd_and_hms(2025, 9, 1, 3, 0, 0).unwrap();
+
+        let rounded = rounding(30, RoundingMode::Nearest).apply_to_timestamp(timestamp, &zone);
+
+        assert_eq!(zone.localize(&rounded).format("%H:%M").to_string(), "09:00");
+    }
+
+    #[test]
+    fn profile_from_json() {
+        let json = r#"{
+            "profiles": {
+                "sap": {
+                    "columns": ["WBS element", "Att./abs. type"],
+                    "default": ["C-1000", "0800"],
+                    "activities": { "Review": ["P-1234-01", "0800"] },
+                    "separator": "semicolon",
+                    "rounding": { "granularity": 15 }
+                }
+            }
+        }"#;
+
+        let config: Config = serde_json::from_str(json).unwrap();
+        let profile = &config.profiles.unwrap()["sap"];
+
+        assert_eq!(profile.columns.len(), 2);
+        assert_eq!(profile.activities["Review"][0], "P-1234-01");
+        assert_eq!(profile.separator, Some(Separator::Semicolon));
+        assert_eq!(profile.rounding, Some(rounding(15, RoundingMode::Nearest)));
+    }
+
+    #[test]
+    fn explicit_terminal_settings_win() {
+        let global: Config =
+            serde_json::from_str(r#"{ "color": "never", "pager": "less -R" }"#).unwrap();
+        let explicit: Config = serde_json::from_str(r#"{ "pager": "" }"#).unwrap();
+
+        let config = Config::coalesce(Some(explicit), None, Some(global));
+
+        assert_eq!(config.color, Some(Color::Never));
+        assert_eq!(config.pager.as_deref(), Some(""));
+    }
+
+    #[test]
+    fn profiles_merge_by_name() {
+        let global: Config = serde_json::from_str(
+            r#"{ "profiles": { "sap": { "columns": ["Global"] }, "csv": { "columns": [] } } }"#,
+        )
+        .unwrap();
+        let local: Config =
+            serde_json::from_str(r#"{ "profiles": { "sap": { "columns": ["Local"] } } }"#).unwrap();
+
+        let profiles = Config::coalesce(None, Some(local), Some(global))
+            .profiles
+            .unwrap();
+
+        assert_eq!(profiles.len(), 2);
+        assert_eq!(profiles["sap"].columns, vec!["Local".to_string()]);
+    }
+
+    #[test]
+    fn norm_defaults_to_weekdays() {
+        let norm: Norm = serde_json::from_str(r#"{ "minutes": 480 }"#).unwrap();
+        let monday = NaiveDate::from_ymd_opt(2025, 9, 1).unwrap();
+        let sunday = NaiveDate::from_ymd_opt(2025, 9, 7).unwrap();
+
+        assert_eq!(norm.on(monday), Duration::hours(8));
+        assert_eq!(norm.on(sunday), Duration::zero());
+
+        let norm: Norm = serde_json::from_str(r#"{ "minutes": 600, "days": ["sun"] }"#).unwrap();
+        assert_eq!(norm.on(monday), Duration::zero());
+        assert_eq!(norm.on(sunday), Duration::hours(10));
+    }
+}
@@ -1,9 +1,14 @@
 /// Format Module
 ///
-/// This module defines the storage format for config files and data streams through datastructures
-/// in rust. Both config and stream data are stored as JSON files on disk.
+/// This module defines the storage format for config files, data streams and their snapshots
+/// through datastructures in rust. All of them are stored as JSON files on disk, and streams and
+/// configs carry the version of their layout.
 mod config;
+mod snapshot;
 mod stream;
+mod version;
 
 pub use config::*;
+pub use snapshot::*;
 pub use stream::*;
+pub use version::*;
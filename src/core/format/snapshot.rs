@@ -0,0 +1,125 @@
+use serde::Deserialize;
+use serde::Serialize;
+use sha2::Digest as _;
+use sha2::Sha256;
+
+use crate::Entity;
+use crate::Id;
+
+/// The replayed state of a stream up to some event, stored next to the stream so that only newer
+/// events need to be replayed
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// Number of events covered
+    pub events: usize,
+    /// Id of the last covered event, to notice when the stream has been rewritten
+    pub last_event_id: Option<Id>,
+    /// Head of the hash chain after the covered events, which were verified when the snapshot was
+    /// taken, so that only newer events need to be verified
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub head: Option<String>,
+    pub entities: Vec<Entity>,
+    /// Hash of the head and the entities, to notice a snapshot that was changed or damaged
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub digest: Option<String>,
+}
+
+impl Snapshot {
+    pub fn new(
+        events: usize,
+        last_event_id: Option<Id>,
+        head: String,
+        entities: Vec<Entity>,
+    ) -> Self {
+        Self {
+            events,
+            last_event_id,
+            digest: Some(digest(&head, &entities)),
+            head: Some(head),
+            entities,
+        }
+    }
+
+    /// Whether the entities are still the ones the snapshot was taken of. Snapshots without a
+    /// head or digest, as written by earlier versions, can't be told apart from changed ones.
+    pub fn is_intact(&self) -> bool {
+        match (&self.head, &self.digest) {
+            (Some(head), Some(recorded)) => digest(head, &self.entities) == *recorded,
+            _ => false,
+        }
+    }
+}
+
+fn digest(head: &str, entities: &[Entity]) -> String {
+    let json = serde_json::to_vec(entities).expect("entities always serialize");
+    let digest = Sha256::new()
+        .chain_update(head.as_bytes())
+        .chain_update(json)
+        .finalize();
+
+    digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use chrono::TimeZone as _;
+    use chrono::Utc;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn snapshot_schema() {
+        let event_id = Id::new();
+        let entity_id = Id::new();
+        let timestamp = Utc.with_ymd_and_hms(2025, 9, 1, 8, 0, 0).unwrap();
+
+        let snapshot = Snapshot::new(
+            3,
+            Some(event_id.clone()),
+            "abc".to_string(),
+            vec![Entity::Login {
+                id: entity_id.clone(),
+                timestamp,
+                tz: None,
+                note: None,
+                tags: BTreeSet::new(),
+            }],
+        );
+
+        let expected = json!({
+            "events": 3,
+            "last_event_id": event_id,
+            "head": "abc",
+            "entities": [{
+                "type": "login",
+                "entity_id": entity_id,
+                "timestamp": timestamp,
+            }],
+            "digest": snapshot.digest,
+        });
+
+        assert_eq!(serde_json::to_value(&snapshot).unwrap(), expected);
+    }
+
+    #[test]
+    fn changed_entities_are_noticed() {
+        let entity = Entity::Login {
+            id: Id::new(),
+            timestamp: Utc.with_ymd_and_hms(2025, 9, 1, 8, 0, 0).unwrap(),
+            tz: None,
+            note: None,
+            tags: BTreeSet::new(),
+        };
+        let mut snapshot = Snapshot::new(1, Some(Id::new()), "abc".to_string(), vec![entity]);
+        assert!(snapshot.is_intact());
+
+        snapshot.entities[0].set_timestamp(Utc.with_ymd_and_hms(2025, 9, 1, 7, 0, 0).unwrap());
+        assert!(!snapshot.is_intact());
+
+        snapshot.digest = None;
+        assert!(!snapshot.is_intact());
+    }
+}
@@ -1,51 +1,266 @@
+use std::collections::BTreeSet;
+
 use chrono::DateTime;
 use chrono::Duration;
+use chrono::FixedOffset;
 use chrono::Utc;
 use serde::Deserialize;
 use serde::Serialize;
+use sha2::Digest as _;
+use sha2::Sha256;
 
 use crate::Error;
 use crate::Id;
+use crate::Zone;
+
+use super::STREAM;
+
+/// The events of a stream, with the hash chain that links them
+pub struct Stream(Vec<Event>, Chain);
+
+/// Every event recorded since chaining was introduced carries a hash over its canonical JSON and
+/// the hash of the event before it. Older events have none, but are covered by the hashes of
+/// later ones.
+#[derive(Default)]
+struct Chain {
+    hashes: Vec<Option<String>>,
+    head: String,
+    /// Whether events were recorded with hashes, after which the last event must have one, so that
+    /// removing every hash doesn't pass for a stream from before chaining
+    chained: bool,
+}
 
-pub struct Stream(Vec<Event>);
+/// An event as it is stored, with its link in the hash chain
+#[derive(Serialize, Deserialize)]
+pub struct Link {
+    #[serde(flatten)]
+    pub event: Event,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+}
 
 impl Stream {
+    /// An empty stream, chained from its first event
     pub fn new() -> Self {
-        Self(Vec::new())
+        Self(
+            Vec::new(),
+            Chain {
+                chained: true,
+                ..Chain::default()
+            },
+        )
     }
 
+    /// Load a stream of any version, failing if any of its events was altered after it was
+    /// recorded
     pub fn from_buffer(buf: &[u8]) -> Result<Self, Error> {
-        let stream: _Stream =
+        Self::from_buffer_since(buf, None)
+    }
+
+    /// Load a stream like `from_buffer`, see `from_links_since` for `verified`
+    pub fn from_buffer_since(buf: &[u8], verified: Option<(usize, &str)>) -> Result<Self, Error> {
+        let value =
             serde_json::from_slice(buf).map_err(|err| Error::DeserializeFailed(err.to_string()))?;
+        let stream: _Stream = serde_json::from_value(STREAM.upgrade(value)?)
+            .map_err(|err| Error::DeserializeFailed(err.to_string()))?;
 
-        Ok(stream.into())
+        Self::from_links_since(stream.events, stream.chained, verified)
+    }
+
+    /// A stream of stored events, without verifying their hashes
+    pub fn from_links(links: Vec<Link>) -> Self {
+        let chained = links.iter().any(|link| link.hash.is_some());
+
+        Self::from_links_after(links, chained, 0)
+    }
+
+    /// A stream of stored events, verifying their hashes. `chained` is whether the stream was
+    /// stored as chained.
+    ///
+    /// `verified` is a number of events and the head they were verified to hash to before, e.g.
+    /// when a snapshot was taken. If the stream still records that head after as many events, only
+    /// the events after them are hashed. Otherwise the whole stream is verified.
+    pub fn from_links_since(
+        links: Vec<Link>,
+        chained: bool,
+        verified: Option<(usize, &str)>,
+    ) -> Result<Self, Error> {
+        let trusted = match verified {
+            Some((events, head))
+                if events > 0
+                    && links.get(events - 1).and_then(|link| link.hash.as_deref())
+                        == Some(head) =>
+            {
+                events
+            }
+            _ => 0,
+        };
+
+        let stream = Self::from_links_after(links, chained, trusted);
+        stream.verify_after(trusted)?;
+
+        Ok(stream)
+    }
+
+    /// Only hashes the events after the first `trusted`, whose recorded hashes are taken as is
+    fn from_links_after(links: Vec<Link>, chained: bool, trusted: usize) -> Self {
+        let mut stream = Self::new();
+        stream.1.chained = chained;
+        for (index, link) in links.into_iter().enumerate() {
+            stream.1.head = match (index < trusted, &link.hash) {
+                (true, Some(recorded)) => recorded.clone(),
+                (true, None) => stream.1.head,
+                (false, _) => hash(&stream.1.head, &link.event),
+            };
+            stream.1.hashes.push(link.hash);
+            stream.0.push(link.event);
+        }
+
+        stream
     }
 
     pub fn to_buffer(&self) -> Result<Vec<u8>, Error> {
-        serde_json::to_vec(&self.0).map_err(|err| Error::SerializeFailed(err.to_string()))
+        let stream = _Stream {
+            version: STREAM.current(),
+            chained: self.1.chained,
+            events: self.links(),
+        };
+
+        serde_json::to_vec(&stream).map_err(|err| Error::SerializeFailed(err.to_string()))
+    }
+
+    pub fn events(&self) -> &[Event] {
+        &self.0
+    }
+
+    /// The events as they are stored
+    pub fn links(&self) -> Vec<Link> {
+        self.0
+            .iter()
+            .zip(&self.1.hashes)
+            .map(|(event, hash)| Link {
+                event: event.clone(),
+                hash: hash.clone(),
+            })
+            .collect()
+    }
+
+    /// The hash of the last event, which covers the whole history of the stream
+    pub fn head(&self) -> &str {
+        &self.1.head
+    }
+
+    pub fn is_chained(&self) -> bool {
+        self.1.chained
+    }
+
+    /// The hash recorded with the last of the first `events` events, if it has one
+    pub fn hash_after(&self, events: usize) -> Option<&str> {
+        let index = events.checked_sub(1)?;
+        self.1.hashes.get(index)?.as_deref()
+    }
+
+    /// The number of events covered by `hash`, if it is the hash of one of the events
+    pub fn covered_by(&self, hash: &str) -> Option<usize> {
+        let mut head = String::new();
+
+        for (index, event) in self.0.iter().enumerate() {
+            head = self::hash(&head, event);
+            if head == hash {
+                return Some(index + 1);
+            }
+        }
+
+        None
+    }
+
+    /// Whether the history of `earlier` is no longer the start of this stream, so that hashes
+    /// noted down from it don't verify it
+    pub fn rewrites(&self, earlier: &Stream) -> bool {
+        !earlier.0.is_empty() && self.covered_by(earlier.head()) != Some(earlier.0.len())
+    }
+
+    /// Check that every recorded hash matches the events up to it. The first event that doesn't
+    /// is reported, as is a missing hash after the first one or at the end of a chained stream.
+    pub fn verify(&self) -> Result<(), Error> {
+        self.verify_after(0)
+    }
+
+    /// Like `verify`, trusting the first `trusted` events, the last of which must have a hash
+    fn verify_after(&self, trusted: usize) -> Result<(), Error> {
+        let mut head = match trusted {
+            0 => String::new(),
+            n => self.1.hashes[n - 1].clone().unwrap_or_default(),
+        };
+        let mut chained = trusted > 0;
+
+        for (index, (event, recorded)) in
+            self.0.iter().zip(&self.1.hashes).enumerate().skip(trusted)
+        {
+            head = hash(&head, event);
+
+            let reason = match recorded {
+                Some(recorded) if *recorded == head => None,
+                Some(_) => Some("it or an earlier event was altered, or events were removed"),
+                None if chained || (self.1.chained && index + 1 == self.0.len()) => {
+                    Some("its hash was removed")
+                }
+                None => None,
+            };
+
+            if let Some(reason) = reason {
+                return Err(Error::BrokenChain(format!(
+                    "event {} of {} ({}, recorded {}): {}",
+                    index + 1,
+                    self.0.len(),
+                    event.id(),
+                    event.created_at().to_rfc3339(),
+                    reason
+                )));
+            }
+
+            chained |= recorded.is_some();
+        }
+
+        Ok(())
     }
 
     pub fn push(&mut self, event: Event) -> Result<(), Error> {
-        let _ = self.validate(&event)?;
+        self.validate(&event)?;
+
+        let head = hash(&self.1.head, &event);
+        self.1.hashes.push(Some(head.clone()));
+        self.1.head = head;
+        self.1.chained = true;
         self.0.push(event);
 
         Ok(())
     }
 
+    /// Entities timestamped before this can't be changed, if the stream is locked
+    pub fn locked_until(&self) -> Option<DateTime<Utc>> {
+        self.0.iter().fold(None, |locked, event| match event {
+            Event::Lock { until, .. } => locked.max(Some(*until)),
+            Event::Unlock { until, .. } => locked.min(*until),
+            _ => locked,
+        })
+    }
+
     #[rustfmt::skip]
     fn validate(&self, event: &Event) -> Result<(), Error> {
         validation::edit_references_create_id(self, event)?;
         validation::create_id_is_unique(self, event)?;
         validation::delete_references_create_id(self, event)?;
         validation::delete_only_once(self, event)?;
+        validation::outside_locked_period(self, event)?;
 
         Ok(())
     }
 }
 
-impl From<_Stream> for Stream {
-    fn from(value: _Stream) -> Self {
-        Stream(value.0)
+impl Default for Stream {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -81,6 +296,68 @@ pub enum Event {
         created_at: DateTime<Utc>,
         entity_id: Id,
     },
+    /// Entities timestamped before `until` can no longer be created, edited or deleted, e.g.
+    /// because their hours were submitted
+    Lock {
+        #[serde(rename = "event_id")]
+        id: Id,
+        created_at: DateTime<Utc>,
+        until: DateTime<Utc>,
+    },
+    /// Lift locks from `until` on, or entirely without it
+    Unlock {
+        #[serde(rename = "event_id")]
+        id: Id,
+        created_at: DateTime<Utc>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        until: Option<DateTime<Utc>>,
+    },
+    /// The hours of `from..to` were handed in, `worked` in total
+    Submit {
+        #[serde(rename = "event_id")]
+        id: Id,
+        created_at: DateTime<Utc>,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        #[serde(with = "duration_seconds")]
+        worked: Duration,
+    },
+}
+
+impl Event {
+    #[rustfmt::skip]
+    pub fn id(&self) -> &Id {
+        match self {
+              Event::Create { id, .. }
+            | Event::Edit   { id, .. }
+            | Event::Delete { id, .. }
+            | Event::Lock   { id, .. }
+            | Event::Unlock { id, .. }
+            | Event::Submit { id, .. } => id,
+        }
+    }
+
+    #[rustfmt::skip]
+    pub fn created_at(&self) -> &DateTime<Utc> {
+        match self {
+              Event::Create { created_at, .. }
+            | Event::Edit   { created_at, .. }
+            | Event::Delete { created_at, .. }
+            | Event::Lock   { created_at, .. }
+            | Event::Unlock { created_at, .. }
+            | Event::Submit { created_at, .. } => created_at,
+        }
+    }
+
+    /// The id of the entity the event creates, edits or deletes. Locks and submissions belong to
+    /// no entity.
+    pub fn entity_id(&self) -> Option<&Id> {
+        match self {
+            Event::Create { entity, .. } | Event::Edit { entity, .. } => Some(entity.id()),
+            Event::Delete { entity_id, .. } => Some(entity_id),
+            Event::Lock { .. } | Event::Unlock { .. } | Event::Submit { .. } => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -90,16 +367,32 @@ pub enum Entity {
         #[serde(rename = "entity_id")]
         id: Id,
         timestamp: DateTime<Utc>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tz: Option<Zone>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        note: Option<String>,
+        #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+        tags: BTreeSet<String>,
     },
     Logout {
         #[serde(rename = "entity_id")]
         id: Id,
         timestamp: DateTime<Utc>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tz: Option<Zone>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        note: Option<String>,
     },
     Break {
         #[serde(rename = "entity_id")]
         id: Id,
         timestamp: DateTime<Utc>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tz: Option<Zone>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        note: Option<String>,
+        #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+        tags: BTreeSet<String>,
 
         #[serde(with = "duration_seconds")]
         duration: Duration,
@@ -109,6 +402,12 @@ pub enum Entity {
         #[serde(rename = "entity_id")]
         id: Id,
         timestamp: DateTime<Utc>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tz: Option<Zone>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        note: Option<String>,
+        #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+        tags: BTreeSet<String>,
         #[serde(with = "duration_seconds")]
         duration: Duration,
         value: String,
@@ -116,10 +415,134 @@ pub enum Entity {
     },
 }
 
+impl Entity {
+    #[rustfmt::skip]
+    pub fn id(&self) -> &Id {
+        match self {
+              Entity::Login    { id, .. }
+            | Entity::Logout   { id, .. }
+            | Entity::Break    { id, .. }
+            | Entity::Activity { id, .. } => id,
+        }
+    }
+
+    #[rustfmt::skip]
+    pub fn timestamp(&self) -> &DateTime<Utc> {
+        match self {
+              Entity::Login    { timestamp, .. }
+            | Entity::Logout   { timestamp, .. }
+            | Entity::Break    { timestamp, .. }
+            | Entity::Activity { timestamp, .. } => timestamp,
+        }
+    }
+
+    #[rustfmt::skip]
+    pub fn set_timestamp(&mut self, value: DateTime<Utc>) {
+        match self {
+              Entity::Login    { timestamp, .. }
+            | Entity::Logout   { timestamp, .. }
+            | Entity::Break    { timestamp, .. }
+            | Entity::Activity { timestamp, .. } => *timestamp = value,
+        }
+    }
+
+    /// The timezone the entity was recorded in. Entities recorded before zones were tracked have
+    /// none.
+    #[rustfmt::skip]
+    pub fn tz(&self) -> Option<Zone> {
+        match self {
+              Entity::Login    { tz, .. }
+            | Entity::Logout   { tz, .. }
+            | Entity::Break    { tz, .. }
+            | Entity::Activity { tz, .. } => *tz,
+        }
+    }
+
+    #[rustfmt::skip]
+    pub fn set_tz(&mut self, value: Option<Zone>) {
+        match self {
+              Entity::Login    { tz, .. }
+            | Entity::Logout   { tz, .. }
+            | Entity::Break    { tz, .. }
+            | Entity::Activity { tz, .. } => *tz = value,
+        }
+    }
+
+    /// Free-text explanation recorded with the entity
+    #[rustfmt::skip]
+    pub fn note(&self) -> Option<&str> {
+        match self {
+              Entity::Login    { note, .. }
+            | Entity::Logout   { note, .. }
+            | Entity::Break    { note, .. }
+            | Entity::Activity { note, .. } => note.as_deref(),
+        }
+    }
+
+    #[rustfmt::skip]
+    pub fn set_note(&mut self, value: Option<String>) {
+        match self {
+              Entity::Login    { note, .. }
+            | Entity::Logout   { note, .. }
+            | Entity::Break    { note, .. }
+            | Entity::Activity { note, .. } => *note = value,
+        }
+    }
+
+    /// The tags of the entity. A logout has none, as it belongs to the session of its login.
+    #[rustfmt::skip]
+    pub fn tags(&self) -> &BTreeSet<String> {
+        static NONE: BTreeSet<String> = BTreeSet::new();
+
+        match self {
+              Entity::Login    { tags, .. }
+            | Entity::Break    { tags, .. }
+            | Entity::Activity { tags, .. } => tags,
+            Entity::Logout { .. } => &NONE,
+        }
+    }
+
+    /// Replace the tags of the entity. A logout has none, so they are dropped.
+    #[rustfmt::skip]
+    pub fn set_tags(&mut self, value: BTreeSet<String>) {
+        match self {
+              Entity::Login    { tags, .. }
+            | Entity::Break    { tags, .. }
+            | Entity::Activity { tags, .. } => *tags = value,
+            Entity::Logout { .. } => {}
+        }
+    }
+
+    /// The timestamp as a wall-clock time. Uses `zone` if given, otherwise the zone the entity was
+    /// recorded in, falling back to the zone of this machine.
+    pub fn local_timestamp(&self, zone: Option<&Zone>) -> DateTime<FixedOffset> {
+        let zone = zone.copied().or(self.tz()).unwrap_or_else(Zone::local);
+        zone.localize(self.timestamp())
+    }
+}
+
 #[derive(Serialize, Deserialize)]
-struct _Stream(Vec<Event>);
+struct _Stream {
+    version: u64,
+    #[serde(default)]
+    chained: bool,
+    events: Vec<Link>,
+}
+
+/// The hash of `event` following `previous`, in hex
+fn hash(previous: &str, event: &Event) -> String {
+    // Events serialize the same way every time: fields in declaration order, tags sorted
+    let json = serde_json::to_vec(event).expect("events always serialize");
+    let digest = Sha256::new()
+        .chain_update(previous.as_bytes())
+        .chain_update(json)
+        .finalize();
+
+    digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
 
-mod duration_seconds {
+/// Durations as whole seconds, as in the stream files
+pub(crate) mod duration_seconds {
     use chrono::Duration;
     use serde::{self, Deserialize, Deserializer, Serializer};
 
@@ -204,6 +627,47 @@ mod validation {
         }
     }
 
+    pub fn outside_locked_period(stream: &Stream, event: &Event) -> Result<(), Error> {
+        let Some(until) = stream.locked_until() else {
+            return Ok(());
+        };
+
+        // Moving an entity out of the locked period changes it as much as moving it in
+        let (id, touched) = match event {
+            Event::Create { entity, .. } => (entity.id(), vec![*entity.timestamp()]),
+            Event::Edit { entity, .. } => {
+                let mut touched = vec![*entity.timestamp()];
+                touched.extend(current_timestamp(stream, entity.id()));
+                (entity.id(), touched)
+            }
+            Event::Delete { entity_id, .. } => (
+                entity_id,
+                current_timestamp(stream, entity_id).into_iter().collect(),
+            ),
+            Event::Lock { .. } | Event::Unlock { .. } | Event::Submit { .. } => return Ok(()),
+        };
+
+        match touched.iter().find(|timestamp| **timestamp < until) {
+            Some(timestamp) => Err(Error::PeriodLocked(format!(
+                "{} at {} is before the lock at {}, unlock the stream first",
+                id,
+                timestamp.to_rfc3339(),
+                until.to_rfc3339()
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// The timestamp of an entity as of the latest event that created or edited it
+    fn current_timestamp(stream: &Stream, id: &Id) -> Option<DateTime<Utc>> {
+        stream.0.iter().rev().find_map(|event| match event {
+            Event::Create { entity, .. } | Event::Edit { entity, .. } if entity.id() == id => {
+                Some(*entity.timestamp())
+            }
+            _ => None,
+        })
+    }
+
     fn delete_event_exists(stream: &Stream, id: &Id) -> bool {
         stream.0.iter().rev().any(|event| match event {
             Event::Delete { entity_id, .. } => entity_id == id,
@@ -285,6 +749,9 @@ mod schema {
             created_at,
             entity: Entity::Login {
                 timestamp,
+                tz: None,
+                note: None,
+                tags: BTreeSet::new(),
                 id: entity_id,
             },
         };
@@ -313,6 +780,8 @@ mod schema {
             created_at,
             entity: Entity::Logout {
                 timestamp,
+                tz: None,
+                note: None,
                 id: entity_id,
             },
         };
@@ -345,6 +814,9 @@ mod schema {
             created_at,
             entity: Entity::Break {
                 timestamp,
+                tz: None,
+                note: None,
+                tags: BTreeSet::new(),
                 duration,
                 id: entity_id,
                 autoinsert,
@@ -381,6 +853,9 @@ mod schema {
             entity: Entity::Activity {
                 id: entity_id,
                 timestamp,
+                tz: None,
+                note: None,
+                tags: BTreeSet::new(),
                 duration,
                 value: "Coding".to_string(),
                 autoinsert,
@@ -390,6 +865,224 @@ mod schema {
         assert_json_eq(&to_json(&event), &expected);
     }
 
+    #[test]
+    fn login_create_with_tz() {
+        let event_id = Id::new();
+        let entity_id = Id::new();
+        let created_at = fixed_time();
+        let timestamp = fixed_time();
+
+        let expected = json!({
+            "op": "create",
+            "event_id": event_id,
+            "created_at": created_at,
+            "entity_id": entity_id,
+            "type": "login",
+            "timestamp": timestamp,
+            "tz": "Europe/Oslo"
+        });
+
+        let event = Event::Create {
+            id: event_id,
+            created_at,
+            entity: Entity::Login {
+                timestamp,
+                id: entity_id,
+                tz: Some("Europe/Oslo".parse().unwrap()),
+                note: None,
+                tags: BTreeSet::new(),
+            },
+        };
+
+        assert_json_eq(&to_json(&event), &expected);
+    }
+
+    #[test]
+    fn logout_create_with_fixed_offset_tz() {
+        let event_id = Id::new();
+        let entity_id = Id::new();
+        let created_at = fixed_time();
+        let timestamp = fixed_time();
+
+        let expected = json!({
+            "op": "create",
+            "event_id": event_id,
+            "created_at": created_at,
+            "entity_id": entity_id,
+            "type": "logout",
+            "timestamp": timestamp,
+            "tz": "+05:30"
+        });
+
+        let event = Event::Create {
+            id: event_id,
+            created_at,
+            entity: Entity::Logout {
+                timestamp,
+                id: entity_id,
+                tz: Some("+05:30".parse().unwrap()),
+                note: None,
+            },
+        };
+
+        assert_json_eq(&to_json(&event), &expected);
+    }
+
+    #[test]
+    fn login_create_without_tz_deserializes() {
+        let entity_id = Id::new();
+        let json = json!([{
+            "op": "create",
+            "event_id": Id::new(),
+            "created_at": fixed_time(),
+            "entity_id": entity_id,
+            "type": "login",
+            "timestamp": fixed_time()
+        }]);
+
+        let stream = Stream::from_buffer(json.to_string().as_bytes()).unwrap();
+
+        match &stream.0[0] {
+            Event::Create { entity, .. } => assert_eq!(
+                entity,
+                &Entity::Login {
+                    id: entity_id,
+                    timestamp: fixed_time(),
+                    tz: None,
+                    note: None,
+                    tags: BTreeSet::new(),
+                }
+            ),
+            _ => panic!("expected create event"),
+        }
+    }
+
+    #[test]
+    fn logout_create_with_note() {
+        let event_id = Id::new();
+        let entity_id = Id::new();
+        let created_at = fixed_time();
+        let timestamp = fixed_time();
+
+        let expected = json!({
+            "op": "create",
+            "event_id": event_id,
+            "created_at": created_at,
+            "entity_id": entity_id,
+            "type": "logout",
+            "timestamp": timestamp,
+            "note": "left early for the dentist"
+        });
+
+        let event = Event::Create {
+            id: event_id,
+            created_at,
+            entity: Entity::Logout {
+                id: entity_id,
+                timestamp,
+                tz: None,
+                note: Some("left early for the dentist".to_string()),
+            },
+        };
+
+        assert_json_eq(&to_json(&event), &expected);
+    }
+
+    #[test]
+    fn break_create_with_tz_and_note() {
+        let event_id = Id::new();
+        let entity_id = Id::new();
+        let created_at = fixed_time();
+        let timestamp = fixed_time();
+
+        let expected = json!({
+            "op": "create",
+            "event_id": event_id,
+            "created_at": created_at,
+            "entity_id": entity_id,
+            "type": "break",
+            "timestamp": timestamp,
+            "tz": "Europe/Oslo",
+            "note": "on-call incident",
+            "duration": 900,
+            "autoinsert": false
+        });
+
+        let event = Event::Create {
+            id: event_id,
+            created_at,
+            entity: Entity::Break {
+                id: entity_id,
+                timestamp,
+                tz: Some("Europe/Oslo".parse().unwrap()),
+                note: Some("on-call incident".to_string()),
+                tags: BTreeSet::new(),
+                duration: Duration::seconds(900),
+                autoinsert: false,
+            },
+        };
+
+        assert_json_eq(&to_json(&event), &expected);
+    }
+
+    #[test]
+    fn login_create_with_tags() {
+        let event_id = Id::new();
+        let entity_id = Id::new();
+        let created_at = fixed_time();
+        let timestamp = fixed_time();
+
+        let expected = json!({
+            "op": "create",
+            "event_id": event_id,
+            "created_at": created_at,
+            "entity_id": entity_id,
+            "type": "login",
+            "timestamp": timestamp,
+            "tags": ["acme", "billable"]
+        });
+
+        let event = Event::Create {
+            id: event_id,
+            created_at,
+            entity: Entity::Login {
+                id: entity_id,
+                timestamp,
+                tz: None,
+                note: None,
+                tags: ["billable", "acme"].map(String::from).into(),
+            },
+        };
+
+        assert_json_eq(&to_json(&event), &expected);
+    }
+
+    #[test]
+    fn activity_create_without_note_deserializes() {
+        let entity_id = Id::new();
+        let json = json!([{
+            "op": "create",
+            "event_id": Id::new(),
+            "created_at": fixed_time(),
+            "entity_id": entity_id,
+            "type": "activity",
+            "timestamp": fixed_time(),
+            "duration": 1200,
+            "value": "Coding",
+            "autoinsert": false
+        }]);
+
+        let stream = Stream::from_buffer(json.to_string().as_bytes()).unwrap();
+
+        match &stream.0[0] {
+            Event::Create { entity, .. } => {
+                assert_eq!(entity.note(), None);
+                assert!(entity.tags().is_empty());
+            }
+            _ => panic!("expected create event"),
+        }
+    }
+
     // -------- Edit variants --------
 
     #[test]
@@ -413,6 +1106,9 @@ mod schema {
             created_at,
             entity: Entity::Login {
                 timestamp,
+                tz: None,
+                note: None,
+                tags: BTreeSet::new(),
                 id: entity_id,
             },
         };
@@ -441,6 +1137,8 @@ mod schema {
             created_at,
             entity: Entity::Logout {
                 timestamp,
+                tz: None,
+                note: None,
                 id: entity_id,
             },
         };
@@ -473,6 +1171,9 @@ mod schema {
             created_at,
             entity: Entity::Break {
                 timestamp,
+                tz: None,
+                note: None,
+                tags: BTreeSet::new(),
                 duration,
                 id: entity_id,
                 autoinsert,
@@ -509,6 +1210,9 @@ mod schema {
             entity: Entity::Activity {
                 id: entity_id,
                 timestamp,
+                tz: None,
+                note: None,
+                tags: BTreeSet::new(),
                 duration,
                 value: "Review".to_string(),
                 autoinsert,
@@ -607,10 +1311,81 @@ mod schema {
 
         assert_json_eq(&to_json(&event), &expected);
     }
+
+    #[test]
+    fn lock() {
+        let event_id = Id::new();
+        let created_at = fixed_time();
+        let until = fixed_time();
+
+        let expected = json!({
+            "op": "lock",
+            "event_id": event_id,
+            "created_at": created_at,
+            "until": until
+        });
+
+        let event = Event::Lock {
+            id: event_id,
+            created_at,
+            until,
+        };
+
+        assert_json_eq(&to_json(&event), &expected);
+    }
+
+    #[test]
+    fn submit() {
+        let event_id = Id::new();
+        let created_at = fixed_time();
+        let from = fixed_time();
+        let to = fixed_time() + Duration::days(7);
+
+        let expected = json!({
+            "op": "submit",
+            "event_id": event_id,
+            "created_at": created_at,
+            "from": from,
+            "to": to,
+            "worked": 144000
+        });
+
+        let event = Event::Submit {
+            id: event_id,
+            created_at,
+            from,
+            to,
+            worked: Duration::hours(40),
+        };
+
+        assert_json_eq(&to_json(&event), &expected);
+    }
+
+    #[test]
+    fn unlock_everything() {
+        let event_id = Id::new();
+        let created_at = fixed_time();
+
+        let expected = json!({
+            "op": "unlock",
+            "event_id": event_id,
+            "created_at": created_at
+        });
+
+        let event = Event::Unlock {
+            id: event_id,
+            created_at,
+            until: None,
+        };
+
+        assert_json_eq(&to_json(&event), &expected);
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use chrono::TimeZone as _;
+
     use super::*;
 
     #[test]
@@ -623,6 +1398,9 @@ mod tests {
             entity: Entity::Login {
                 id: id.clone(),
                 timestamp: chrono::Utc::now(),
+                tz: None,
+                note: None,
+                tags: BTreeSet::new(),
             },
         };
 
@@ -634,6 +1412,9 @@ mod tests {
             entity: Entity::Login {
                 id: id.clone(),
                 timestamp: chrono::Utc::now(),
+                tz: None,
+                note: None,
+                tags: BTreeSet::new(),
             },
         };
 
@@ -652,6 +1433,9 @@ mod tests {
             entity: Entity::Login {
                 id: id.clone(),
                 timestamp: chrono::Utc::now(),
+                tz: None,
+                note: None,
+                tags: BTreeSet::new(),
             },
         };
 
@@ -672,6 +1456,9 @@ mod tests {
             entity: Entity::Login {
                 id: id.clone(),
                 timestamp: chrono::Utc::now(),
+                tz: None,
+                note: None,
+                tags: BTreeSet::new(),
             },
         };
 
@@ -683,6 +1470,9 @@ mod tests {
             entity: Entity::Login {
                 id: id.clone(),
                 timestamp: chrono::Utc::now(),
+                tz: None,
+                note: None,
+                tags: BTreeSet::new(),
             },
         };
         stream.push(event).unwrap();
@@ -693,6 +1483,9 @@ mod tests {
             entity: Entity::Login {
                 id: id.clone(),
                 timestamp: chrono::Utc::now(),
+                tz: None,
+                note: None,
+                tags: BTreeSet::new(),
             },
         };
         stream.push(event).unwrap();
@@ -708,6 +1501,9 @@ mod tests {
             entity: Entity::Login {
                 id: Id::new(),
                 timestamp: chrono::Utc::now(),
+                tz: None,
+                note: None,
+                tags: BTreeSet::new(),
             },
         };
 
@@ -725,6 +1521,9 @@ mod tests {
             entity: Entity::Login {
                 id: id.clone(),
                 timestamp: chrono::Utc::now(),
+                tz: None,
+                note: None,
+                tags: BTreeSet::new(),
             },
         };
 
@@ -736,6 +1535,9 @@ mod tests {
             entity: Entity::Login {
                 id: id.clone(),
                 timestamp: chrono::Utc::now(),
+                tz: None,
+                note: None,
+                tags: BTreeSet::new(),
             },
         };
 
@@ -773,6 +1575,9 @@ mod tests {
             entity: Entity::Login {
                 id: id.clone(),
                 timestamp: chrono::Utc::now(),
+                tz: None,
+                note: None,
+                tags: BTreeSet::new(),
             },
         };
         stream.push(event).unwrap();
@@ -796,6 +1601,9 @@ mod tests {
             entity: Entity::Login {
                 id: id.clone(),
                 timestamp: chrono::Utc::now(),
+                tz: None,
+                note: None,
+                tags: BTreeSet::new(),
             },
         };
         stream.push(event).unwrap();
@@ -805,11 +1613,245 @@ mod tests {
             created_at: chrono::Utc::now(),
             entity_id: id.clone(),
         };
-        let _ = stream.push(event.clone()).unwrap();
+        stream.push(event.clone()).unwrap();
         let err = stream.push(event).unwrap_err();
         match err {
             super::Error::EntityAlreadyDeleted(eid) => assert_eq!(eid, id),
             other => panic!("expected EventDoesNotExist, got {:?}", other),
         }
     }
+
+    fn login(note: &str) -> Event {
+        Event::Create {
+            id: Id::new(),
+            created_at: chrono::Utc::now(),
+            entity: Entity::Login {
+                id: Id::new(),
+                timestamp: chrono::Utc::now(),
+                tz: None,
+                note: Some(note.to_string()),
+                tags: BTreeSet::new(),
+            },
+        }
+    }
+
+    #[test]
+    fn chained_stream_round_trips() {
+        let mut stream = Stream::new();
+        stream.push(login("first")).unwrap();
+        stream.push(login("second")).unwrap();
+
+        let loaded = Stream::from_buffer(&stream.to_buffer().unwrap()).unwrap();
+
+        assert_eq!(loaded.head(), stream.head());
+        assert_eq!(loaded.covered_by(stream.head()), Some(2));
+        assert_eq!(loaded.covered_by("0000"), None);
+        assert!(loaded.links().iter().all(|link| link.hash.is_some()));
+    }
+
+    #[test]
+    fn altered_event_breaks_the_chain() {
+        let mut stream = Stream::new();
+        for note in ["first", "second", "third"] {
+            stream.push(login(note)).unwrap();
+        }
+
+        let buf = String::from_utf8(stream.to_buffer().unwrap()).unwrap();
+        let buf = buf.replace("\"second\"", "\"altered\"");
+
+        match Stream::from_buffer(buf.as_bytes()) {
+            Err(Error::BrokenChain(message)) => assert!(message.starts_with("event 2 of 3")),
+            Err(other) => panic!("expected BrokenChain, got {:?}", other),
+            Ok(_) => panic!("expected BrokenChain"),
+        }
+    }
+
+    #[test]
+    fn events_recorded_before_chaining_are_covered_by_later_ones() {
+        let unchained = serde_json::to_vec(&vec![login("first")]).unwrap();
+        let mut stream = Stream::from_buffer(&unchained).unwrap();
+        stream.push(login("second")).unwrap();
+
+        let links = stream.links();
+        assert!(links[0].hash.is_none());
+        assert!(links[1].hash.is_some());
+
+        let buf = String::from_utf8(stream.to_buffer().unwrap()).unwrap();
+        assert!(Stream::from_buffer(buf.as_bytes()).is_ok());
+        let buf = buf.replace("\"first\"", "\"altered\"");
+        assert!(matches!(
+            Stream::from_buffer(buf.as_bytes()),
+            Err(Error::BrokenChain(_))
+        ));
+    }
+
+    #[test]
+    fn removed_hash_breaks_the_chain() {
+        let mut stream = Stream::new();
+        stream.push(login("first")).unwrap();
+        stream.push(login("second")).unwrap();
+
+        let mut links = stream.links();
+        links[1].hash = None;
+
+        assert!(matches!(
+            Stream::from_links(links).verify(),
+            Err(Error::BrokenChain(_))
+        ));
+    }
+
+    #[test]
+    fn removing_every_hash_breaks_a_chained_stream() {
+        let mut stream = Stream::new();
+        stream.push(login("first")).unwrap();
+        stream.push(login("second")).unwrap();
+
+        let mut value: serde_json::Value =
+            serde_json::from_slice(&stream.to_buffer().unwrap()).unwrap();
+        assert_eq!(value["chained"], true);
+        for event in value["events"].as_array_mut().unwrap() {
+            event.as_object_mut().unwrap().remove("hash");
+        }
+
+        assert!(matches!(
+            Stream::from_buffer(&serde_json::to_vec(&value).unwrap()),
+            Err(Error::BrokenChain(_))
+        ));
+    }
+
+    #[test]
+    fn only_changing_earlier_events_rewrites_the_history() {
+        let (first, second) = (login("first"), login("second"));
+        let mut earlier = Stream::new();
+        earlier.push(first.clone()).unwrap();
+
+        let mut appended = Stream::from_links(earlier.links());
+        appended.push(second.clone()).unwrap();
+        assert!(!appended.rewrites(&earlier));
+        assert!(!appended.rewrites(&Stream::new()));
+
+        let mut reordered = Stream::new();
+        reordered.push(second).unwrap();
+        reordered.push(first).unwrap();
+        assert!(reordered.rewrites(&earlier));
+    }
+
+    #[test]
+    fn verified_prefix_is_not_hashed_again() {
+        let mut stream = Stream::new();
+        stream.push(login("first")).unwrap();
+        stream.push(login("second")).unwrap();
+        let verified = (1, stream.links()[0].hash.clone().unwrap());
+
+        let mut links = stream.links();
+        links[1].event = login("altered");
+        let resumed =
+            Stream::from_links_since(stream.links(), true, Some((1, &verified.1))).unwrap();
+        assert_eq!(resumed.head(), stream.head());
+        assert!(matches!(
+            Stream::from_links_since(links, true, Some((1, &verified.1))),
+            Err(Error::BrokenChain(_))
+        ));
+
+        // A head the stream doesn't record means it was rewritten, so it is verified in full
+        let mut links = stream.links();
+        links[0].event = login("altered");
+        assert!(matches!(
+            Stream::from_links_since(links, true, Some((1, "other"))),
+            Err(Error::BrokenChain(_))
+        ));
+    }
+
+    fn login_at(id: &Id, hour: u32) -> Entity {
+        Entity::Login {
+            id: id.clone(),
+            timestamp: Utc.with_ymd_and_hms(2025, 9, 1, hour, 0, 0).unwrap(),
+            tz: None,
+            note: None,
+            tags: BTreeSet::new(),
+        }
+    }
+
+    fn lock(hour: u32) -> Event {
+        Event::Lock {
+            id: Id::new(),
+            created_at: chrono::Utc::now(),
+            until: Utc.with_ymd_and_hms(2025, 9, 1, hour, 0, 0).unwrap(),
+        }
+    }
+
+    fn unlock(hour: Option<u32>) -> Event {
+        Event::Unlock {
+            id: Id::new(),
+            created_at: chrono::Utc::now(),
+            until: hour.map(|hour| Utc.with_ymd_and_hms(2025, 9, 1, hour, 0, 0).unwrap()),
+        }
+    }
+
+    fn create(entity: Entity) -> Event {
+        Event::Create {
+            id: Id::new(),
+            created_at: chrono::Utc::now(),
+            entity,
+        }
+    }
+
+    fn edit(entity: Entity) -> Event {
+        Event::Edit {
+            id: Id::new(),
+            created_at: chrono::Utc::now(),
+            entity,
+        }
+    }
+
+    fn delete(entity_id: &Id) -> Event {
+        Event::Delete {
+            id: Id::new(),
+            created_at: chrono::Utc::now(),
+            entity_id: entity_id.clone(),
+        }
+    }
+
+    #[test]
+    fn locked_period_rejects_changes() {
+        let (before, after) = (Id::new(), Id::new());
+        let mut stream = Stream::new();
+        stream.push(create(login_at(&before, 8))).unwrap();
+        stream.push(create(login_at(&after, 14))).unwrap();
+        stream.push(lock(12)).unwrap();
+
+        let rejected = [
+            create(login_at(&Id::new(), 9)),
+            edit(login_at(&before, 9)),
+            delete(&before),
+            // Moving an entity into the locked period, or out of it, changes the period
+            edit(login_at(&after, 9)),
+        ];
+        for event in rejected {
+            assert!(matches!(stream.push(event), Err(Error::PeriodLocked(_))));
+        }
+
+        stream.push(create(login_at(&Id::new(), 12))).unwrap();
+        stream.push(edit(login_at(&after, 15))).unwrap();
+        stream.push(delete(&after)).unwrap();
+    }
+
+    #[test]
+    fn unlock_lifts_locks_from_a_point_on() {
+        let mut stream = Stream::new();
+        stream.push(lock(12)).unwrap();
+        stream.push(lock(10)).unwrap();
+        assert_eq!(
+            stream.locked_until(),
+            Some(Utc.with_ymd_and_hms(2025, 9, 1, 12, 0, 0).unwrap())
+        );
+
+        stream.push(unlock(Some(9))).unwrap();
+        stream.push(create(login_at(&Id::new(), 9))).unwrap();
+        assert!(stream.push(create(login_at(&Id::new(), 8))).is_err());
+
+        stream.push(unlock(None)).unwrap();
+        assert_eq!(stream.locked_until(), None);
+        stream.push(create(login_at(&Id::new(), 8))).unwrap();
+    }
 }
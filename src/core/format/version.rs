@@ -0,0 +1,196 @@
+use serde_json::Value;
+use serde_json::json;
+
+use crate::Error;
+
+/// Turns the JSON of one version of a file into that of the next
+pub type Migration = fn(Value) -> Result<Value, Error>;
+
+/// The versions a kind of file went through
+///
+/// Files are versioned by a `version` field at the top. Files written before versioning have
+/// none and are version 0. The migration at index `n` upgrades version `n` to `n + 1`, so the
+/// current version is the number of migrations.
+pub struct Schema {
+    pub name: &'static str,
+    migrations: &'static [Migration],
+    pretty: bool,
+}
+
+/// Streams were a bare array of events, then didn't record whether they were chained
+pub const STREAM: Schema = Schema {
+    name: "stream",
+    migrations: &[stream_envelope, stream_chained],
+    pretty: false,
+};
+
+/// Configs are edited by hand, so they are kept readable
+pub const CONFIG: Schema = Schema {
+    name: "config",
+    migrations: &[config_version],
+    pretty: true,
+};
+
+impl Schema {
+    pub fn current(&self) -> u64 {
+        self.migrations.len() as u64
+    }
+
+    pub fn version(&self, value: &Value) -> u64 {
+        value.get("version").and_then(Value::as_u64).unwrap_or(0)
+    }
+
+    /// Bring the JSON of any known version up to the current one
+    pub fn upgrade(&self, mut value: Value) -> Result<Value, Error> {
+        let version = self.version(&value);
+        if version > self.current() {
+            return Err(Error::UnsupportedVersion(format!(
+                "{} version {} was written by a newer skuff, this one reads up to version {}",
+                self.name,
+                version,
+                self.current()
+            )));
+        }
+
+        for migration in &self.migrations[version as usize..] {
+            value = migration(value)?;
+        }
+
+        Ok(value)
+    }
+
+    /// The upgraded file and the version it had, or None if the file is current
+    pub fn upgrade_buffer(&self, buf: &[u8]) -> Result<Option<(u64, Vec<u8>)>, Error> {
+        // Only the version is read from current files, which is quicker than reading them whole
+        #[derive(serde::Deserialize)]
+        struct Versioned {
+            version: u64,
+        }
+
+        if let Ok(Versioned { version }) = serde_json::from_slice(buf)
+            && version == self.current()
+        {
+            return Ok(None);
+        }
+
+        let value: Value =
+            serde_json::from_slice(buf).map_err(|err| Error::DeserializeFailed(err.to_string()))?;
+
+        let version = self.version(&value);
+        let value = self.upgrade(value)?;
+        let buf = if self.pretty {
+            serde_json::to_vec_pretty(&value)
+        } else {
+            serde_json::to_vec(&value)
+        }
+        .map_err(|err| Error::SerializeFailed(err.to_string()))?;
+
+        Ok(Some((version, buf)))
+    }
+}
+
+fn stream_envelope(value: Value) -> Result<Value, Error> {
+    match value {
+        Value::Array(events) => Ok(json!({ "version": 1, "events": events })),
+        _ => Err(Error::DeserializeFailed(
+            "a stream without a version must be an array of events".to_string(),
+        )),
+    }
+}
+
+/// A stream is chained if any of its events has a hash, as new events always do
+fn stream_chained(mut value: Value) -> Result<Value, Error> {
+    let chained = value["events"]
+        .as_array()
+        .is_some_and(|events| events.iter().any(|event| event.get("hash").is_some()));
+    value["version"] = json!(2);
+    value["chained"] = json!(chained);
+
+    Ok(value)
+}
+
+fn config_version(value: Value) -> Result<Value, Error> {
+    match value {
+        Value::Object(config) => {
+            let mut versioned = serde_json::Map::new();
+            versioned.insert("version".to_string(), json!(1));
+            versioned.extend(config);
+
+            Ok(Value::Object(versioned))
+        }
+        _ => Err(Error::DeserializeFailed(
+            "a config must be an object".to_string(),
+        )),
+    }
+}
+
+/// Every historical version of each file is kept in `fixtures` and must still load
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Config;
+    use crate::Stream;
+    use crate::replay;
+
+    const STREAMS: [&[u8]; 3] = [
+        include_bytes!("fixtures/stream.v0.json"),
+        include_bytes!("fixtures/stream.v1.json"),
+        include_bytes!("fixtures/stream.v2.json"),
+    ];
+
+    const CONFIGS: [&[u8]; 2] = [
+        include_bytes!("fixtures/config.v0.json"),
+        include_bytes!("fixtures/config.v1.json"),
+    ];
+
+    #[test]
+    fn every_version_has_a_fixture() {
+        assert_eq!(STREAMS.len() as u64, STREAM.current() + 1);
+        assert_eq!(CONFIGS.len() as u64, CONFIG.current() + 1);
+    }
+
+    #[test]
+    fn streams_of_every_version_load() {
+        for (version, buf) in STREAMS.iter().enumerate() {
+            let stream = Stream::from_buffer(buf).unwrap();
+            let state = replay(&stream);
+
+            assert_eq!(stream.events().len(), 6, "version {}", version);
+            assert_eq!(state.iter().count(), 3, "version {}", version);
+        }
+    }
+
+    #[test]
+    fn configs_of_every_version_load() {
+        for (version, buf) in CONFIGS.iter().enumerate() {
+            let config = Config::from_buffer(buf).unwrap();
+
+            assert_eq!(config.pager.as_deref(), Some(""), "version {}", version);
+            assert_eq!(config.norm.unwrap().minutes, 480, "version {}", version);
+        }
+    }
+
+    #[test]
+    fn older_files_upgrade_to_the_current_version() {
+        let (version, buf) = STREAM.upgrade_buffer(STREAMS[0]).unwrap().unwrap();
+        assert_eq!(version, 0);
+        assert_eq!(STREAM.upgrade_buffer(&buf).unwrap(), None);
+
+        let (version, buf) = CONFIG.upgrade_buffer(CONFIGS[0]).unwrap().unwrap();
+        assert_eq!(version, 0);
+        assert_eq!(CONFIG.upgrade_buffer(&buf).unwrap(), None);
+
+        let current = *STREAMS.last().unwrap();
+        assert_eq!(STREAM.upgrade_buffer(current).unwrap(), None);
+    }
+
+    #[test]
+    fn newer_versions_are_refused() {
+        let newer = json!({ "version": STREAM.current() + 1, "events": [] });
+
+        assert!(matches!(
+            STREAM.upgrade(newer),
+            Err(Error::UnsupportedVersion(_))
+        ));
+    }
+}
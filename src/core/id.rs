@@ -1,7 +1,11 @@
 use std::fmt::Display;
+use std::str::FromStr;
 
 use serde::Deserialize;
 use serde::Serialize;
+use sha2::Digest as _;
+use sha2::Sha256;
+use uuid::Builder;
 use uuid::Uuid;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -13,7 +17,29 @@ impl Id {
         Id(Uuid::new_v4())
     }
 
-    pub fn from_str(s: &str) -> Result<Self, uuid::Error> {
+    /// An id derived from `self` and `name`, the same every time they are
+    pub fn derive(&self, name: &str) -> Self {
+        let digest = Sha256::new()
+            .chain_update(self.0.as_bytes())
+            .chain_update(name.as_bytes())
+            .finalize();
+        let mut bytes = [0; 16];
+        bytes.copy_from_slice(&digest[..16]);
+
+        Id(Builder::from_custom_bytes(bytes).into_uuid())
+    }
+}
+
+impl Default for Id {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FromStr for Id {
+    type Err = uuid::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
         Uuid::parse_str(s).map(Id)
     }
 }
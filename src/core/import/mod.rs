@@ -0,0 +1,286 @@
+/// Import module
+///
+/// This module converts the history recorded by other time trackers into skuff events. Each
+/// supported format is parsed into a list of intervals, which are then turned into Login/Logout
+/// pairs, plus an Activity when the interval is tagged.
+mod timeclock;
+mod timewarrior;
+mod watson;
+
+use std::collections::BTreeSet;
+
+use chrono::DateTime;
+use chrono::Utc;
+
+use crate::Dst;
+use crate::Entity;
+use crate::Error;
+use crate::Event;
+use crate::Id;
+use crate::State;
+use crate::Zone;
+use crate::sessions;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Source {
+    Timewarrior,
+    Watson,
+    Timeclock,
+}
+
+/// An interval as recorded by another tracker. Intervals that are still running have no end.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Interval {
+    pub start: DateTime<Utc>,
+    pub end: Option<DateTime<Utc>>,
+    pub activity: Option<String>,
+}
+
+/// Parse the contents of a file written by `source`. Only timeclock files store wall-clock times,
+/// which are interpreted in `zone`.
+pub fn parse(
+    source: Source,
+    contents: &str,
+    zone: &Zone,
+    dst: Option<Dst>,
+) -> Result<Vec<Interval>, Error> {
+    match source {
+        Source::Timewarrior => timewarrior::parse(contents),
+        Source::Watson => watson::parse(contents),
+        Source::Timeclock => timeclock::parse(contents, zone, dst),
+    }
+}
+
+/// The events needed to bring a set of intervals into a stream
+pub struct Import {
+    pub events: Vec<Event>,
+    /// Intervals that were skipped because the stream already has a session from their start to
+    /// their end
+    pub duplicates: usize,
+    /// Intervals that are still running, and so only got a Login
+    pub open: usize,
+}
+
+impl Import {
+    /// Intervals are imported as whole sessions. One that matches a session already in the stream,
+    /// or an interval imported before it, is skipped, while one that only partly overlaps it is
+    /// rejected, as importing it would leave a stray Login or Logout behind.
+    pub fn new(intervals: &[Interval], existing: &State, zone: &Zone) -> Result<Self, Error> {
+        let mut seen: Vec<Span> = sessions(existing)
+            .iter()
+            .map(|session| (session.start(), session.end()))
+            .collect();
+        let mut events = vec![];
+        let mut duplicates = 0;
+        let mut open = 0;
+
+        for interval in intervals {
+            let span = (interval.start, interval.end);
+
+            if seen.contains(&span) {
+                duplicates += 1;
+                continue;
+            }
+
+            if seen.iter().any(|other| overlap(other, &span)) {
+                return Err(Error::ImportFailed(format!(
+                    "the interval starting {} partly overlaps a session already in the stream",
+                    zone.localize(&interval.start).format("%Y-%m-%d %H:%M")
+                )));
+            }
+
+            seen.push(span);
+
+            let mut entities = vec![Entity::Login {
+                id: Id::new(),
+                timestamp: interval.start,
+                tz: Some(*zone),
+                note: None,
+                tags: BTreeSet::new(),
+            }];
+
+            match interval.end {
+                Some(end) => {
+                    entities.push(Entity::Logout {
+                        id: Id::new(),
+                        timestamp: end,
+                        tz: Some(*zone),
+                        note: None,
+                    });
+
+                    if let Some(value) = &interval.activity {
+                        entities.push(Entity::Activity {
+                            id: Id::new(),
+                            timestamp: interval.start,
+                            tz: Some(*zone),
+                            note: None,
+                            tags: BTreeSet::new(),
+                            duration: end - interval.start,
+                            value: value.clone(),
+                            autoinsert: false,
+                        });
+                    }
+                }
+                None => open += 1,
+            }
+
+            events.extend(entities.into_iter().map(|entity| Event::Create {
+                id: Id::new(),
+                created_at: Utc::now(),
+                entity,
+            }));
+        }
+
+        Ok(Self {
+            events,
+            duplicates,
+            open,
+        })
+    }
+}
+
+/// The start of a session and its end, if it has one
+type Span = (DateTime<Utc>, Option<DateTime<Utc>>);
+
+/// Whether two sessions share a login or logout time, or overlap in time. Running sessions only
+/// count from their start.
+fn overlap(a: &Span, b: &Span) -> bool {
+    let (a_end, b_end) = (a.1.unwrap_or(a.0), b.1.unwrap_or(b.0));
+
+    a.0 == b.0 || (a.1.is_some() && a.1 == b.1) || (a.0 < b_end && b.0 < a_end)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone as _;
+
+    use super::*;
+    use crate::Stream;
+    use crate::replay;
+
+    fn utc(h: u32, m: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 9, 1, h, m, 0).unwrap()
+    }
+
+    fn zone() -> Zone {
+        "Europe/Oslo".parse().unwrap()
+    }
+
+    #[test]
+    fn intervals_become_login_logout_and_activity() {
+        let intervals = vec![
+            Interval {
+                start: utc(7, 0),
+                end: Some(utc(11, 0)),
+                activity: Some("Coding".to_string()),
+            },
+            Interval {
+                start: utc(12, 0),
+                end: None,
+                activity: None,
+            },
+        ];
+
+        let import = Import::new(&intervals, &replay(&Stream::new()), &zone()).unwrap();
+
+        assert_eq!(import.events.len(), 4);
+        assert_eq!(import.duplicates, 0);
+        assert_eq!(import.open, 1);
+    }
+
+    fn stream(entities: Vec<Entity>) -> Stream {
+        let mut stream = Stream::new();
+        for entity in entities {
+            stream
+                .push(Event::Create {
+                    id: Id::new(),
+                    created_at: Utc::now(),
+                    entity,
+                })
+                .unwrap();
+        }
+        stream
+    }
+
+    fn login(timestamp: DateTime<Utc>) -> Entity {
+        Entity::Login {
+            id: Id::new(),
+            timestamp,
+            tz: None,
+            note: None,
+            tags: BTreeSet::new(),
+        }
+    }
+
+    fn logout(timestamp: DateTime<Utc>) -> Entity {
+        Entity::Logout {
+            id: Id::new(),
+            timestamp,
+            tz: None,
+            note: None,
+        }
+    }
+
+    #[test]
+    fn existing_sessions_are_skipped() {
+        let stream = stream(vec![login(utc(7, 0)), logout(utc(11, 0))]);
+
+        let intervals = vec![
+            Interval {
+                start: utc(7, 0),
+                end: Some(utc(11, 0)),
+                activity: Some("Coding".to_string()),
+            },
+            Interval {
+                start: utc(12, 0),
+                end: Some(utc(15, 0)),
+                activity: None,
+            },
+        ];
+
+        let import = Import::new(&intervals, &replay(&stream), &zone()).unwrap();
+
+        assert_eq!(import.events.len(), 2);
+        assert_eq!(import.duplicates, 1);
+    }
+
+    #[test]
+    fn partly_overlapping_sessions_are_rejected() {
+        let intervals = vec![Interval {
+            start: utc(7, 0),
+            end: Some(utc(11, 0)),
+            activity: None,
+        }];
+
+        for entities in [
+            vec![login(utc(7, 0))],
+            vec![login(utc(7, 0)), logout(utc(10, 0))],
+            vec![login(utc(10, 0)), logout(utc(12, 0))],
+        ] {
+            let stream = stream(entities);
+            match Import::new(&intervals, &replay(&stream), &zone()) {
+                Err(Error::ImportFailed(msg)) => assert!(msg.contains("overlaps"), "{}", msg),
+                _ => panic!("expected ImportFailed"),
+            }
+        }
+    }
+
+    #[test]
+    fn repeated_intervals_are_imported_once() {
+        let interval = Interval {
+            start: utc(7, 0),
+            end: Some(utc(11, 0)),
+            activity: Some("Coding".to_string()),
+        };
+
+        let import = Import::new(
+            &[interval.clone(), interval],
+            &replay(&Stream::new()),
+            &zone(),
+        )
+        .unwrap();
+
+        assert_eq!(import.events.len(), 3);
+        assert_eq!(import.duplicates, 1);
+    }
+}
@@ -0,0 +1,172 @@
+use chrono::DateTime;
+use chrono::NaiveDateTime;
+use chrono::Utc;
+
+use super::Interval;
+use crate::Dst;
+use crate::Error;
+use crate::Zone;
+
+const FORMATS: [&str; 4] = [
+    "%Y/%m/%d %H:%M:%S",
+    "%Y/%m/%d %H:%M",
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%d %H:%M",
+];
+
+/// Parse a ledger/hledger timeclock file
+///
+///   i 2025/09/01 08:00:00 client:project  optional description
+///   o 2025/09/01 16:00:00
+///
+/// The account of the clock-in line becomes the activity. Times are wall-clock times in `zone`.
+pub fn parse(contents: &str, zone: &Zone, dst: Option<Dst>) -> Result<Vec<Interval>, Error> {
+    let mut intervals = vec![];
+    let mut open: Option<Interval> = None;
+
+    // Editors on Windows like to start files with a byte order mark
+    let contents = contents.strip_prefix('\u{feff}').unwrap_or(contents);
+
+    for (n, line) in contents.lines().enumerate() {
+        let failed = |msg: &str| Error::ImportFailed(format!("line {}: {}", n + 1, msg));
+
+        let line = line.trim_end();
+        let Some(code) = line.chars().next() else {
+            continue;
+        };
+
+        if matches!(code, ';' | '#' | '*') {
+            continue;
+        }
+
+        let (date, rest) = field(&line[code.len_utf8()..]);
+        let (time, rest) = field(rest);
+        if date.is_empty() || time.is_empty() {
+            return Err(failed("expected a date and time"));
+        }
+
+        let naive = FORMATS
+            .iter()
+            .find_map(|format| {
+                NaiveDateTime::parse_from_str(&format!("{} {}", date, time), format).ok()
+            })
+            .ok_or_else(|| failed("invalid date or time"))?;
+        let timestamp: DateTime<Utc> = zone.resolve(&naive, dst).map_err(|err| match err {
+            Error::AmbiguousLocalTime(msg) | Error::NonexistentLocalTime(msg) => failed(&msg),
+            other => other,
+        })?;
+
+        match code {
+            'i' | 'I' => {
+                if open.is_some() {
+                    return Err(failed("clock-in while already clocked in"));
+                }
+
+                open = Some(Interval {
+                    start: timestamp,
+                    end: None,
+                    activity: account(rest),
+                });
+            }
+            'o' | 'O' => {
+                let Some(mut interval) = open.take() else {
+                    return Err(failed("clock-out without clock-in"));
+                };
+                interval.end = Some(timestamp);
+                intervals.push(interval);
+            }
+            _ => return Err(failed("expected a line starting with 'i' or 'o'")),
+        }
+    }
+
+    intervals.extend(open);
+
+    Ok(intervals)
+}
+
+/// Split off the next whitespace separated field
+fn field(s: &str) -> (&str, &str) {
+    let s = s.trim_start();
+    s.split_once(char::is_whitespace).unwrap_or((s, ""))
+}
+
+/// The account is the rest of the line up to two spaces or a tab, which start the description.
+/// Single spaces are part of the account name.
+fn account(rest: &str) -> Option<String> {
+    let rest = rest.trim_start();
+    let end = [rest.find("  "), rest.find('\t')]
+        .into_iter()
+        .flatten()
+        .min()
+        .unwrap_or(rest.len());
+
+    Some(rest[..end].to_string()).filter(|account| !account.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone as _;
+
+    use super::*;
+
+    fn zone() -> Zone {
+        "Europe/Oslo".parse().unwrap()
+    }
+
+    #[test]
+    fn clock_in_and_out() {
+        let contents = "\
+; imported from my old setup
+i 2025/09/01 09:00:00 client:project  design review
+o 2025/09/01 13:00:00
+
+i 2025-09-01 14:00
+o 2025-09-01 17:30
+i 2025/09/02 09:00  client:internal meetings  standup
+";
+
+        let intervals = parse(contents, &zone(), None).unwrap();
+
+        assert_eq!(
+            intervals,
+            vec![
+                Interval {
+                    start: Utc.with_ymd_and_hms(2025, 9, 1, 7, 0, 0).unwrap(),
+                    end: Some(Utc.with_ymd_and_hms(2025, 9, 1, 11, 0, 0).unwrap()),
+                    activity: Some("client:project".to_string()),
+                },
+                Interval {
+                    start: Utc.with_ymd_and_hms(2025, 9, 1, 12, 0, 0).unwrap(),
+                    end: Some(Utc.with_ymd_and_hms(2025, 9, 1, 15, 30, 0).unwrap()),
+                    activity: None,
+                },
+                Interval {
+                    start: Utc.with_ymd_and_hms(2025, 9, 2, 7, 0, 0).unwrap(),
+                    end: None,
+                    activity: Some("client:internal meetings".to_string()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn clock_out_without_clock_in() {
+        let contents = "o 2025/09/01 13:00:00\n";
+
+        match parse(contents, &zone(), None).unwrap_err() {
+            Error::ImportFailed(msg) => assert!(msg.starts_with("line 1"), "{}", msg),
+            other => panic!("expected ImportFailed, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn byte_order_mark_and_unexpected_characters() {
+        let contents = "\u{feff}i 2025/09/01 09:00:00\no 2025/09/01 13:00:00\n";
+        assert_eq!(parse(contents, &zone(), None).unwrap().len(), 1);
+
+        match parse("é 2025/09/01 09:00:00\n", &zone(), None).unwrap_err() {
+            Error::ImportFailed(msg) => assert!(msg.contains("'i' or 'o'"), "{}", msg),
+            other => panic!("expected ImportFailed, got {:?}", other),
+        }
+    }
+}
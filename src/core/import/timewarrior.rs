@@ -0,0 +1,182 @@
+use chrono::DateTime;
+use chrono::NaiveDateTime;
+use chrono::Utc;
+use serde::Deserialize;
+
+use super::Interval;
+use crate::Error;
+
+const FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+#[derive(Deserialize)]
+struct Exported {
+    start: String,
+    end: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+/// Parse either the output of `timew export` or one of the files in `~/.timewarrior/data`
+pub fn parse(contents: &str) -> Result<Vec<Interval>, Error> {
+    if contents.trim_start().starts_with('[') {
+        parse_export(contents)
+    } else {
+        parse_data(contents)
+    }
+}
+
+fn parse_export(contents: &str) -> Result<Vec<Interval>, Error> {
+    let exported: Vec<Exported> =
+        serde_json::from_str(contents).map_err(|err| Error::ImportFailed(err.to_string()))?;
+
+    exported
+        .into_iter()
+        .map(|interval| {
+            Ok(Interval {
+                start: timestamp(&interval.start)?,
+                end: interval.end.as_deref().map(timestamp).transpose()?,
+                activity: activity(interval.tags),
+            })
+        })
+        .collect()
+}
+
+/// Data files have one interval per line, e.g.
+///
+///   inc 20250901T070000Z - 20250901T150000Z # coding "code review"
+fn parse_data(contents: &str) -> Result<Vec<Interval>, Error> {
+    let mut intervals = vec![];
+
+    for (n, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let failed = |msg: &str| Error::ImportFailed(format!("line {}: {}", n + 1, msg));
+
+        let (range, tags) = match line.split_once('#') {
+            Some((range, tags)) => (range, tags),
+            None => (line, ""),
+        };
+
+        let tokens: Vec<&str> = range.split_whitespace().collect();
+        let (start, end) = match tokens.as_slice() {
+            ["inc", start] => (*start, None),
+            ["inc", start, "-", end] => (*start, Some(*end)),
+            _ => return Err(failed("expected 'inc <start> [- <end>]'")),
+        };
+
+        intervals.push(Interval {
+            start: timestamp(start).map_err(|_| failed("invalid start"))?,
+            end: end
+                .map(timestamp)
+                .transpose()
+                .map_err(|_| failed("invalid end"))?,
+            activity: activity(split_tags(tags)),
+        });
+    }
+
+    Ok(intervals)
+}
+
+fn timestamp(s: &str) -> Result<DateTime<Utc>, Error> {
+    NaiveDateTime::parse_from_str(s, FORMAT)
+        .map(|naive| naive.and_utc())
+        .map_err(|err| Error::ImportFailed(format!("'{}': {}", s, err)))
+}
+
+fn activity(tags: Vec<String>) -> Option<String> {
+    match tags.is_empty() {
+        true => None,
+        false => Some(tags.join(", ")),
+    }
+}
+
+/// Split on whitespace, keeping "quoted tags" together
+fn split_tags(s: &str) -> Vec<String> {
+    let mut tags = vec![];
+    let mut current = String::new();
+    let mut quoted = false;
+
+    for c in s.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    tags.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+
+    if !current.is_empty() {
+        tags.push(current);
+    }
+
+    tags
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone as _;
+
+    use super::*;
+
+    #[test]
+    fn data_lines() {
+        let contents = "\
+inc 20250901T070000Z - 20250901T110000Z # coding \"code review\"
+inc 20250901T120000Z - 20250901T150000Z
+inc 20250902T070000Z # meetings
+";
+
+        let intervals = parse(contents).unwrap();
+
+        assert_eq!(
+            intervals,
+            vec![
+                Interval {
+                    start: Utc.with_ymd_and_hms(2025, 9, 1, 7, 0, 0).unwrap(),
+                    end: Some(Utc.with_ymd_and_hms(2025, 9, 1, 11, 0, 0).unwrap()),
+                    activity: Some("coding, code review".to_string()),
+                },
+                Interval {
+                    start: Utc.with_ymd_and_hms(2025, 9, 1, 12, 0, 0).unwrap(),
+                    end: Some(Utc.with_ymd_and_hms(2025, 9, 1, 15, 0, 0).unwrap()),
+                    activity: None,
+                },
+                Interval {
+                    start: Utc.with_ymd_and_hms(2025, 9, 2, 7, 0, 0).unwrap(),
+                    end: None,
+                    activity: Some("meetings".to_string()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn export_json() {
+        let contents = r#"[
+            {"id": 2, "start": "20250901T070000Z", "end": "20250901T110000Z", "tags": ["coding"]},
+            {"id": 1, "start": "20250901T120000Z"}
+        ]"#;
+
+        let intervals = parse(contents).unwrap();
+
+        assert_eq!(intervals.len(), 2);
+        assert_eq!(intervals[0].activity, Some("coding".to_string()));
+        assert_eq!(intervals[1].end, None);
+    }
+
+    #[test]
+    fn malformed_line_reports_line_number() {
+        let contents = "inc 20250901T070000Z - 20250901T110000Z\nexc 20250901T070000Z\n";
+
+        match parse(contents).unwrap_err() {
+            Error::ImportFailed(msg) => assert!(msg.starts_with("line 2"), "{}", msg),
+            other => panic!("expected ImportFailed, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use chrono::DateTime;
+use chrono::Utc;
+use serde_json::Value;
+
+use super::Interval;
+use crate::Error;
+
+/// Parse Watson's `frames` file, a JSON array where each frame is
+///
+///   [start, stop, project, id, tags, updated_at]
+///
+/// with start and stop as unix timestamps. The project and tags are combined into the activity.
+pub fn parse(contents: &str) -> Result<Vec<Interval>, Error> {
+    let frames: Vec<Vec<Value>> =
+        serde_json::from_str(contents).map_err(|err| Error::ImportFailed(err.to_string()))?;
+
+    frames
+        .iter()
+        .enumerate()
+        .map(|(n, frame)| {
+            let failed = |msg: &str| Error::ImportFailed(format!("frame {}: {}", n + 1, msg));
+
+            let start = frame
+                .first()
+                .and_then(timestamp)
+                .ok_or_else(|| failed("invalid start"))?;
+            let end = frame
+                .get(1)
+                .and_then(timestamp)
+                .ok_or_else(|| failed("invalid stop"))?;
+            let project = frame
+                .get(2)
+                .and_then(Value::as_str)
+                .ok_or_else(|| failed("missing project"))?;
+            let tags: Vec<&str> = frame
+                .get(4)
+                .and_then(Value::as_array)
+                .map(|tags| tags.iter().filter_map(Value::as_str).collect())
+                .unwrap_or_default();
+
+            let activity = match tags.is_empty() {
+                true => project.to_string(),
+                false => format!("{} ({})", project, tags.join(", ")),
+            };
+
+            Ok(Interval {
+                start,
+                end: Some(end),
+                activity: Some(activity),
+            })
+        })
+        .collect()
+}
+
+fn timestamp(value: &Value) -> Option<DateTime<Utc>> {
+    DateTime::from_timestamp(value.as_i64()?, 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone as _;
+
+    use super::*;
+
+    #[test]
+    fn frames() {
+        let contents = r#"[
+            [1756710000, 1756724400, "skuff", "4e1a", ["coding", "review"], 1756724400],
+            [1756728000, 1756738800, "support", "9b2c", [], 1756738800]
+        ]"#;
+
+        let intervals = parse(contents).unwrap();
+
+        assert_eq!(
+            intervals,
+            vec![
+                Interval {
+                    start: Utc.with_ymd_and_hms(2025, 9, 1, 7, 0, 0).unwrap(),
+                    end: Some(Utc.with_ymd_and_hms(2025, 9, 1, 11, 0, 0).unwrap()),
+                    activity: Some("skuff (coding, review)".to_string()),
+                },
+                Interval {
+                    start: Utc.with_ymd_and_hms(2025, 9, 1, 12, 0, 0).unwrap(),
+                    end: Some(Utc.with_ymd_and_hms(2025, 9, 1, 15, 0, 0).unwrap()),
+                    activity: Some("support".to_string()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn malformed_frame() {
+        let contents = r#"[[1756710000, "soon", "skuff", "4e1a", [], 0]]"#;
+
+        match parse(contents).unwrap_err() {
+            Error::ImportFailed(msg) => assert!(msg.starts_with("frame 1"), "{}", msg),
+            other => panic!("expected ImportFailed, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::PathBuf;
+
+use chrono::DateTime;
+use chrono::Datelike as _;
+use chrono::NaiveDateTime;
+use chrono::Utc;
+
+use crate::Backups;
+use crate::Error;
+use crate::Zone;
+use crate::layout::StorageLayout;
+
+/// Backups are named after when they were taken, so that they sort by name
+const FORMAT: &str = "%Y%m%dT%H%M%S%.3fZ";
+
+/// An earlier version of a stream, as it was just before it was written at `taken_at`
+pub struct Backup {
+    pub taken_at: DateTime<Utc>,
+    pub path: PathBuf,
+}
+
+/// The backups of a stream, newest first
+pub fn list_backups(layout: &StorageLayout, stream: &str) -> Result<Vec<Backup>, Error> {
+    let path = layout.backups_path(stream);
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+
+    let entries = fs::read_dir(&path).map_err(|err| Error::FailedToReadDir(err.to_string()))?;
+
+    let mut backups = vec![];
+    for entry in entries {
+        let path = entry
+            .map_err(|err| Error::FailedToReadDir(err.to_string()))?
+            .path();
+
+        let taken_at = path
+            .file_stem()
+            .and_then(|stem| NaiveDateTime::parse_from_str(&stem.to_string_lossy(), FORMAT).ok());
+
+        // Anything else in the folder was put there by hand
+        if let Some(taken_at) = taken_at {
+            backups.push(Backup {
+                taken_at: taken_at.and_utc(),
+                path,
+            });
+        }
+    }
+
+    backups.sort_by_key(|backup| std::cmp::Reverse(backup.taken_at));
+
+    Ok(backups)
+}
+
+/// Copy the stream file aside as it is, then drop the backups `policy` doesn't keep. Returns the
+/// copy, if there was a stream file and the policy keeps any backups.
+pub fn take_backup(
+    layout: &StorageLayout,
+    stream: &str,
+    policy: &Backups,
+) -> Result<Option<PathBuf>, Error> {
+    let source = layout.stream_path(stream);
+    if !source.exists() || policy.last + policy.daily + policy.weekly == 0 {
+        return Ok(None);
+    }
+
+    let dir = layout.backups_path(stream);
+    fs::create_dir_all(&dir).map_err(|err| Error::BackupFailed(err.to_string()))?;
+
+    let copy = dir.join(format!("{}.json", Utc::now().format(FORMAT)));
+    fs::copy(&source, &copy).map_err(|err| Error::BackupFailed(err.to_string()))?;
+
+    let backups = list_backups(layout, stream)?;
+    for (backup, keep) in backups
+        .iter()
+        .zip(retained(&backups, policy, &Zone::local()))
+    {
+        if !keep {
+            fs::remove_file(&backup.path).map_err(|err| Error::BackupFailed(err.to_string()))?;
+        }
+    }
+
+    Ok(Some(copy))
+}
+
+/// Which of `backups`, newest first, `policy` keeps. Days and weeks are those on the wall clock
+/// in `zone`.
+fn retained(backups: &[Backup], policy: &Backups, zone: &Zone) -> Vec<bool> {
+    let mut days = BTreeSet::new();
+    let mut weeks = BTreeSet::new();
+
+    backups
+        .iter()
+        .enumerate()
+        .map(|(index, backup)| {
+            let date = zone.localize(&backup.taken_at).date_naive();
+
+            // The newest backup of a day or week is the first one seen
+            let daily = days.len() < policy.daily && days.insert(date);
+            let weekly = weeks.len() < policy.weekly && weeks.insert(date.iso_week());
+
+            index < policy.last || daily || weekly
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use chrono::TimeZone as _;
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn keep_last_daily_and_weekly_generations() {
+        // Every six hours over four weeks, newest first
+        let start = Utc.with_ymd_and_hms(2025, 9, 29, 0, 0, 0).unwrap();
+        let backups = (0..4 * 7 * 4)
+            .rev()
+            .map(|n| Backup {
+                taken_at: start + Duration::hours(6 * n),
+                path: PathBuf::new(),
+            })
+            .collect::<Vec<_>>();
+
+        let policy = Backups {
+            last: 3,
+            daily: 2,
+            weekly: 2,
+        };
+        let kept = backups
+            .iter()
+            .zip(retained(&backups, &policy, &"UTC".parse().unwrap()))
+            .filter(|(_, keep)| *keep)
+            .map(|(backup, _)| backup.taken_at.format("%a %d %H").to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            kept,
+            [
+                "Sun 26 18",
+                "Sun 26 12",
+                "Sun 26 06", // the last three
+                "Sat 25 18", // the day before
+                "Sun 19 18", // the week before
+            ]
+        );
+    }
+
+    #[test]
+    fn take_copies_the_stream_and_rotates() {
+        let temp = TempDir::new().unwrap();
+        let layout = StorageLayout::coalesce(None, Some(temp.path().to_path_buf()));
+        let path = layout.stream_path("work");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+
+        let policy = Backups {
+            last: 2,
+            daily: 0,
+            weekly: 0,
+        };
+        for version in ["[]", "[1]", "[2]"] {
+            fs::write(&path, version).unwrap();
+            take_backup(&layout, "work", &policy).unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(2));
+        }
+
+        let backups = list_backups(&layout, "work").unwrap();
+        assert_eq!(backups.len(), 2);
+        assert_eq!(fs::read_to_string(&backups[0].path).unwrap(), "[2]");
+        assert_eq!(fs::read_to_string(&backups[1].path).unwrap(), "[1]");
+    }
+}
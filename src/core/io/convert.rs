@@ -0,0 +1,211 @@
+use std::fs;
+
+use chrono::Utc;
+
+use crate::Error;
+use crate::layout::StorageLayout;
+
+use super::Backend;
+use super::Storage;
+use super::open;
+
+/// Move every stream of the storage root from one backend to another
+///
+/// Each stream is copied and read back before the old backend is retired. Its data is kept as a
+/// backup: stream files are renamed to `stream.<date>.json` and the database to `skuff.<date>.db`.
+/// Returns the number of events copied per stream.
+pub fn convert(layout: &StorageLayout, to: Backend) -> Result<Vec<(String, usize)>, Error> {
+    let from = Backend::detect(layout);
+    if from == to {
+        return Err(Error::ConversionFailed(format!(
+            "the storage already uses {}",
+            to
+        )));
+    }
+
+    if layout.encryption_path().exists() {
+        return Err(Error::ConversionFailed(
+            "the storage is encrypted, decrypt it first".to_string(),
+        ));
+    }
+
+    let source = open(layout.clone(), from)?;
+    let streams = source.streams()?;
+    let current = source.current_stream()?;
+
+    let target = open(layout.clone(), to)?;
+    for name in &streams {
+        if target.stream_exists(name)? {
+            return Err(Error::ConversionFailed(format!(
+                "stream '{}' already exists in {}",
+                name, to
+            )));
+        }
+    }
+
+    let copied = copy(&*source, &*target, streams.clone(), current);
+
+    drop(source);
+    drop(target);
+
+    match copied {
+        Ok(copied) => {
+            retire(layout, from, &copied)?;
+            Ok(copied)
+        }
+        Err(err) => {
+            // Leave the storage as it was, so that it is still detected as the old backend
+            discard(layout, to, &streams);
+            Err(err)
+        }
+    }
+}
+
+fn copy(
+    source: &dyn Storage,
+    target: &dyn Storage,
+    streams: Vec<String>,
+    current: Option<String>,
+) -> Result<Vec<(String, usize)>, Error> {
+    let mut copied = vec![];
+    for name in streams {
+        let events = source.read(&name)?;
+
+        target.stream_create(&name)?;
+        target.replace(&name, &events)?;
+
+        let count = events.events().len();
+        if target.read(&name)?.events().len() != count {
+            return Err(Error::ConversionFailed(format!(
+                "stream '{}' was not copied completely",
+                name
+            )));
+        }
+
+        copied.push((name, count));
+    }
+
+    if let Some(current) = current {
+        target.write_current_stream(&current)?;
+    }
+
+    Ok(copied)
+}
+
+fn discard(layout: &StorageLayout, backend: Backend, streams: &[String]) {
+    match backend {
+        Backend::Files => {
+            for name in streams {
+                let _ = fs::remove_file(layout.stream_path(name));
+            }
+            let _ = fs::remove_file(layout.current_stream_path());
+        }
+        Backend::Sqlite => {
+            let _ = fs::remove_file(layout.database_path());
+        }
+    }
+}
+
+fn retire(
+    layout: &StorageLayout,
+    backend: Backend,
+    streams: &[(String, usize)],
+) -> Result<(), Error> {
+    let suffix = Utc::now().format("%Y%m%dT%H%M%S").to_string();
+    let rename =
+        |from, to| fs::rename(from, to).map_err(|err| Error::FailedToWriteFile(err.to_string()));
+
+    match backend {
+        Backend::Files => {
+            for (name, _) in streams {
+                rename(layout.stream_path(name), layout.backup_path(name, &suffix))?;
+
+                let snapshot = layout.snapshot_path(name);
+                if snapshot.exists() {
+                    fs::remove_file(snapshot)
+                        .map_err(|err| Error::FailedToWriteFile(err.to_string()))?;
+                }
+            }
+
+            let current = layout.current_stream_path();
+            if current.exists() {
+                fs::remove_file(current)
+                    .map_err(|err| Error::FailedToWriteFile(err.to_string()))?;
+            }
+
+            Ok(())
+        }
+        Backend::Sqlite => rename(layout.database_path(), layout.database_backup_path(&suffix)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use chrono::Utc;
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::Entity;
+    use crate::Event;
+    use crate::Id;
+
+    fn login() -> Event {
+        Event::Create {
+            id: Id::new(),
+            created_at: Utc::now(),
+            entity: Entity::Login {
+                id: Id::new(),
+                timestamp: Utc::now(),
+                tz: None,
+                note: None,
+                tags: BTreeSet::new(),
+            },
+        }
+    }
+
+    #[test]
+    fn round_trip_between_backends() {
+        let temp = TempDir::new().unwrap();
+        let layout = StorageLayout::coalesce(None, Some(temp.path().to_path_buf()));
+
+        let files = open(layout.clone(), Backend::Files).unwrap();
+        files.stream_create("work").unwrap();
+        files.stream_create("acme").unwrap();
+        files.set_current_stream("acme").unwrap();
+        let work = Some("work".to_string());
+        files.stream_extend(vec![login(), login()], &work).unwrap();
+        let events = files.stream(&work).unwrap();
+        drop(files);
+
+        let mut copied = convert(&layout, Backend::Sqlite).unwrap();
+        copied.sort();
+        assert_eq!(copied, [("acme".to_string(), 0), ("work".to_string(), 2)]);
+        assert_eq!(Backend::detect(&layout), Backend::Sqlite);
+        assert!(!layout.stream_path("work").exists());
+
+        let sqlite = open(layout.clone(), Backend::Sqlite).unwrap();
+        assert_eq!(sqlite.current_stream().unwrap(), Some("acme".to_string()));
+        drop(sqlite);
+
+        assert!(matches!(
+            convert(&layout, Backend::Sqlite),
+            Err(Error::ConversionFailed(_))
+        ));
+
+        convert(&layout, Backend::Files).unwrap();
+        assert_eq!(Backend::detect(&layout), Backend::Files);
+
+        let files = open(layout, Backend::Files).unwrap();
+        assert_eq!(files.current_stream().unwrap(), Some("acme".to_string()));
+        let ids = |stream: &crate::Stream| {
+            stream
+                .events()
+                .iter()
+                .map(|event| event.id().clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ids(&files.stream(&work).unwrap()), ids(&events));
+    }
+}
@@ -0,0 +1,330 @@
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+use argon2::Algorithm;
+use argon2::Argon2;
+use argon2::Params;
+use argon2::Version;
+use chacha20poly1305::Key;
+use chacha20poly1305::KeyInit as _;
+use chacha20poly1305::XChaCha20Poly1305;
+use chacha20poly1305::XNonce;
+use chacha20poly1305::aead::Aead as _;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::Error;
+use crate::layout::SETTINGS_DIRECTORY;
+use crate::layout::StorageLayout;
+
+use super::util::*;
+
+/// Written before the nonce and ciphertext of every encrypted file
+const MAGIC: &[u8] = b"skuff-encrypted-v1\n";
+const NONCE: usize = 24;
+
+/// Sealed into the header, to tell a wrong key from a damaged file
+const CHECK: &[u8] = b"skuff";
+
+/// Stored at the root of an encrypted storage. Holds everything needed to derive the key, except
+/// the secret itself.
+#[derive(Serialize, Deserialize)]
+struct Header {
+    salt: String,
+    m_cost: u32,
+    t_cost: u32,
+    p_cost: u32,
+    check: String,
+}
+
+/// Encrypts and decrypts the files of a storage with a key derived from a passphrase or key file
+pub struct Cipher {
+    cipher: XChaCha20Poly1305,
+}
+
+impl Cipher {
+    fn derive(secret: &[u8], header: &Header) -> Result<Self, Error> {
+        let params = Params::new(header.m_cost, header.t_cost, header.p_cost, None)
+            .map_err(|err| Error::EncryptionFailed(err.to_string()))?;
+        let salt = from_hex(&header.salt)?;
+
+        let mut key = [0u8; 32];
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(secret, &salt, &mut key)
+            .map_err(|err| Error::EncryptionFailed(err.to_string()))?;
+
+        Ok(Self {
+            cipher: XChaCha20Poly1305::new(Key::from_slice(&key)),
+        })
+    }
+
+    pub fn seal(&self, plain: &[u8]) -> Result<Vec<u8>, Error> {
+        let nonce: [u8; NONCE] = rand::random();
+        let sealed = self
+            .cipher
+            .encrypt(XNonce::from_slice(&nonce), plain)
+            .map_err(|err| Error::EncryptionFailed(err.to_string()))?;
+
+        Ok([MAGIC, &nonce, &sealed].concat())
+    }
+
+    pub fn open(&self, buf: &[u8]) -> Result<Vec<u8>, Error> {
+        let rest = buf
+            .strip_prefix(MAGIC)
+            .filter(|rest| rest.len() >= NONCE)
+            .ok_or_else(|| Error::DecryptionFailed("not an encrypted file".to_string()))?;
+        let (nonce, sealed) = rest.split_at(NONCE);
+
+        self.cipher
+            .decrypt(XNonce::from_slice(nonce), sealed)
+            .map_err(|_| {
+                Error::DecryptionFailed(
+                    "the file is damaged or was encrypted with another key".to_string(),
+                )
+            })
+    }
+}
+
+pub fn is_encrypted(buf: &[u8]) -> bool {
+    buf.starts_with(MAGIC)
+}
+
+/// The cipher of an encrypted storage, or None if the storage isn't encrypted
+///
+/// The secret is the passphrase in SKUFF_PASSPHRASE, the key file in SKUFF_KEYFILE, or the key
+/// file ~/.skuff/key, in that order.
+pub fn cipher(layout: &StorageLayout) -> Result<Option<Cipher>, Error> {
+    let path = layout.encryption_path();
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let secret = secret(None)?.ok_or_else(|| {
+        Error::KeyMissing(
+            "the storage is encrypted, set SKUFF_PASSPHRASE or SKUFF_KEYFILE, or put the key in \
+             ~/.skuff/key"
+                .to_string(),
+        )
+    })?;
+
+    unlock(layout, &secret).map(Some)
+}
+
+/// Derive the cipher of an encrypted storage from `secret`, checking that it is the right one
+pub fn unlock(layout: &StorageLayout, secret: &[u8]) -> Result<Cipher, Error> {
+    let header: Header = json::read(&layout.encryption_path())?;
+    let cipher = Cipher::derive(secret, &header)?;
+
+    match cipher.open(&from_hex(&header.check)?) {
+        Ok(check) if check == CHECK => Ok(cipher),
+        _ => Err(Error::DecryptionFailed(
+            "wrong passphrase or key file".to_string(),
+        )),
+    }
+}
+
+/// The secret from `keyfile`, or else from the environment or the default key file
+pub fn secret(keyfile: Option<&Path>) -> Result<Option<Vec<u8>>, Error> {
+    if let Some(path) = keyfile {
+        return read_keyfile(path).map(Some);
+    }
+
+    if let Ok(passphrase) = std::env::var("SKUFF_PASSPHRASE") {
+        return Ok(Some(passphrase.into_bytes()));
+    }
+
+    if let Ok(path) = std::env::var("SKUFF_KEYFILE") {
+        return read_keyfile(Path::new(&path)).map(Some);
+    }
+
+    let path = SETTINGS_DIRECTORY.join("key");
+    if path.exists() {
+        return read_keyfile(&path).map(Some);
+    }
+
+    Ok(None)
+}
+
+/// The contents of a key file, byte for byte. A trailing newline is part of the key, since key
+/// files may hold random bytes that happen to end in one.
+fn read_keyfile(path: &Path) -> Result<Vec<u8>, Error> {
+    fs::read(path).map_err(|err| Error::KeyMissing(format!("{}: {}", path.display(), err)))
+}
+
+/// Encrypt every file of a storage with a key derived from `secret`, returning how many files
+/// were encrypted
+pub fn encrypt(layout: &StorageLayout, secret: &[u8]) -> Result<usize, Error> {
+    encrypt_with(
+        layout,
+        secret,
+        Params::DEFAULT_M_COST,
+        Params::DEFAULT_T_COST,
+    )
+}
+
+pub(super) fn encrypt_with(
+    layout: &StorageLayout,
+    secret: &[u8],
+    m_cost: u32,
+    t_cost: u32,
+) -> Result<usize, Error> {
+    let path = layout.encryption_path();
+    if path.exists() {
+        return Err(Error::EncryptionFailed(
+            "the storage is already encrypted".to_string(),
+        ));
+    }
+
+    let salt: [u8; 16] = rand::random();
+    let mut header = Header {
+        salt: to_hex(&salt),
+        m_cost,
+        t_cost,
+        p_cost: Params::DEFAULT_P_COST,
+        check: String::new(),
+    };
+    let cipher = Cipher::derive(secret, &header)?;
+    header.check = to_hex(&cipher.seal(CHECK)?);
+
+    let files = files(layout)?;
+    let mut sealed = vec![];
+    for file in &files {
+        let buf = read(file)?;
+        if !is_encrypted(&buf) {
+            sealed.push((file, cipher.seal(&buf)?));
+        }
+    }
+
+    // The header goes first, so that a storage is never left with files it can't decrypt
+    json::write(&path, &header)?;
+    for (file, buf) in &sealed {
+        write(file, buf)?;
+    }
+
+    Ok(sealed.len())
+}
+
+/// Decrypt every file of an encrypted storage, returning how many files were decrypted
+pub fn decrypt(layout: &StorageLayout, cipher: &Cipher) -> Result<usize, Error> {
+    let mut opened = vec![];
+    for file in files(layout)? {
+        let buf = read(&file)?;
+        if is_encrypted(&buf) {
+            opened.push((file, cipher.open(&buf)?));
+        }
+    }
+
+    for (file, buf) in &opened {
+        write(file, buf)?;
+    }
+    fs::remove_file(layout.encryption_path())
+        .map_err(|err| Error::FailedToWriteFile(err.to_string()))?;
+
+    Ok(opened.len())
+}
+
+/// Every stream, config, snapshot and backup file of the storage
+fn files(layout: &StorageLayout) -> Result<Vec<PathBuf>, Error> {
+    let path = layout.streams_path();
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+
+    let mut files = vec![];
+    for dir in dirs::dirs(&path).map_err(|err| Error::FailedToReadDir(err.to_string()))? {
+        let name = dir.file_name().unwrap().to_string_lossy().to_string();
+
+        for dir in [dir, layout.backups_path(&name)] {
+            if !dir.exists() {
+                continue;
+            }
+
+            let entries =
+                fs::read_dir(&dir).map_err(|err| Error::FailedToReadDir(err.to_string()))?;
+            for entry in entries {
+                let path = entry
+                    .map_err(|err| Error::FailedToReadDir(err.to_string()))?
+                    .path();
+                if path.is_file() && path.extension().is_some_and(|ext| ext == "json") {
+                    files.push(path);
+                }
+            }
+        }
+    }
+
+    Ok(files)
+}
+
+fn read(path: &Path) -> Result<Vec<u8>, Error> {
+    fs::read(path).map_err(|err| Error::FailedToReadFile(err.to_string()))
+}
+
+fn write(path: &Path, buf: &[u8]) -> Result<(), Error> {
+    fs::write(path, buf).map_err(|err| Error::FailedToWriteFile(err.to_string()))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn from_hex(s: &str) -> Result<Vec<u8>, Error> {
+    (0..s.len())
+        .step_by(2)
+        .map(|i| {
+            s.get(i..i + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                .ok_or_else(|| Error::DeserializeFailed(format!("invalid hex '{}'", s)))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn layout(temp: &TempDir) -> StorageLayout {
+        StorageLayout::coalesce(None, Some(temp.path().to_path_buf()))
+    }
+
+    #[test]
+    fn encrypt_and_decrypt_storage() {
+        let temp = TempDir::new().unwrap();
+        let layout = layout(&temp);
+        let stream = layout.stream_path("work");
+        fs::create_dir_all(stream.parent().unwrap()).unwrap();
+        fs::write(&stream, "[]").unwrap();
+        fs::write(layout.config_path("work"), "{}").unwrap();
+
+        assert_eq!(encrypt_with(&layout, b"secret", 64, 1).unwrap(), 2);
+        assert!(is_encrypted(&fs::read(&stream).unwrap()));
+        assert!(encrypt_with(&layout, b"secret", 64, 1).is_err());
+
+        assert!(matches!(
+            unlock(&layout, b"wrong"),
+            Err(Error::DecryptionFailed(_))
+        ));
+        let cipher = unlock(&layout, b"secret").unwrap();
+        assert_eq!(cipher.open(&fs::read(&stream).unwrap()).unwrap(), b"[]");
+
+        assert_eq!(decrypt(&layout, &cipher).unwrap(), 2);
+        assert_eq!(fs::read(&stream).unwrap(), b"[]");
+        assert!(!layout.encryption_path().exists());
+    }
+
+    #[test]
+    fn sealing_twice_gives_different_files() {
+        let temp = TempDir::new().unwrap();
+        let layout = layout(&temp);
+        encrypt_with(&layout, b"secret", 64, 1).unwrap();
+        let cipher = unlock(&layout, b"secret").unwrap();
+
+        let first = cipher.seal(b"hours").unwrap();
+        let second = cipher.seal(b"hours").unwrap();
+
+        assert_ne!(first, second);
+        assert_eq!(cipher.open(&second).unwrap(), b"hours");
+    }
+}
@@ -0,0 +1,487 @@
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::CONFIG;
+use crate::Config;
+use crate::Error;
+use crate::Event;
+use crate::STREAM;
+use crate::Schema;
+use crate::Snapshot;
+use crate::Stream;
+use crate::layout::StorageLayout;
+
+use super::Backup;
+use super::Cipher;
+use super::Settings;
+use super::Storage;
+use super::cipher;
+use super::is_encrypted;
+use super::list_backups;
+use super::take_backup;
+use super::util::*;
+
+/// Streams kept as JSON files under the storage root, encrypted if the storage was encrypted
+pub struct FileStorage {
+    layout: StorageLayout,
+    cipher: Option<Cipher>,
+}
+
+impl FileStorage {
+    /// Storage that reads and writes plain files
+    pub fn new(layout: StorageLayout) -> Self {
+        Self {
+            layout,
+            cipher: None,
+        }
+    }
+
+    /// Storage that encrypts its files if the storage root is encrypted. Fails if the key is
+    /// missing or wrong.
+    pub fn open(layout: StorageLayout) -> Result<Self, Error> {
+        let cipher = cipher(&layout)?;
+
+        Ok(Self { layout, cipher })
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.cipher.is_some()
+    }
+
+    /// The plain contents of a file read from the storage. Files of encrypted storage must be
+    /// encrypted, so that a plain file put in place of one is not taken as recorded.
+    pub fn decode(&self, buf: &[u8]) -> Result<Vec<u8>, Error> {
+        match &self.cipher {
+            Some(cipher) if is_encrypted(buf) => cipher.open(buf),
+            Some(_) => Err(Error::DecryptionFailed(
+                "the storage is encrypted, but the file is not".to_string(),
+            )),
+            None if is_encrypted(buf) => Err(Error::KeyMissing(
+                "the storage is encrypted, but no key was given".to_string(),
+            )),
+            None => Ok(buf.to_vec()),
+        }
+    }
+
+    fn load(&self, path: &Path) -> Result<Vec<u8>, Error> {
+        let buf = fs::read(path).map_err(|err| Error::FailedToReadFile(err.to_string()))?;
+
+        self.decode(&buf).map_err(|err| match err {
+            Error::DecryptionFailed(reason) => {
+                Error::DecryptionFailed(format!("{}: {}", path.display(), reason))
+            }
+            err => err,
+        })
+    }
+
+    /// Keep the stream as it is before it is written, as the config says
+    fn rotate(&self, stream: &str) -> Result<Option<PathBuf>, Error> {
+        let config = Config::coalesce(None, Settings::config_file()?, self.config(stream)?);
+
+        take_backup(&self.layout, stream, &config.backups.unwrap_or_default())
+    }
+
+    /// Read a stream or config file, upgrading it first if an older version wrote it. The file as
+    /// it was is kept at the path `backup` gives for its version, e.g. `v0`.
+    fn load_current(
+        &self,
+        path: &Path,
+        schema: &Schema,
+        backup: impl Fn(&str) -> PathBuf,
+    ) -> Result<Vec<u8>, Error> {
+        let buf = self.load(path)?;
+
+        let Some((version, upgraded)) = schema.upgrade_buffer(&buf)? else {
+            return Ok(buf);
+        };
+
+        let backup = backup(&format!("v{}", version));
+        if !backup.exists() {
+            fs::copy(path, &backup).map_err(|err| Error::BackupFailed(err.to_string()))?;
+        }
+        self.save(path, &upgraded)?;
+
+        Ok(upgraded)
+    }
+
+    fn save(&self, path: &Path, buf: &[u8]) -> Result<(), Error> {
+        let buf = match &self.cipher {
+            Some(cipher) => cipher.seal(buf)?,
+            None => buf.to_vec(),
+        };
+
+        fs::write(path, buf).map_err(|err| Error::FailedToWriteFile(err.to_string()))
+    }
+}
+
+impl Storage for FileStorage {
+    fn current_stream(&self) -> Result<Option<String>, Error> {
+        let path = self.layout.current_stream_path();
+
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let current = text::read(&path)?;
+
+        Ok(Some(current))
+    }
+
+    fn write_current_stream(&self, stream: &str) -> Result<(), Error> {
+        let path = self.layout.current_stream_path();
+        text::write(&path, stream)?;
+
+        Ok(())
+    }
+
+    fn streams(&self) -> Result<Vec<String>, Error> {
+        let path = self.layout.streams_path();
+
+        if !path.exists() {
+            return Ok(vec![]);
+        }
+
+        let streams = dirs::dirs(&path)
+            .map_err(|err| Error::FailedToReadDir(err.to_string()))?
+            .iter()
+            .filter(|p| p.is_dir())
+            .map(|p| p.file_name().unwrap().to_string_lossy().to_string())
+            .collect::<Vec<String>>();
+
+        Ok(streams)
+    }
+
+    fn stream_exists(&self, stream: &str) -> Result<bool, Error> {
+        let path = self.layout.stream_path(stream);
+
+        Ok(path.exists())
+    }
+
+    fn stream_create(&self, stream: &str) -> Result<(), Error> {
+        let path = self.layout.stream_path(stream);
+
+        if path.exists() {
+            return Err(Error::StreamAlreadyExists(stream.to_string()));
+        }
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|err| Error::StreamCreationFailed(err.to_string()))?;
+        }
+
+        self.save(&path, &Stream::new().to_buffer()?)?;
+
+        Ok(())
+    }
+
+    fn read(&self, stream: &str) -> Result<Stream, Error> {
+        let path = self.layout.stream_path(stream);
+
+        let buf = self.load_current(&path, &STREAM, |suffix| {
+            self.layout.backup_path(stream, suffix)
+        })?;
+
+        Stream::from_buffer(&buf)
+    }
+
+    fn read_since(&self, stream: &str, verified: (usize, &str)) -> Result<Stream, Error> {
+        let path = self.layout.stream_path(stream);
+
+        let buf = self.load_current(&path, &STREAM, |suffix| {
+            self.layout.backup_path(stream, suffix)
+        })?;
+
+        Stream::from_buffer_since(&buf, Some(verified))
+    }
+
+    fn append(&self, stream: &str, events: Vec<Event>) -> Result<(), Error> {
+        let path = self.layout.stream_path(stream);
+
+        let mut buffer = self.read(stream)?;
+
+        for event in events {
+            buffer.push(event)?;
+        }
+
+        self.rotate(stream)?;
+
+        self.save(&path, &buffer.to_buffer()?)?;
+
+        Ok(())
+    }
+
+    fn replace(&self, stream: &str, events: &Stream) -> Result<(), Error> {
+        let snapshot = self.layout.snapshot_path(stream);
+        if snapshot.exists() {
+            fs::remove_file(&snapshot).map_err(|err| Error::FailedToWriteFile(err.to_string()))?;
+        }
+
+        let path = self.layout.stream_path(stream);
+        self.save(&path, &events.to_buffer()?)?;
+
+        Ok(())
+    }
+
+    fn backup(&self, stream: &str) -> Result<PathBuf, Error> {
+        let config = Config::coalesce(None, Settings::config_file()?, self.config(stream)?);
+        let mut policy = config.backups.unwrap_or_default();
+        // The version before a rewrite is kept whatever the config says, until later backups
+        // rotate it out
+        policy.last = policy.last.max(1);
+
+        take_backup(&self.layout, stream, &policy)?
+            .ok_or_else(|| Error::BackupFailed(format!("{} has no stream file", stream)))
+    }
+
+    fn read_snapshot(&self, stream: &str) -> Option<Snapshot> {
+        let path = self.layout.snapshot_path(stream);
+
+        if !path.exists() {
+            return None;
+        }
+
+        let buf = self.load(&path).ok()?;
+
+        serde_json::from_slice(&buf).ok()
+    }
+
+    fn write_snapshot(&self, stream: &str, snapshot: &Snapshot) -> Result<(), Error> {
+        let buf = serde_json::to_vec_pretty(snapshot)
+            .map_err(|err| Error::SerializeFailed(err.to_string()))?;
+
+        self.save(&self.layout.snapshot_path(stream), &buf)
+    }
+
+    fn config(&self, stream: &str) -> Result<Option<Config>, Error> {
+        let path = self.layout.config_path(stream);
+
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let buf = self.load_current(&path, &CONFIG, |suffix| {
+            self.layout.config_backup_path(stream, suffix)
+        })?;
+        let config = Config::from_buffer(&buf)?;
+
+        Ok(Some(config))
+    }
+
+    fn backups(&self, stream: &str) -> Result<Vec<Backup>, Error> {
+        list_backups(&self.layout, stream)
+    }
+
+    fn read_backup(&self, backup: &Backup) -> Result<Stream, Error> {
+        Stream::from_buffer(&self.load(&backup.path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use chrono::Utc;
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn create_new_stream() {
+        let env = TestEnv::new();
+        let io = FileStorage::new(env.layout());
+
+        io.stream_create("stream1").unwrap();
+
+        let expected_path = env.root.join("streams/stream1/stream.json");
+        assert!(expected_path.exists());
+    }
+
+    #[test]
+    fn create_stream_fails_if_already_exists() {
+        let env = TestEnv::new();
+        let io = FileStorage::new(env.layout());
+
+        // First create succeeds
+        io.stream_create("stream1").unwrap();
+        // Second create should fail
+        let err = io.stream_create("stream1").unwrap_err();
+
+        match err {
+            Error::StreamAlreadyExists(s) => assert_eq!(s, "stream1"),
+            other => panic!("expected StreamAlreadyExists, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn exists_returns_false_if_stream_missing() {
+        let env = TestEnv::new();
+        let io = FileStorage::new(env.layout());
+
+        let result = io.stream_exists("stream1").unwrap();
+        assert!(!result);
+    }
+
+    #[test]
+    fn exists_returns_true_if_stream_exists() {
+        let env = TestEnv::new();
+        let io = FileStorage::new(env.layout());
+
+        // Manually create the directory
+        let stream_path = env.root.join("streams/stream1");
+        std::fs::create_dir_all(&stream_path).unwrap();
+        std::fs::File::create(stream_path.join("stream.json")).unwrap();
+
+        let result = io.stream_exists("stream1").unwrap();
+        assert!(result);
+    }
+
+    #[test]
+    fn state_resumes_from_snapshot() {
+        let env = TestEnv::new();
+        let io = FileStorage::new(env.layout());
+        let stream = Some("stream1".to_string());
+        io.stream_create("stream1").unwrap();
+
+        let login = |hour| Event::Create {
+            id: crate::Id::new(),
+            created_at: Utc::now(),
+            entity: crate::Entity::Login {
+                id: crate::Id::new(),
+                timestamp: Utc::now() - chrono::Duration::hours(hour),
+                tz: None,
+                note: None,
+                tags: Default::default(),
+            },
+        };
+
+        io.stream_append(login(2), &stream).unwrap();
+        assert_eq!(io.state(&stream).unwrap().iter().count(), 1);
+
+        let path = env.root.join("streams/stream1/snapshot.json");
+        let snapshot: Snapshot = json::read(&path).unwrap();
+        assert_eq!(snapshot.events, 1);
+        assert_eq!(
+            snapshot.head.as_deref(),
+            Some(io.read("stream1").unwrap().head())
+        );
+
+        // Reading the state leaves the snapshot alone
+        std::fs::remove_file(&path).unwrap();
+        io.state(&stream).unwrap();
+        assert!(!path.exists());
+
+        io.stream_append(login(1), &stream).unwrap();
+        assert_eq!(io.state(&stream).unwrap().iter().count(), 2);
+
+        let snapshot: Snapshot = json::read(&path).unwrap();
+        assert_eq!(snapshot.events, 2);
+
+        // A snapshot whose entities were changed is ignored
+        let mut changed = snapshot.clone();
+        changed.entities.pop();
+        json::write(&path, &changed).unwrap();
+        assert_eq!(io.state(&stream).unwrap().iter().count(), 2);
+
+        // As is one of another head
+        let mut changed = snapshot;
+        changed.head = Some("0".repeat(64));
+        json::write(&path, &changed).unwrap();
+        assert_eq!(io.state(&stream).unwrap().iter().count(), 2);
+    }
+
+    #[test]
+    fn replace_drops_snapshot() {
+        let env = TestEnv::new();
+        let io = FileStorage::new(env.layout());
+        let stream = Some("stream1".to_string());
+        io.stream_create("stream1").unwrap();
+
+        io.stream_extend(vec![], &stream).unwrap();
+        let path = env.root.join("streams/stream1/snapshot.json");
+        assert!(path.exists());
+
+        let backup = io.stream_replace(&Stream::new(), &stream).unwrap();
+        assert!(!path.exists());
+        assert_eq!(io.backups("stream1").unwrap()[0].path, backup);
+    }
+
+    #[test]
+    fn encrypted_storage_needs_its_key() {
+        let env = TestEnv::new();
+        let stream = Some("stream1".to_string());
+        FileStorage::new(env.layout())
+            .stream_create("stream1")
+            .unwrap();
+
+        super::super::crypt::encrypt_with(&env.layout(), b"secret", 64, 1).unwrap();
+        let io = FileStorage {
+            layout: env.layout(),
+            cipher: Some(super::super::unlock(&env.layout(), b"secret").unwrap()),
+        };
+
+        let login = Event::Create {
+            id: crate::Id::new(),
+            created_at: Utc::now(),
+            entity: crate::Entity::Login {
+                id: crate::Id::new(),
+                timestamp: Utc::now(),
+                tz: None,
+                note: None,
+                tags: Default::default(),
+            },
+        };
+        io.stream_append(login, &stream).unwrap();
+        assert_eq!(io.state(&stream).unwrap().iter().count(), 1);
+
+        for file in ["stream.json", "snapshot.json"] {
+            let buf = std::fs::read(env.root.join("streams/stream1").join(file)).unwrap();
+            assert!(is_encrypted(&buf));
+        }
+        assert!(matches!(
+            FileStorage::new(env.layout()).stream(&stream),
+            Err(Error::KeyMissing(_))
+        ));
+
+        // A plain file in place of an encrypted one is not taken as recorded
+        let path = env.root.join("streams/stream1/stream.json");
+        std::fs::write(&path, Stream::new().to_buffer().unwrap()).unwrap();
+        assert!(matches!(
+            io.stream(&stream),
+            Err(Error::DecryptionFailed(_))
+        ));
+    }
+
+    #[test]
+    fn older_stream_is_upgraded_and_kept() {
+        let env = TestEnv::new();
+        let io = FileStorage::new(env.layout());
+        let path = env.root.join("streams/stream1/stream.json");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, "[]").unwrap();
+
+        assert_eq!(io.read("stream1").unwrap().events().len(), 0);
+
+        let upgraded = std::fs::read(&path).unwrap();
+        assert_eq!(STREAM.upgrade_buffer(&upgraded).unwrap(), None);
+        let kept = env.root.join("streams/stream1/stream.v0.json");
+        assert_eq!(std::fs::read_to_string(kept).unwrap(), "[]");
+    }
+
+    struct TestEnv {
+        root: PathBuf,
+        _temp: TempDir, // keep TempDir alive so it cleans up
+    }
+
+    impl TestEnv {
+        fn new() -> Self {
+            let temp = TempDir::new().expect("failed to create tempdir");
+            let root = temp.path().to_path_buf();
+            Self { root, _temp: temp }
+        }
+
+        fn layout(&self) -> StorageLayout {
+            StorageLayout::coalesce(None, Some(self.root.clone()))
+        }
+    }
+}
@@ -0,0 +1,552 @@
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+use git2::Commit;
+use git2::IndexAddOption;
+use git2::Oid;
+use git2::Repository;
+use git2::Signature;
+use git2::build::CheckoutBuilder;
+use serde::Serialize;
+
+use crate::Config;
+use crate::Conflict;
+use crate::Error;
+use crate::Event;
+use crate::Snapshot;
+use crate::Stream;
+use crate::keeps_locked_period;
+use crate::layout::StorageLayout;
+use crate::log;
+use crate::merge;
+
+use super::Backup;
+use super::FileStorage;
+use super::Storage;
+
+const REMOTE: &str = "origin";
+
+/// Files that belong to one machine and are never committed
+const IGNORED: &str =
+    "CURRENT_STREAM\nsnapshot.json\nstream.*.json\nconfig.*.json\nskuff.*\nbackups/\n";
+
+/// Streams kept as files in a git repository, committing every change
+pub struct GitStorage {
+    files: FileStorage,
+    root: PathBuf,
+}
+
+impl GitStorage {
+    pub fn open(layout: StorageLayout) -> Result<Self, Error> {
+        Ok(Self {
+            root: layout.root.clone(),
+            files: FileStorage::open(layout)?,
+        })
+    }
+
+    fn commit(&self, message: &str) -> Result<(), Error> {
+        let repo = Repository::open(&self.root).map_err(failed)?;
+        commit(&repo, message)?;
+
+        Ok(())
+    }
+}
+
+impl Storage for GitStorage {
+    fn current_stream(&self) -> Result<Option<String>, Error> {
+        self.files.current_stream()
+    }
+
+    fn write_current_stream(&self, stream: &str) -> Result<(), Error> {
+        self.files.write_current_stream(stream)
+    }
+
+    fn streams(&self) -> Result<Vec<String>, Error> {
+        self.files.streams()
+    }
+
+    fn stream_exists(&self, stream: &str) -> Result<bool, Error> {
+        self.files.stream_exists(stream)
+    }
+
+    fn stream_create(&self, stream: &str) -> Result<(), Error> {
+        self.files.stream_create(stream)?;
+        self.commit(&format!("{}: create stream", stream))
+    }
+
+    fn read(&self, stream: &str) -> Result<Stream, Error> {
+        self.files.read(stream)
+    }
+
+    fn read_since(&self, stream: &str, verified: (usize, &str)) -> Result<Stream, Error> {
+        self.files.read_since(stream, verified)
+    }
+
+    fn append(&self, stream: &str, events: Vec<Event>) -> Result<(), Error> {
+        // Commit messages aren't encrypted, so they only describe events of plain storage
+        let message = match events.as_slice() {
+            _ if self.files.is_encrypted() => {
+                format!("{}: append {} events", stream, events.len())
+            }
+            [Event::Create { entity, .. }] => {
+                format!("{}: add {}", stream, log::describe(entity, None))
+            }
+            [Event::Edit { entity, .. }] => {
+                format!("{}: edit {}", stream, log::describe(entity, None))
+            }
+            [Event::Delete { entity_id, .. }] => format!("{}: remove {}", stream, entity_id),
+            [Event::Lock { until, .. }] => format!("{}: lock until {}", stream, until.to_rfc3339()),
+            [
+                Event::Unlock {
+                    until: Some(until), ..
+                },
+            ] => {
+                format!("{}: unlock from {}", stream, until.to_rfc3339())
+            }
+            [Event::Unlock { until: None, .. }] => format!("{}: unlock", stream),
+            [Event::Submit { from, .. }] => {
+                format!("{}: submit from {}", stream, from.to_rfc3339())
+            }
+            events => format!("{}: append {} events", stream, events.len()),
+        };
+
+        self.files.append(stream, events)?;
+        self.commit(&message)
+    }
+
+    fn replace(&self, stream: &str, events: &Stream) -> Result<(), Error> {
+        self.files.replace(stream, events)?;
+        self.commit(&format!("{}: rewrite history", stream))
+    }
+
+    fn backup(&self, stream: &str) -> Result<PathBuf, Error> {
+        self.files.backup(stream)
+    }
+
+    fn read_snapshot(&self, stream: &str) -> Option<Snapshot> {
+        self.files.read_snapshot(stream)
+    }
+
+    fn write_snapshot(&self, stream: &str, snapshot: &Snapshot) -> Result<(), Error> {
+        self.files.write_snapshot(stream, snapshot)
+    }
+
+    fn config(&self, stream: &str) -> Result<Option<Config>, Error> {
+        self.files.config(stream)
+    }
+
+    fn backups(&self, stream: &str) -> Result<Vec<Backup>, Error> {
+        self.files.backups(stream)
+    }
+
+    fn read_backup(&self, backup: &Backup) -> Result<Stream, Error> {
+        self.files.read_backup(backup)
+    }
+}
+
+/// What `sync` had to do to bring the storage and its remote together
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(tag = "result", rename_all = "snake_case")]
+pub enum Synced {
+    UpToDate,
+    Pushed,
+    FastForwarded,
+    /// Both sides had new events. The streams whose events were combined are listed, with the
+    /// entities both sides changed differently. Of those, the latest change was kept.
+    Merged {
+        streams: Vec<String>,
+        conflicts: Vec<(String, Conflict)>,
+        /// Streams whose hash chain no longer starts with the history of either side, with the
+        /// last hash of the combined stream
+        rewritten: Vec<(String, String)>,
+    },
+}
+
+/// Pull from the remote, combine diverged streams event by event, and push the result
+///
+/// The storage root is made a git repository first if it isn't one. With `remote`, the remote is
+/// set to that URL or path.
+pub fn sync(layout: &StorageLayout, remote: Option<&str>) -> Result<Synced, Error> {
+    let repo = match Repository::open(&layout.root) {
+        Ok(repo) => repo,
+        Err(_) => init(&layout.root)?,
+    };
+
+    // Configs are edited by hand, so they may not have been committed yet
+    commit(&repo, "Update configs")?;
+
+    if let Some(url) = remote {
+        match repo.find_remote(REMOTE) {
+            Ok(_) => repo.remote_set_url(REMOTE, url).map_err(failed)?,
+            Err(_) => {
+                repo.remote(REMOTE, url).map_err(failed)?;
+            }
+        }
+    }
+
+    let mut origin = repo.find_remote(REMOTE).map_err(|_| {
+        Error::GitFailed("no remote to sync with, set one with --remote".to_string())
+    })?;
+
+    let branch = branch(&repo)?;
+    origin
+        .fetch(
+            &[format!(
+                "+refs/heads/{0}:refs/remotes/{1}/{0}",
+                branch, REMOTE
+            )],
+            None,
+            None,
+        )
+        .map_err(failed)?;
+
+    let ours = head(&repo);
+    let theirs = repo
+        .find_reference(&format!("refs/remotes/{}/{}", REMOTE, branch))
+        .ok()
+        .and_then(|reference| reference.peel_to_commit().ok());
+
+    let synced = match (&ours, &theirs) {
+        (None, None) => return Ok(Synced::UpToDate),
+        (Some(_), None) => Synced::Pushed,
+        (None, Some(theirs)) => {
+            fast_forward(&repo, layout, &branch, theirs)?;
+            return Ok(Synced::FastForwarded);
+        }
+        (Some(ours), Some(theirs)) if ours.id() == theirs.id() => return Ok(Synced::UpToDate),
+        (Some(ours), Some(theirs)) if descends(&repo, ours.id(), theirs.id())? => Synced::Pushed,
+        (Some(ours), Some(theirs)) if descends(&repo, theirs.id(), ours.id())? => {
+            fast_forward(&repo, layout, &branch, theirs)?;
+            return Ok(Synced::FastForwarded);
+        }
+        (Some(ours), Some(theirs)) => merge_commits(&repo, layout, ours, theirs)?,
+    };
+
+    origin
+        .push(&[format!("refs/heads/{0}:refs/heads/{0}", branch)], None)
+        .map_err(failed)?;
+
+    Ok(synced)
+}
+
+fn init(root: &Path) -> Result<Repository, Error> {
+    let repo = Repository::init(root).map_err(failed)?;
+    commit(&repo, "Track storage in git")?;
+
+    Ok(repo)
+}
+
+/// Make sure `.gitignore` lists every machine-local file, also in repositories made by older
+/// versions
+fn ignore(root: &Path) -> Result<(), Error> {
+    let path = root.join(".gitignore");
+    let mut ignored = fs::read_to_string(&path).unwrap_or_default();
+
+    let missing = IGNORED
+        .lines()
+        .filter(|line| !ignored.lines().any(|existing| existing == *line))
+        .collect::<Vec<_>>();
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    if !ignored.is_empty() && !ignored.ends_with('\n') {
+        ignored.push('\n');
+    }
+    for line in missing {
+        ignored.push_str(line);
+        ignored.push('\n');
+    }
+
+    fs::write(&path, ignored).map_err(|err| Error::FailedToWriteFile(err.to_string()))
+}
+
+/// Commit every change in the storage root, if there are any
+fn commit(repo: &Repository, message: &str) -> Result<Option<Oid>, Error> {
+    if let Some(root) = repo.workdir() {
+        ignore(root)?;
+    }
+
+    let mut index = repo.index().map_err(failed)?;
+    index
+        .add_all(["*"], IndexAddOption::DEFAULT, None)
+        .map_err(failed)?;
+    index.update_all(["*"], None).map_err(failed)?;
+    index.write().map_err(failed)?;
+
+    let tree = index.write_tree().map_err(failed)?;
+    let parent = head(repo);
+    if parent.as_ref().map(|parent| parent.tree_id()) == Some(tree) {
+        return Ok(None);
+    }
+
+    let tree = repo.find_tree(tree).map_err(failed)?;
+    let signature = signature(repo)?;
+    let parents: Vec<&Commit> = parent.iter().collect();
+
+    repo.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        message,
+        &tree,
+        &parents,
+    )
+    .map(Some)
+    .map_err(failed)
+}
+
+/// Combine the streams of both sides into a merge commit
+fn merge_commits(
+    repo: &Repository,
+    layout: &StorageLayout,
+    ours: &Commit,
+    theirs: &Commit,
+) -> Result<Synced, Error> {
+    let files = FileStorage::open(layout.clone())?;
+    let tree = theirs.tree().map_err(failed)?;
+
+    // Everything is combined before anything is written, so a stream that cannot be combined
+    // leaves the storage as it was
+    let mut streams = vec![];
+    let mut conflicts = vec![];
+    let mut rewritten = vec![];
+    let mut configs = vec![];
+    if let Ok(entry) = tree.get_path(Path::new("streams")) {
+        let object = entry.to_object(repo).map_err(failed)?;
+        let entries = object
+            .as_tree()
+            .ok_or_else(|| Error::GitFailed("'streams' is not a directory".to_string()))?;
+
+        for entry in entries.iter() {
+            let Some(name) = entry.name() else { continue };
+            let blob = |file: &str| -> Result<Option<Vec<u8>>, Error> {
+                match tree.get_path(&Path::new("streams").join(name).join(file)) {
+                    Ok(entry) => {
+                        let object = entry.to_object(repo).map_err(failed)?;
+                        Ok(object.as_blob().map(|blob| blob.content().to_vec()))
+                    }
+                    Err(_) => Ok(None),
+                }
+            };
+
+            if let Some(buf) = blob("stream.json")? {
+                let theirs = Stream::from_buffer(&files.decode(&buf)?)?;
+
+                if files.stream_exists(name)? {
+                    let ours = files.read(name)?;
+                    let merge = merge(&ours, &theirs)?;
+                    keeps_locked_period(&ours, &merge.stream)?;
+                    keeps_locked_period(&theirs, &merge.stream)?;
+                    if merge.stream.rewrites(&ours) || merge.stream.rewrites(&theirs) {
+                        rewritten.push((name.to_string(), merge.stream.head().to_string()));
+                    }
+                    conflicts.extend(
+                        merge
+                            .conflicts
+                            .into_iter()
+                            .map(|conflict| (name.to_string(), conflict)),
+                    );
+                    streams.push((name.to_string(), merge.stream, false));
+                } else {
+                    streams.push((name.to_string(), theirs, true));
+                }
+            }
+
+            // Configs are kept as they are on this machine when both sides have one
+            let config = layout.config_path(name);
+            if let (false, Some(buf)) = (config.exists(), blob("config.json")?) {
+                configs.push((config, buf));
+            }
+        }
+    }
+
+    let mut merged = vec![];
+    for (name, stream, new) in streams {
+        if new {
+            files.stream_create(&name)?;
+        } else {
+            files.backup(&name)?;
+        }
+        files.replace(&name, &stream)?;
+        merged.push(name);
+    }
+    for (path, buf) in configs {
+        fs::write(path, buf).map_err(|err| Error::FailedToWriteFile(err.to_string()))?;
+    }
+
+    let mut index = repo.index().map_err(failed)?;
+    index
+        .add_all(["*"], IndexAddOption::DEFAULT, None)
+        .map_err(failed)?;
+    index.write().map_err(failed)?;
+    let tree = index.write_tree().map_err(failed)?;
+    let tree = repo.find_tree(tree).map_err(failed)?;
+    let signature = signature(repo)?;
+
+    repo.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        "Merge events from remote",
+        &tree,
+        &[ours, theirs],
+    )
+    .map_err(failed)?;
+
+    Ok(Synced::Merged {
+        streams: merged,
+        conflicts,
+        rewritten,
+    })
+}
+
+fn fast_forward(
+    repo: &Repository,
+    layout: &StorageLayout,
+    branch: &str,
+    theirs: &Commit,
+) -> Result<(), Error> {
+    let name = format!("refs/heads/{}", branch);
+    repo.reference(&name, theirs.id(), true, "sync: fast-forward")
+        .map_err(failed)?;
+    repo.set_head(&name).map_err(failed)?;
+    repo.checkout_head(Some(CheckoutBuilder::new().force()))
+        .map_err(failed)?;
+
+    // The streams were rewritten behind the snapshots' back
+    for stream in FileStorage::new(layout.clone()).streams()? {
+        let _ = fs::remove_file(layout.snapshot_path(&stream));
+    }
+
+    Ok(())
+}
+
+fn head(repo: &Repository) -> Option<Commit<'_>> {
+    repo.head().ok()?.peel_to_commit().ok()
+}
+
+/// The branch HEAD points at, even before its first commit
+fn branch(repo: &Repository) -> Result<String, Error> {
+    let head = repo.find_reference("HEAD").map_err(failed)?;
+    let target = head
+        .symbolic_target()
+        .ok_or_else(|| Error::GitFailed("HEAD is detached".to_string()))?;
+
+    Ok(target.trim_start_matches("refs/heads/").to_string())
+}
+
+fn descends(repo: &Repository, commit: Oid, ancestor: Oid) -> Result<bool, Error> {
+    repo.graph_descendant_of(commit, ancestor).map_err(failed)
+}
+
+fn signature(repo: &Repository) -> Result<Signature<'static>, Error> {
+    repo.signature()
+        .or_else(|_| Signature::now("skuff", "skuff@localhost"))
+        .map_err(failed)
+}
+
+fn failed(err: git2::Error) -> Error {
+    Error::GitFailed(err.message().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use chrono::Utc;
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::Entity;
+    use crate::Id;
+
+    fn login() -> Event {
+        Event::Create {
+            id: Id::new(),
+            created_at: Utc::now(),
+            entity: Entity::Login {
+                id: Id::new(),
+                timestamp: Utc::now(),
+                tz: None,
+                note: None,
+                tags: BTreeSet::new(),
+            },
+        }
+    }
+
+    fn layout(temp: &TempDir) -> StorageLayout {
+        StorageLayout::coalesce(None, Some(temp.path().to_path_buf()))
+    }
+
+    fn events(layout: &StorageLayout) -> usize {
+        FileStorage::new(layout.clone())
+            .read("work")
+            .unwrap()
+            .events()
+            .len()
+    }
+
+    #[test]
+    fn appends_are_committed() {
+        let temp = TempDir::new().unwrap();
+        let layout = layout(&temp);
+        init(&layout.root).unwrap();
+
+        let storage = GitStorage::open(layout.clone()).unwrap();
+        storage.stream_create("work").unwrap();
+        storage
+            .stream_append(login(), &Some("work".to_string()))
+            .unwrap();
+
+        let repo = Repository::open(&layout.root).unwrap();
+        let message = head(&repo).unwrap().message().unwrap().to_string();
+        assert!(message.starts_with("work: add Login @"), "{}", message);
+        let mut options = git2::StatusOptions::new();
+        options.include_ignored(false);
+        assert!(repo.statuses(Some(&mut options)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn sync_merges_streams_from_both_machines() {
+        let remote = TempDir::new().unwrap();
+        Repository::init_bare(remote.path()).unwrap();
+        let url = remote.path().to_str().unwrap();
+        let work = Some("work".to_string());
+
+        let laptop = TempDir::new().unwrap();
+        let laptop = layout(&laptop);
+        init(&laptop.root).unwrap();
+        let storage = GitStorage::open(laptop.clone()).unwrap();
+        storage.stream_create("work").unwrap();
+        storage.stream_append(login(), &work).unwrap();
+        assert_eq!(sync(&laptop, Some(url)).unwrap(), Synced::Pushed);
+
+        let desktop = TempDir::new().unwrap();
+        let desktop = layout(&desktop);
+        assert!(matches!(
+            sync(&desktop, Some(url)).unwrap(),
+            Synced::FastForwarded | Synced::Merged { .. }
+        ));
+        assert_eq!(events(&desktop), 1);
+
+        GitStorage::open(desktop.clone())
+            .unwrap()
+            .stream_append(login(), &work)
+            .unwrap();
+        storage.stream_append(login(), &work).unwrap();
+
+        assert_eq!(sync(&desktop, None).unwrap(), Synced::Pushed);
+        assert!(matches!(
+            sync(&laptop, None).unwrap(),
+            Synced::Merged { streams, conflicts, rewritten }
+                if streams == ["work"] && conflicts.is_empty() && rewritten.len() == 1
+        ));
+        assert_eq!(events(&laptop), 3);
+
+        assert_eq!(sync(&desktop, None).unwrap(), Synced::FastForwarded);
+        assert_eq!(events(&desktop), 3);
+        assert_eq!(sync(&desktop, None).unwrap(), Synced::UpToDate);
+    }
+}
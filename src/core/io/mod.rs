@@ -1,7 +1,61 @@
+mod backups;
+mod convert;
+mod crypt;
+mod files;
+mod git;
 mod settings;
+mod sqlite;
 mod storage;
 mod util;
 
+pub use backups::*;
+pub use convert::*;
+pub use crypt::*;
+pub use files::*;
+pub use git::*;
 pub use settings::*;
+pub use sqlite::*;
 pub use storage::*;
 pub use util::*;
+
+use std::fmt::Display;
+
+use crate::Error;
+use crate::layout::StorageLayout;
+
+/// How the streams under a storage root are kept
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Backend {
+    Files,
+    Sqlite,
+}
+
+impl Backend {
+    /// The backend of an existing storage root. Roots with a database use SQLite.
+    pub fn detect(layout: &StorageLayout) -> Self {
+        if layout.database_path().exists() {
+            Backend::Sqlite
+        } else {
+            Backend::Files
+        }
+    }
+}
+
+impl Display for Backend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Backend::Files => write!(f, "files"),
+            Backend::Sqlite => write!(f, "sqlite"),
+        }
+    }
+}
+
+/// Open the storage root with `backend`. File storage in a git repository commits every change, and
+/// encrypted file storage needs its key.
+pub fn open(layout: StorageLayout, backend: Backend) -> Result<Box<dyn Storage>, Error> {
+    match backend {
+        Backend::Files if layout.git_path().exists() => Ok(Box::new(GitStorage::open(layout)?)),
+        Backend::Files => Ok(Box::new(FileStorage::open(layout)?)),
+        Backend::Sqlite => Ok(Box::new(SqliteStorage::open(layout)?)),
+    }
+}
@@ -28,7 +28,7 @@ impl Settings {
             return Ok(None);
         }
 
-        let config: Config = json::read(&filepath)?;
+        let config = Config::from_buffer(text::read(&filepath)?.as_bytes())?;
 
         Ok(Some(config))
     }
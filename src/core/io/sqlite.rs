@@ -0,0 +1,525 @@
+use std::fs;
+use std::path::PathBuf;
+
+use chrono::DateTime;
+use chrono::SecondsFormat;
+use chrono::TimeZone as _;
+use chrono::Utc;
+use rusqlite::Connection;
+use rusqlite::OptionalExtension as _;
+use rusqlite::params;
+
+use crate::Config;
+use crate::Error;
+use crate::Event;
+use crate::Link;
+use crate::Snapshot;
+use crate::State;
+use crate::Stream;
+use crate::layout::StorageLayout;
+use crate::replay;
+
+use super::Storage;
+use super::util::*;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS streams (
+        name TEXT PRIMARY KEY,
+        chained INTEGER NOT NULL DEFAULT 0
+    );
+    CREATE TABLE IF NOT EXISTS events (
+        seq INTEGER PRIMARY KEY AUTOINCREMENT,
+        stream TEXT NOT NULL REFERENCES streams (name),
+        event_id TEXT NOT NULL,
+        entity_id TEXT NOT NULL,
+        timestamp TEXT,
+        body TEXT NOT NULL,
+        UNIQUE (stream, event_id)
+    );
+    CREATE INDEX IF NOT EXISTS events_by_timestamp ON events (stream, timestamp);
+    CREATE INDEX IF NOT EXISTS events_by_entity ON events (stream, entity_id);
+    CREATE TABLE IF NOT EXISTS snapshots (
+        stream TEXT PRIMARY KEY REFERENCES streams (name),
+        body TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS settings (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );
+";
+
+/// Streams kept in an embedded SQLite database at the storage root
+///
+/// Events are stored one per row in the order they were appended, indexed by the timestamp of the
+/// entity they carry. Per-stream configs stay in files so that they can be edited by hand.
+pub struct SqliteStorage {
+    layout: StorageLayout,
+    connection: Connection,
+}
+
+impl SqliteStorage {
+    /// Open the database at the storage root, creating it if needed
+    pub fn open(layout: StorageLayout) -> Result<Self, Error> {
+        fs::create_dir_all(&layout.root)
+            .map_err(|err| Error::StorageInitializationFailed(err.to_string()))?;
+
+        let connection = Connection::open(layout.database_path()).map_err(failed)?;
+        connection.execute_batch(SCHEMA).map_err(failed)?;
+        add_chained(&connection)?;
+
+        Ok(Self { layout, connection })
+    }
+
+    /// Whether the stream was stored as chained, see `Stream::from_links_since`
+    fn chained(&self, stream: &str) -> Result<bool, Error> {
+        self.connection
+            .query_row(
+                "SELECT chained FROM streams WHERE name = ?1",
+                [stream],
+                |row| row.get(0),
+            )
+            .map_err(failed)
+    }
+
+    fn set_chained(&self, stream: &str, events: &Stream) -> Result<(), Error> {
+        self.connection
+            .execute(
+                "UPDATE streams SET chained = ?2 WHERE name = ?1",
+                params![stream, events.is_chained()],
+            )
+            .map_err(failed)?;
+
+        Ok(())
+    }
+
+    fn links(&self, query: &str, params: impl rusqlite::Params) -> Result<Vec<Link>, Error> {
+        let mut statement = self.connection.prepare(query).map_err(failed)?;
+        let bodies = statement
+            .query_map(params, |row| row.get::<_, String>(0))
+            .map_err(failed)?;
+
+        let mut links = vec![];
+        for body in bodies {
+            let link: Link = serde_json::from_str(&body.map_err(failed)?)
+                .map_err(|err| Error::DeserializeFailed(err.to_string()))?;
+            links.push(link);
+        }
+
+        Ok(links)
+    }
+
+    fn insert(&self, stream: &str, links: &[Link]) -> Result<(), Error> {
+        let mut statement = self
+            .connection
+            .prepare(
+                "INSERT INTO events (stream, event_id, entity_id, timestamp, body)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+            )
+            .map_err(failed)?;
+
+        for link in links {
+            let event = &link.event;
+            let timestamp = match event {
+                Event::Create { entity, .. } | Event::Edit { entity, .. } => {
+                    Some(sortable(entity.timestamp()))
+                }
+                Event::Delete { .. }
+                | Event::Lock { .. }
+                | Event::Unlock { .. }
+                | Event::Submit { .. } => None,
+            };
+            // Locks and submissions belong to no entity, and are filed under their own id
+            let entity_id = event.entity_id().unwrap_or(event.id());
+            let body = serde_json::to_string(link)
+                .map_err(|err| Error::SerializeFailed(err.to_string()))?;
+
+            statement
+                .execute(params![
+                    stream,
+                    event.id().to_string(),
+                    entity_id.to_string(),
+                    timestamp,
+                    body
+                ])
+                .map_err(failed)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn current_stream(&self) -> Result<Option<String>, Error> {
+        self.connection
+            .query_row(
+                "SELECT value FROM settings WHERE key = 'current_stream'",
+                [],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(failed)
+    }
+
+    fn write_current_stream(&self, stream: &str) -> Result<(), Error> {
+        self.connection
+            .execute(
+                "INSERT OR REPLACE INTO settings (key, value) VALUES ('current_stream', ?1)",
+                [stream],
+            )
+            .map_err(failed)?;
+
+        Ok(())
+    }
+
+    fn streams(&self) -> Result<Vec<String>, Error> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT name FROM streams ORDER BY name")
+            .map_err(failed)?;
+
+        statement
+            .query_map([], |row| row.get(0))
+            .map_err(failed)?
+            .collect::<Result<_, _>>()
+            .map_err(failed)
+    }
+
+    fn stream_exists(&self, stream: &str) -> Result<bool, Error> {
+        self.connection
+            .query_row(
+                "SELECT EXISTS (SELECT 1 FROM streams WHERE name = ?1)",
+                [stream],
+                |row| row.get(0),
+            )
+            .map_err(failed)
+    }
+
+    fn stream_create(&self, stream: &str) -> Result<(), Error> {
+        if self.stream_exists(stream)? {
+            return Err(Error::StreamAlreadyExists(stream.to_string()));
+        }
+
+        self.connection
+            .execute("INSERT INTO streams (name) VALUES (?1)", [stream])
+            .map_err(|err| Error::StreamCreationFailed(err.to_string()))?;
+
+        Ok(())
+    }
+
+    fn read(&self, stream: &str) -> Result<Stream, Error> {
+        let links = self.links(
+            "SELECT body FROM events WHERE stream = ?1 ORDER BY seq",
+            [stream],
+        )?;
+
+        Stream::from_links_since(links, self.chained(stream)?, None)
+    }
+
+    fn read_since(&self, stream: &str, verified: (usize, &str)) -> Result<Stream, Error> {
+        let links = self.links(
+            "SELECT body FROM events WHERE stream = ?1 ORDER BY seq",
+            [stream],
+        )?;
+
+        Stream::from_links_since(links, self.chained(stream)?, Some(verified))
+    }
+
+    fn append(&self, stream: &str, events: Vec<Event>) -> Result<(), Error> {
+        let transaction = self.connection.unchecked_transaction().map_err(failed)?;
+
+        let mut validated = self.read(stream)?;
+        let appended = validated.events().len();
+        for event in events {
+            validated.push(event)?;
+        }
+        self.insert(stream, &validated.links()[appended..])?;
+        self.set_chained(stream, &validated)?;
+
+        transaction.commit().map_err(failed)
+    }
+
+    fn replace(&self, stream: &str, events: &Stream) -> Result<(), Error> {
+        let transaction = self.connection.unchecked_transaction().map_err(failed)?;
+
+        self.connection
+            .execute("DELETE FROM snapshots WHERE stream = ?1", [stream])
+            .map_err(failed)?;
+        self.connection
+            .execute("DELETE FROM events WHERE stream = ?1", [stream])
+            .map_err(failed)?;
+        self.insert(stream, &events.links())?;
+        self.set_chained(stream, events)?;
+
+        transaction.commit().map_err(failed)
+    }
+
+    fn backup(&self, _stream: &str) -> Result<PathBuf, Error> {
+        let suffix = Utc::now().format("%Y%m%dT%H%M%S").to_string();
+        let backup = self.layout.database_backup_path(&suffix);
+
+        self.connection
+            .execute("VACUUM INTO ?1", [backup.to_string_lossy()])
+            .map_err(|err| Error::FailedToWriteFile(err.to_string()))?;
+
+        Ok(backup)
+    }
+
+    fn read_snapshot(&self, stream: &str) -> Option<Snapshot> {
+        let body: String = self
+            .connection
+            .query_row(
+                "SELECT body FROM snapshots WHERE stream = ?1",
+                [stream],
+                |row| row.get(0),
+            )
+            .ok()?;
+
+        serde_json::from_str(&body).ok()
+    }
+
+    fn write_snapshot(&self, stream: &str, snapshot: &Snapshot) -> Result<(), Error> {
+        let body = serde_json::to_string(snapshot)
+            .map_err(|err| Error::SerializeFailed(err.to_string()))?;
+
+        self.connection
+            .execute(
+                "INSERT OR REPLACE INTO snapshots (stream, body) VALUES (?1, ?2)",
+                params![stream, body],
+            )
+            .map_err(failed)?;
+
+        Ok(())
+    }
+
+    fn config(&self, stream: &str) -> Result<Option<Config>, Error> {
+        let path = self.layout.config_path(stream);
+
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let config = Config::from_buffer(text::read(&path)?.as_bytes())?;
+
+        Ok(Some(config))
+    }
+
+    /// Only the events of entities that were at some point in `from..to` are replayed
+    fn state_between(
+        &self,
+        stream_name: &Option<String>,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<State, Error> {
+        let stream_name = self.coalease_stream_name(stream_name)?;
+
+        if !self.stream_exists(&stream_name)? {
+            return Err(Error::StreamDoesNotExist(stream_name));
+        }
+
+        // The selected events can't be verified on their own, as each hash covers every event
+        // before it, so the stream is verified first
+        self.read_verified(&stream_name)?;
+
+        let links = self.links(
+            "SELECT body FROM events
+             WHERE stream = ?1 AND entity_id IN (
+                 SELECT entity_id FROM events
+                 WHERE stream = ?1 AND timestamp >= ?2 AND timestamp < ?3
+             )
+             ORDER BY seq",
+            params![stream_name, sortable(&from), sortable(&to)],
+        )?;
+
+        Ok(replay(&Stream::from_links(links))
+            .filter(|entity| (from..to).contains(entity.timestamp())))
+    }
+}
+
+/// Databases created before streams recorded whether they are chained get the column, set for
+/// streams with any hashed event
+fn add_chained(connection: &Connection) -> Result<(), Error> {
+    let exists: bool = connection
+        .query_row(
+            "SELECT EXISTS (SELECT 1 FROM pragma_table_info('streams') WHERE name = 'chained')",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(failed)?;
+    if exists {
+        return Ok(());
+    }
+
+    connection
+        .execute_batch(
+            "ALTER TABLE streams ADD COLUMN chained INTEGER NOT NULL DEFAULT 0;
+             UPDATE streams SET chained = 1 WHERE name IN (
+                 SELECT stream FROM events WHERE json_extract(body, '$.hash') IS NOT NULL
+             );",
+        )
+        .map_err(failed)
+}
+
+/// Timestamps are stored with a fixed width so that they sort as text. Years beyond four digits
+/// would break the width, so unbounded ranges are clamped.
+fn sortable(timestamp: &DateTime<Utc>) -> String {
+    let first = Utc.with_ymd_and_hms(0, 1, 1, 0, 0, 0).unwrap();
+    let last = Utc.with_ymd_and_hms(9999, 12, 31, 23, 59, 59).unwrap();
+
+    timestamp
+        .clamp(&first, &last)
+        .to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+fn failed(err: rusqlite::Error) -> Error {
+    Error::DatabaseFailed(err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use chrono::TimeZone as _;
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::Entity;
+    use crate::Id;
+
+    fn login(day: u32) -> Event {
+        Event::Create {
+            id: Id::new(),
+            created_at: Utc::now(),
+            entity: Entity::Login {
+                id: Id::new(),
+                timestamp: Utc.with_ymd_and_hms(2025, 9, day, 8, 0, 0).unwrap(),
+                tz: None,
+                note: None,
+                tags: BTreeSet::new(),
+            },
+        }
+    }
+
+    fn open(temp: &TempDir) -> SqliteStorage {
+        let layout = StorageLayout::coalesce(None, Some(temp.path().to_path_buf()));
+        SqliteStorage::open(layout).unwrap()
+    }
+
+    #[test]
+    fn streams_and_current_stream() {
+        let temp = TempDir::new().unwrap();
+        let io = open(&temp);
+
+        assert_eq!(io.current_stream().unwrap(), None);
+        io.stream_create("b").unwrap();
+        io.stream_create("a").unwrap();
+        assert!(matches!(
+            io.stream_create("a"),
+            Err(Error::StreamAlreadyExists(_))
+        ));
+
+        io.set_current_stream("a").unwrap();
+        assert!(io.set_current_stream("c").is_err());
+
+        assert_eq!(io.streams().unwrap(), ["a", "b"]);
+        assert_eq!(io.current_stream().unwrap(), Some("a".to_string()));
+        assert!(temp.path().join("skuff.db").exists());
+    }
+
+    #[test]
+    fn append_is_validated_and_atomic() {
+        let temp = TempDir::new().unwrap();
+        let io = open(&temp);
+        let stream = Some("work".to_string());
+        io.stream_create("work").unwrap();
+
+        let first = login(1);
+        io.stream_append(first.clone(), &stream).unwrap();
+
+        let delete = Event::Delete {
+            id: Id::new(),
+            created_at: Utc::now(),
+            entity_id: Id::new(),
+        };
+        assert!(io.stream_extend(vec![login(2), delete], &stream).is_err());
+
+        let events = io.stream(&stream).unwrap();
+        assert_eq!(events.events().len(), 1);
+        assert_eq!(events.events()[0].id(), first.id());
+    }
+
+    #[test]
+    fn state_between_uses_recorded_timestamps() {
+        let temp = TempDir::new().unwrap();
+        let io = open(&temp);
+        let stream = Some("work".to_string());
+        io.stream_create("work").unwrap();
+
+        let moved = login(1);
+        let Event::Create { entity, .. } = &moved else {
+            unreachable!()
+        };
+        let mut edited = entity.clone();
+        edited.set_timestamp(Utc.with_ymd_and_hms(2025, 9, 20, 8, 0, 0).unwrap());
+        let edit = Event::Edit {
+            id: Id::new(),
+            created_at: Utc::now(),
+            entity: edited,
+        };
+        io.stream_extend(vec![moved, login(2), login(3), edit], &stream)
+            .unwrap();
+
+        let from = Utc.with_ymd_and_hms(2025, 9, 1, 0, 0, 0).unwrap();
+        let to = Utc.with_ymd_and_hms(2025, 9, 3, 0, 0, 0).unwrap();
+        assert_eq!(
+            io.state_between(&stream, from, to).unwrap().iter().count(),
+            1
+        );
+
+        let to = Utc.with_ymd_and_hms(2025, 10, 1, 0, 0, 0).unwrap();
+        assert_eq!(
+            io.state_between(&stream, from, to).unwrap().iter().count(),
+            3
+        );
+
+        // Altering an event in the range is noticed, unless it was verified when the snapshot was
+        // taken
+        io.connection
+            .execute_batch(
+                "UPDATE events SET body = json_set(body, '$.note', 'altered') WHERE seq = 2;
+                 DELETE FROM snapshots;",
+            )
+            .unwrap();
+        assert!(matches!(
+            io.state_between(&stream, from, to),
+            Err(Error::BrokenChain(_))
+        ));
+    }
+
+    #[test]
+    fn stripped_hashes_are_noticed_in_older_databases() {
+        let temp = TempDir::new().unwrap();
+        let io = open(&temp);
+        let stream = Some("work".to_string());
+        io.stream_create("work").unwrap();
+        io.stream_append(login(1), &stream).unwrap();
+
+        // As the database was before streams recorded whether they are chained
+        io.connection
+            .execute_batch(
+                "ALTER TABLE streams DROP COLUMN chained;
+                 UPDATE events SET body = json_remove(body, '$.hash');",
+            )
+            .unwrap();
+        drop(io);
+
+        let io = open(&temp);
+        assert!(!io.chained("work").unwrap());
+        assert!(io.stream(&stream).is_ok());
+
+        io.stream_append(login(2), &stream).unwrap();
+        io.connection
+            .execute("UPDATE events SET body = json_remove(body, '$.hash')", [])
+            .unwrap();
+        assert!(matches!(io.stream(&stream), Err(Error::BrokenChain(_))));
+    }
+}
@@ -1,220 +1,205 @@
-use std::fs;
+use std::path::PathBuf;
+
+use chrono::DateTime;
+use chrono::Utc;
 
 use crate::Config;
 use crate::Error;
 use crate::Event;
+use crate::Snapshot;
+use crate::State;
 use crate::Stream;
-use crate::layout::StorageLayout;
-
-use super::util::*;
-
-pub struct Storage {
-    layout: StorageLayout,
-}
+use crate::keeps_locked_period;
+use crate::replay;
+use crate::replay_since;
+use crate::snapshot;
 
-impl Storage {
-    pub fn new(layout: StorageLayout) -> Self {
-        Self { layout }
-    }
-
-    pub fn current_stream(&self) -> Result<Option<String>, Error> {
-        let path = self.layout.current_stream_path();
+use super::Backup;
 
-        if !path.exists() {
-            return Ok(None);
-        }
+/// Where streams, the current stream and per-stream configs are kept
+///
+/// Backends implement the primitives on stream names. Commands use the provided methods, which
+/// fall back to the current stream when no name is given.
+pub trait Storage {
+    fn current_stream(&self) -> Result<Option<String>, Error>;
 
-        let current = text::read(&path)?;
+    fn write_current_stream(&self, stream: &str) -> Result<(), Error>;
 
-        Ok(Some(current))
-    }
+    fn streams(&self) -> Result<Vec<String>, Error>;
 
-    pub fn set_current_stream(&self, stream: &str) -> Result<(), Error> {
-        let path = self.layout.current_stream_path();
+    fn stream_exists(&self, stream: &str) -> Result<bool, Error>;
 
-        if !path.exists() {
-            return Err(Error::StreamDoesNotExist(stream.to_string()));
-        }
+    fn stream_create(&self, stream: &str) -> Result<(), Error>;
 
-        text::write(&path, stream)?;
+    fn read(&self, stream: &str) -> Result<Stream, Error>;
 
-        Ok(())
+    /// Like `read`, only verifying the events after the first `events`, which were verified to
+    /// hash to `head` before
+    fn read_since(&self, stream: &str, _verified: (usize, &str)) -> Result<Stream, Error> {
+        self.read(stream)
     }
 
-    pub fn stream_exists(&self, stream: &str) -> Result<bool, Error> {
-        let path = self.layout.stream_path(stream);
+    /// Append events to an existing stream. Either all events are valid and appended, or none are.
+    fn append(&self, stream: &str, events: Vec<Event>) -> Result<(), Error>;
 
-        Ok(path.exists())
-    }
+    /// Replace the history of an existing stream, dropping its snapshot. The previous history is
+    /// not kept, see `stream_replace`.
+    fn replace(&self, stream: &str, events: &Stream) -> Result<(), Error>;
 
-    pub fn stream_create(&self, stream: &str) -> Result<(), Error> {
-        let path = self.layout.stream_path(stream);
+    /// Copy a stream aside before its history is rewritten, returning where the copy is. File
+    /// storage keeps it with the other backups of the stream.
+    fn backup(&self, stream: &str) -> Result<PathBuf, Error>;
 
-        if path.exists() {
-            return Err(Error::StreamAlreadyExists(stream.to_string()));
-        }
+    /// The stream's snapshot, if it has a readable one
+    fn read_snapshot(&self, stream: &str) -> Option<Snapshot>;
 
-        if let Some(parent) = path.parent() {
-            fs::create_dir_all(parent)
-                .map_err(|err| Error::StreamCreationFailed(err.to_string()))?;
-        }
+    fn write_snapshot(&self, stream: &str, snapshot: &Snapshot) -> Result<(), Error>;
 
-        let buf = Stream::new().to_buffer()?;
+    fn config(&self, stream: &str) -> Result<Option<Config>, Error>;
 
-        fs::write(&path, buf).map_err(|err| Error::FailedToWriteFile(err.to_string()))?;
+    /// Earlier versions of a stream kept by the storage, newest first
+    fn backups(&self, _stream: &str) -> Result<Vec<Backup>, Error> {
+        Err(Error::BackupFailed(
+            "backups are only kept for file storage".to_string(),
+        ))
+    }
 
-        Ok(())
+    /// Load an earlier version of a stream, verifying it like the stream itself
+    fn read_backup(&self, _backup: &Backup) -> Result<Stream, Error> {
+        Err(Error::BackupFailed(
+            "backups are only kept for file storage".to_string(),
+        ))
     }
 
-    pub fn stream(&self, stream_name: &Option<String>) -> Result<Stream, Error> {
-        let stream_name = self.coalease_stream_name(&stream_name)?;
+    /// The entities of a stream with a timestamp in `from..to`
+    fn state_between(
+        &self,
+        stream_name: &Option<String>,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<State, Error> {
+        let state = self.state(stream_name)?;
 
-        let path = self.layout.stream_path(&stream_name);
+        Ok(state.filter(|entity| (from..to).contains(entity.timestamp())))
+    }
 
-        if !path.exists() {
-            return Err(Error::StreamDoesNotExist(stream_name));
+    fn set_current_stream(&self, stream: &str) -> Result<(), Error> {
+        if !self.stream_exists(stream)? {
+            return Err(Error::StreamDoesNotExist(stream.to_string()));
         }
 
-        let buf = fs::read(&path).map_err(|err| Error::FailedToReadFile(err.to_string()))?;
-
-        Stream::from_buffer(&buf)
+        self.write_current_stream(stream)
     }
 
-    pub fn stream_append(&self, event: Event, stream_name: &Option<String>) -> Result<(), Error> {
-        let stream_name = self.coalease_stream_name(&stream_name)?;
+    fn stream(&self, stream_name: &Option<String>) -> Result<Stream, Error> {
+        let stream_name = self.coalease_stream_name(stream_name)?;
 
         if !self.stream_exists(&stream_name)? {
-            return Err(Error::StreamDoesNotExist(stream_name.to_string()));
+            return Err(Error::StreamDoesNotExist(stream_name));
         }
 
-        let path = self.layout.stream_path(&stream_name);
-
-        let buf = fs::read(&path).map_err(|err| Error::FailedToReadFile(err.to_string()))?;
-
-        let mut stream = Stream::from_buffer(&buf)?;
-
-        let _ = stream.push(event);
-
-        let buf = stream.to_buffer()?;
-        fs::write(&path, buf).map_err(|err| Error::FailedToWriteFile(err.to_string()))?;
-
-        Ok(())
+        self.read(&stream_name)
     }
 
-    pub fn streams(&self) -> Result<Vec<String>, Error> {
-        let path = self.layout.streams_path();
+    /// Replay a stream, starting from its snapshot if it has a usable one
+    fn state(&self, stream_name: &Option<String>) -> Result<State, Error> {
+        let stream_name = self.coalease_stream_name(stream_name)?;
 
-        if !path.exists() {
-            return Ok(vec![]);
+        if !self.stream_exists(&stream_name)? {
+            return Err(Error::StreamDoesNotExist(stream_name));
         }
 
-        let streams = dirs::dirs(&path)
-            .map_err(|err| Error::FailedToReadDir(err.to_string()))?
-            .iter()
-            .filter(|p| p.is_dir())
-            .map(|p| p.file_name().unwrap().to_string_lossy().to_string())
-            .collect::<Vec<String>>();
-
-        Ok(streams)
+        Ok(self.replayed(&stream_name)?.0)
     }
 
-    pub fn config_file(&self, stream_name: &Option<String>) -> Result<Option<Config>, Error> {
-        let stream_name = self.coalease_stream_name(&stream_name)?;
+    /// A stream, of which only the events after its snapshot are verified if it has an intact one.
+    /// The snapshot is returned along with it.
+    fn read_verified(&self, stream: &str) -> Result<(Stream, Option<Snapshot>), Error> {
+        let snapshot = self.read_snapshot(stream).filter(Snapshot::is_intact);
+        let events = match &snapshot {
+            Some(Snapshot {
+                events,
+                head: Some(head),
+                ..
+            }) => self.read_since(stream, (*events, head))?,
+            _ => self.read(stream)?,
+        };
 
-        let path = self.layout.config_path(&stream_name);
-
-        if !path.exists() {
-            return Ok(None);
-        }
+        Ok((events, snapshot))
+    }
 
-        let config: Config = json::read(&path)?;
+    /// A stream and its replayed state. Only the events after the snapshot, if the stream has a
+    /// usable one, are verified and replayed. A snapshot that was changed since it was taken is
+    /// ignored, and the whole stream is verified and replayed instead.
+    fn replayed(&self, stream: &str) -> Result<(State, Stream), Error> {
+        let (events, snapshot) = self.read_verified(stream)?;
+        let state = snapshot
+            .and_then(|snapshot| replay_since(&snapshot, &events))
+            .unwrap_or_else(|| replay(&events));
 
-        Ok(Some(config))
+        Ok((state, events))
     }
 
-    fn coalease_stream_name(&self, stream_name: &Option<String>) -> Result<String, Error> {
-        match stream_name {
-            Some(s) => Ok(s.clone()),
-            None => match self.current_stream()? {
-                Some(s) => Ok(s),
-                None => Err(Error::NoStreamSet),
-            },
-        }
+    fn stream_append(&self, event: Event, stream_name: &Option<String>) -> Result<(), Error> {
+        self.stream_extend(vec![event], stream_name)
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use std::path::PathBuf;
+    /// Append several events at once. Either all events are valid and appended, or none are.
+    fn stream_extend(&self, events: Vec<Event>, stream_name: &Option<String>) -> Result<(), Error> {
+        let stream_name = self.coalease_stream_name(stream_name)?;
 
-    use tempfile::TempDir;
-
-    use super::*;
+        if !self.stream_exists(&stream_name)? {
+            return Err(Error::StreamDoesNotExist(stream_name));
+        }
 
-    #[test]
-    fn create_new_stream() {
-        let env = TestEnv::new();
-        let io = Storage::new(env.layout());
+        self.append(&stream_name, events)?;
 
-        io.stream_create("stream1").unwrap();
+        // Reading the state doesn't write the snapshot, so it is brought up to date here. It only
+        // saves time, so failing to write it is not an error.
+        if let Ok((state, stream)) = self.replayed(&stream_name) {
+            let _ = self.write_snapshot(&stream_name, &snapshot(&state, &stream));
+        }
 
-        let expected_path = env.root.join("streams/stream1/stream.json");
-        assert!(expected_path.exists());
+        Ok(())
     }
 
-    #[test]
-    fn create_stream_fails_if_already_exists() {
-        let env = TestEnv::new();
-        let io = Storage::new(env.layout());
+    /// Replace the history of a stream, keeping the previous history as a backup. Returns where
+    /// the backup is. Fails if the locked period of the stream would change.
+    fn stream_replace(
+        &self,
+        stream: &Stream,
+        stream_name: &Option<String>,
+    ) -> Result<PathBuf, Error> {
+        let stream_name = self.coalease_stream_name(stream_name)?;
 
-        // First create succeeds
-        io.stream_create("stream1").unwrap();
-        // Second create should fail
-        let err = io.stream_create("stream1").unwrap_err();
+        if !self.stream_exists(&stream_name)? {
+            return Err(Error::StreamDoesNotExist(stream_name));
+        }
 
-        match err {
-            Error::StreamAlreadyExists(s) => assert_eq!(s, "stream1"),
-            other => panic!("expected StreamAlreadyExists, got {:?}", other),
+        // A stream that no longer loads can only be replaced, e.g. by a backup, without the check
+        if let Ok(current) = self.read(&stream_name) {
+            keeps_locked_period(&current, stream)?;
         }
-    }
 
-    #[test]
-    fn exists_returns_false_if_stream_missing() {
-        let env = TestEnv::new();
-        let io = Storage::new(env.layout());
+        let backup = self.backup(&stream_name)?;
+        self.replace(&stream_name, stream)?;
 
-        let result = io.stream_exists("stream1").unwrap();
-        assert!(!result);
+        Ok(backup)
     }
 
-    #[test]
-    fn exists_returns_true_if_stream_exists() {
-        let env = TestEnv::new();
-        let io = Storage::new(env.layout());
+    fn config_file(&self, stream_name: &Option<String>) -> Result<Option<Config>, Error> {
+        let stream_name = self.coalease_stream_name(stream_name)?;
 
-        // Manually create the directory
-        let stream_path = env.root.join("streams/stream1");
-        std::fs::create_dir_all(&stream_path).unwrap();
-        std::fs::File::create(stream_path.join("stream.json")).unwrap();
-
-        let result = io.stream_exists("stream1").unwrap();
-        assert!(result);
-    }
-
-    struct TestEnv {
-        root: PathBuf,
-        _temp: TempDir, // keep TempDir alive so it cleans up
+        self.config(&stream_name)
     }
 
-    impl TestEnv {
-        fn new() -> Self {
-            let temp = TempDir::new().expect("failed to create tempdir");
-            let root = temp.path().to_path_buf();
-            Self { root, _temp: temp }
-        }
-
-        fn layout(&self) -> StorageLayout {
-            StorageLayout::coalesce(None, Some(self.root.clone()))
+    fn coalease_stream_name(&self, stream_name: &Option<String>) -> Result<String, Error> {
+        match stream_name {
+            Some(s) => Ok(s.clone()),
+            None => match self.current_stream()? {
+                Some(s) => Ok(s),
+                None => Err(Error::NoStreamSet),
+            },
         }
     }
 }
@@ -8,6 +8,7 @@ lazy_static::lazy_static! {
     };
 }
 
+#[derive(Clone)]
 pub struct StorageLayout {
     pub root: PathBuf,
 }
@@ -25,6 +26,19 @@ impl StorageLayout {
         self.root.join(format!("streams/{}/stream.json", stream))
     }
 
+    pub fn snapshot_path(&self, stream: &str) -> PathBuf {
+        self.root.join(format!("streams/{}/snapshot.json", stream))
+    }
+
+    pub fn backup_path(&self, stream: &str, suffix: &str) -> PathBuf {
+        self.root
+            .join(format!("streams/{}/stream.{}.json", stream, suffix))
+    }
+
+    pub fn backups_path(&self, stream: &str) -> PathBuf {
+        self.root.join(format!("backups/{}", stream))
+    }
+
     pub fn streams_path(&self) -> PathBuf {
         self.root.join("streams")
     }
@@ -33,9 +47,30 @@ impl StorageLayout {
         self.root.join(format!("streams/{}/config.json", stream))
     }
 
+    pub fn config_backup_path(&self, stream: &str, suffix: &str) -> PathBuf {
+        self.root
+            .join(format!("streams/{}/config.{}.json", stream, suffix))
+    }
+
     pub fn current_stream_path(&self) -> PathBuf {
         self.root.join("CURRENT_STREAM")
     }
+
+    pub fn git_path(&self) -> PathBuf {
+        self.root.join(".git")
+    }
+
+    pub fn database_path(&self) -> PathBuf {
+        self.root.join("skuff.db")
+    }
+
+    pub fn encryption_path(&self) -> PathBuf {
+        self.root.join("ENCRYPTION")
+    }
+
+    pub fn database_backup_path(&self, suffix: &str) -> PathBuf {
+        self.root.join(format!("skuff.{}.db", suffix))
+    }
 }
 
 #[cfg(test)]
@@ -1,56 +1,76 @@
+use std::io::ErrorKind;
+use std::io::Write as _;
+
 use super::Entity;
+use super::Error;
 use super::State;
-use chrono::Local;
+use super::Zone;
 use colored::Colorize;
 
-pub fn print(events: &State) {
+/// Print the state, newest first. Timestamps are shown in the zone they were recorded in unless
+/// `tz` overrides it. Entities recorded in another zone than this machine's are annotated with it.
+///
+/// Printing stops quietly when the reader goes away, e.g. when piped into `head`.
+pub fn print(events: &State, tz: Option<&Zone>) -> Result<(), Error> {
+    let mut stdout = std::io::stdout().lock();
     for entity in events.iter().rev() {
-        match entity {
-            Entity::Login { id, timestamp } => {
-                let timestamp = timestamp.with_timezone(&Local).format("%Y-%m-%d %H:%M");
-
-                println!("{}", id.to_string().yellow());
-                println!("Login @ {}", timestamp);
-            }
-            Entity::Logout { id, timestamp } => {
-                let timestamp = timestamp.with_timezone(&Local).format("%Y-%m-%d %H:%M");
+        let written = writeln!(
+            stdout,
+            "{}\n{}\n",
+            entity.id().to_string().yellow(),
+            describe(entity, tz)
+        );
+        match written {
+            Err(err) if err.kind() == ErrorKind::BrokenPipe => return Ok(()),
+            Err(err) => return Err(Error::FailedToWriteFile(format!("stdout: {}", err))),
+            Ok(()) => {}
+        }
+    }
 
-                println!("{}", id.to_string().yellow());
-                println!("Logout @ {}", timestamp);
-            }
-            Entity::Break {
-                id,
-                timestamp,
-                duration,
-                autoinsert,
-            } => {
-                let timestamp = timestamp.with_timezone(&Local).format("%Y-%m-%d %H:%M");
+    Ok(())
+}
 
-                println!("{}", id.to_string().yellow());
-                print!("Break @ {} for {}", timestamp, duration);
-                if *autoinsert {
-                    print!(" (auto)");
-                }
-                println!();
-            }
-            Entity::Activity {
-                id,
-                timestamp,
-                duration,
-                value,
-                autoinsert,
-            } => {
-                let timestamp = timestamp.with_timezone(&Local).format("%Y-%m-%d %H:%M");
+/// One-line description of an entity, e.g. "Break @ 2025-09-01 11:30 for PT1800S +away \"lunch\""
+pub fn describe(entity: &Entity, tz: Option<&Zone>) -> String {
+    let timestamp = entity.local_timestamp(tz).format("%Y-%m-%d %H:%M");
+    let zone = match (tz, entity.tz()) {
+        (None, Some(zone)) if zone != Zone::local() => format!(" ({})", zone),
+        _ => String::new(),
+    };
 
-                println!("{}", id.to_string().yellow());
-                print!("Activity: {} @ {} for {}", value, timestamp, duration);
-                if *autoinsert {
-                    print!(" (auto)");
-                }
-                println!();
-            }
+    let line = match entity {
+        Entity::Login { .. } => format!("Login @ {}{}", timestamp, zone),
+        Entity::Logout { .. } => format!("Logout @ {}{}", timestamp, zone),
+        Entity::Break {
+            duration,
+            autoinsert,
+            ..
+        } => {
+            let auto = if *autoinsert { " (auto)" } else { "" };
+            format!("Break @ {}{} for {}{}", timestamp, zone, duration, auto)
+        }
+        Entity::Activity {
+            duration,
+            value,
+            autoinsert,
+            ..
+        } => {
+            let auto = if *autoinsert { " (auto)" } else { "" };
+            format!(
+                "Activity: {} @ {}{} for {}{}",
+                value, timestamp, zone, duration, auto
+            )
         }
+    };
+
+    let tags: String = entity
+        .tags()
+        .iter()
+        .map(|tag| format!(" +{}", tag))
+        .collect();
 
-        println!();
+    match entity.note() {
+        Some(note) => format!("{}{} \"{}\"", line, tags, note),
+        None => format!("{}{}", line, tags),
     }
 }
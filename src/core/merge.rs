@@ -0,0 +1,289 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use serde::Serialize;
+
+use super::Entity;
+use super::Error;
+use super::Event;
+use super::Id;
+use super::Stream;
+use super::Zone;
+use super::log;
+use super::replay;
+
+/// Two copies of a stream combined into one
+pub struct Merge {
+    pub stream: Stream,
+    /// Number of events that were only in the other copy
+    pub added: usize,
+    pub conflicts: Vec<Conflict>,
+}
+
+/// An entity that was changed differently in each copy. None means it was removed.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Conflict {
+    pub entity_id: Id,
+    pub ours: Option<Entity>,
+    pub theirs: Option<Entity>,
+}
+
+impl Conflict {
+    /// e.g. "<id>: Login @ 2025-09-01 08:05 here, removed there"
+    pub fn describe(&self, tz: Option<&Zone>) -> String {
+        let side = |entity: &Option<Entity>| match entity {
+            Some(entity) => log::describe(entity, tz),
+            None => "removed".to_string(),
+        };
+
+        format!(
+            "{}: {} here, {} there",
+            self.entity_id,
+            side(&self.ours),
+            side(&self.theirs)
+        )
+    }
+}
+
+/// Combine two copies of a stream that were appended to separately
+///
+/// Events in both copies are taken once, by event id, and interleaved by when they were created, so
+/// the latest change to an entity wins. An entity removed in both copies is removed once. Entities
+/// that both copies changed into something different are reported as conflicts.
+pub fn merge(ours: &Stream, theirs: &Stream) -> Result<Merge, Error> {
+    let our_ids: HashSet<&Id> = ours.events().iter().map(Event::id).collect();
+    let their_ids: HashSet<&Id> = theirs.events().iter().map(Event::id).collect();
+
+    let mut events = interleave(
+        ours.events(),
+        theirs
+            .events()
+            .iter()
+            .filter(|event| !our_ids.contains(event.id())),
+    );
+
+    let mut deleted = HashSet::new();
+    events.retain(|event| match event {
+        Event::Delete { entity_id, .. } => deleted.insert(entity_id),
+        _ => true,
+    });
+
+    let mut stream = Stream::new();
+    for event in &events {
+        stream.push((*event).clone())?;
+    }
+
+    let changed = |stream: &Stream, other: &HashSet<&Id>| -> HashSet<Id> {
+        stream
+            .events()
+            .iter()
+            .filter(|event| !other.contains(event.id()))
+            .filter_map(|event| event.entity_id().cloned())
+            .collect()
+    };
+    let changed_by_us = changed(ours, &their_ids);
+    let changed_by_them = changed(theirs, &our_ids);
+
+    let entities = |stream: &Stream| -> HashMap<Id, Entity> {
+        replay(stream)
+            .iter()
+            .map(|entity| (entity.id().clone(), entity.clone()))
+            .collect()
+    };
+    let (our_entities, their_entities) = (entities(ours), entities(theirs));
+
+    let mut seen = HashSet::new();
+    let conflicts = events
+        .iter()
+        .filter_map(|event| event.entity_id())
+        .filter(|id| changed_by_us.contains(*id) && changed_by_them.contains(*id))
+        .filter(|id| seen.insert(*id))
+        .filter(|id| our_entities.get(*id) != their_entities.get(*id))
+        .map(|id| Conflict {
+            entity_id: id.clone(),
+            ours: our_entities.get(id).cloned(),
+            theirs: their_entities.get(id).cloned(),
+        })
+        .collect();
+
+    Ok(Merge {
+        added: events.len() - ours.events().len(),
+        stream,
+        conflicts,
+    })
+}
+
+/// Merge two sequences of events by when they were created, keeping the order within each, since
+/// an event may depend on an earlier one whose clock was ahead. Ours go first on ties.
+fn interleave<'a>(ours: &'a [Event], theirs: impl Iterator<Item = &'a Event>) -> Vec<&'a Event> {
+    let mut ours = ours.iter().peekable();
+    let mut theirs = theirs.peekable();
+    let mut events = vec![];
+
+    loop {
+        let next = match (ours.peek(), theirs.peek()) {
+            (Some(our), Some(their)) if their.created_at() < our.created_at() => theirs.next(),
+            (Some(_), _) => ours.next(),
+            (None, _) => theirs.next(),
+        };
+
+        match next {
+            Some(event) => events.push(event),
+            None => return events,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use chrono::DateTime;
+    use chrono::TimeZone as _;
+    use chrono::Utc;
+
+    use super::*;
+
+    fn at(minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 9, 1, 8, minute, 0).unwrap()
+    }
+
+    fn login(id: &Id, minute: u32) -> Entity {
+        Entity::Login {
+            id: id.clone(),
+            timestamp: at(minute),
+            tz: None,
+            note: None,
+            tags: BTreeSet::new(),
+        }
+    }
+
+    fn copy(stream: &Stream) -> Stream {
+        Stream::from_buffer(&stream.to_buffer().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn events_from_both_copies_are_taken_once() {
+        let (first, second, third) = (Id::new(), Id::new(), Id::new());
+
+        let mut ours = Stream::new();
+        ours.push(Event::Create {
+            id: Id::new(),
+            created_at: at(0),
+            entity: login(&first, 0),
+        })
+        .unwrap();
+        let mut theirs = copy(&ours);
+
+        ours.push(Event::Create {
+            id: Id::new(),
+            created_at: at(30),
+            entity: login(&third, 30),
+        })
+        .unwrap();
+        theirs
+            .push(Event::Create {
+                id: Id::new(),
+                created_at: at(10),
+                entity: login(&second, 10),
+            })
+            .unwrap();
+        for (stream, minute) in [(&mut ours, 40), (&mut theirs, 20)] {
+            stream
+                .push(Event::Delete {
+                    id: Id::new(),
+                    created_at: at(minute),
+                    entity_id: first.clone(),
+                })
+                .unwrap();
+        }
+
+        let merge = merge(&ours, &theirs).unwrap();
+
+        assert_eq!(merge.added, 1);
+        assert!(merge.conflicts.is_empty());
+        let state = replay(&merge.stream);
+        let ids: Vec<&Id> = state.iter().map(Entity::id).collect();
+        assert_eq!(ids, [&second, &third]);
+    }
+
+    #[test]
+    fn different_edits_of_an_entity_conflict() {
+        let (edited, removed) = (Id::new(), Id::new());
+
+        let mut ours = Stream::new();
+        for id in [&edited, &removed] {
+            ours.push(Event::Create {
+                id: Id::new(),
+                created_at: at(0),
+                entity: login(id, 0),
+            })
+            .unwrap();
+        }
+        let mut theirs = copy(&ours);
+
+        for (stream, minute) in [(&mut ours, 5), (&mut theirs, 10)] {
+            for id in [&edited, &removed] {
+                stream
+                    .push(Event::Edit {
+                        id: Id::new(),
+                        created_at: at(minute),
+                        entity: login(id, minute),
+                    })
+                    .unwrap();
+            }
+        }
+        theirs
+            .push(Event::Delete {
+                id: Id::new(),
+                created_at: at(15),
+                entity_id: removed.clone(),
+            })
+            .unwrap();
+
+        let merge = merge(&ours, &theirs).unwrap();
+
+        assert_eq!(merge.conflicts.len(), 2);
+        assert_eq!(merge.conflicts[0].entity_id, edited);
+        assert_eq!(merge.conflicts[0].theirs, Some(login(&edited, 10)));
+        assert_eq!(merge.conflicts[1].entity_id, removed);
+        assert_eq!(merge.conflicts[1].theirs, None);
+
+        let state = replay(&merge.stream);
+        assert_eq!(state.iter().collect::<Vec<_>>(), [&login(&edited, 10)]);
+    }
+
+    #[test]
+    fn each_copy_keeps_its_order() {
+        let (ours_id, theirs_id) = (Id::new(), Id::new());
+
+        let (mut ours, mut theirs) = (Stream::new(), Stream::new());
+
+        // The clock was set back between creating and editing each login
+        for (stream, id, minute) in [(&mut ours, &ours_id, 10), (&mut theirs, &theirs_id, 15)] {
+            stream
+                .push(Event::Create {
+                    id: Id::new(),
+                    created_at: at(minute + 20),
+                    entity: login(id, 0),
+                })
+                .unwrap();
+            stream
+                .push(Event::Edit {
+                    id: Id::new(),
+                    created_at: at(minute),
+                    entity: login(id, minute),
+                })
+                .unwrap();
+        }
+
+        let merge = merge(&ours, &theirs).unwrap();
+
+        assert_eq!(merge.added, 2);
+        let state = replay(&merge.stream);
+        assert_eq!(
+            state.iter().collect::<Vec<_>>(),
+            [&login(&ours_id, 10), &login(&theirs_id, 15)]
+        );
+    }
+}
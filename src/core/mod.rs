@@ -1,14 +1,35 @@
+mod day;
 mod error;
+mod expr;
+#[cfg(test)]
+mod fixtures;
 mod format;
 mod id;
+mod merge;
+mod session;
 mod state;
+mod submission;
+mod week;
+mod zone;
 
+pub use day::*;
 pub use error::*;
+pub use expr::*;
 pub use format::*;
 pub use id::*;
+pub use merge::*;
+pub use session::*;
 pub use state::*;
+pub use submission::*;
+pub use week::*;
+pub use zone::*;
 
+pub mod editor;
+pub mod export;
+pub mod import;
 pub mod io;
 pub mod layout;
 pub mod log;
+pub mod output;
+pub mod report;
 pub mod util;
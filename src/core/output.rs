@@ -0,0 +1,149 @@
+/// Output module
+///
+/// Machine-readable output for scripts built on top of skuff. Lists are printed either as a
+/// single JSON array or, for streaming, as newline-delimited JSON with one document per line.
+/// Entities are serialized exactly as in the stream files, see `format::stream`.
+///
+/// Text output is coloured and paged only when stdout is a terminal, unless the user says
+/// otherwise.
+use std::io::IsTerminal as _;
+
+use pager::Pager;
+use serde::Deserialize;
+use serde::Serialize;
+
+use super::Config;
+use super::Error;
+use super::Id;
+
+const DEFAULT_PAGER: &str = "less -FRX";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    #[default]
+    Text,
+    Json,
+    Ndjson,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum Color {
+    Auto,
+    Always,
+    Never,
+}
+
+/// How text output is presented on a terminal
+#[derive(Debug, Clone, Copy, clap::Args)]
+pub struct Terminal {
+    /// When to colour the output [default: auto]
+    #[arg(long, global = true, value_enum)]
+    pub color: Option<Color>,
+
+    /// Print directly to stdout instead of through a pager
+    #[arg(long, global = true)]
+    pub no_pager: bool,
+}
+
+impl Terminal {
+    /// Set up colours and the pager before printing text output
+    ///
+    /// --color wins over the config, which wins over NO_COLOR. The pager is SKUFF_PAGER, then the
+    /// `pager` config, then PAGER. It is skipped with --no-pager and when stdout is not a terminal.
+    pub fn setup(&self, config: &Config) {
+        self.colors(config);
+
+        if self.no_pager || !std::io::stdout().is_terminal() {
+            return;
+        }
+
+        if let Some(pager) = pager(config) {
+            Pager::with_pager(&pager).setup();
+        }
+    }
+
+    /// Set up colours only, for output that is never paged
+    pub fn colors(&self, config: &Config) {
+        let color =
+            self.color
+                .or(config.color)
+                .unwrap_or_else(|| match std::env::var("NO_COLOR") {
+                    Ok(value) if !value.is_empty() => Color::Never,
+                    _ => Color::Auto,
+                });
+
+        // Decided up front, as once the pager is running stdout is a pipe to it
+        colored::control::set_override(match color {
+            Color::Auto => std::io::stdout().is_terminal(),
+            Color::Always => true,
+            Color::Never => false,
+        });
+    }
+}
+
+fn pager(config: &Config) -> Option<String> {
+    let pager = std::env::var("SKUFF_PAGER")
+        .ok()
+        .or_else(|| config.pager.clone())
+        .or_else(|| std::env::var("PAGER").ok())
+        .unwrap_or_else(|| DEFAULT_PAGER.to_string());
+
+    match pager.trim() {
+        "" | "cat" => None,
+        _ => Some(pager),
+    }
+}
+
+/// What commands that append events print in json and ndjson mode
+#[derive(Serialize)]
+pub struct Written {
+    pub event_id: Id,
+    pub entity_id: Id,
+}
+
+/// What `ls`, `new` and `switch` print per stream in json and ndjson mode
+#[derive(Serialize)]
+pub struct Listed<'a> {
+    pub name: &'a str,
+    pub current: bool,
+}
+
+/// Print a single document
+pub fn print<T: Serialize>(value: &T) -> Result<(), Error> {
+    println!("{}", to_string(value)?);
+    Ok(())
+}
+
+/// Print a list as one JSON array, or as one document per line for ndjson
+pub fn print_all<'a, T, I>(format: Format, items: I) -> Result<(), Error>
+where
+    T: Serialize + 'a,
+    I: IntoIterator<Item = &'a T>,
+{
+    match format {
+        Format::Ndjson => {
+            for item in items {
+                print(item)?;
+            }
+            Ok(())
+        }
+        _ => print(&items.into_iter().collect::<Vec<_>>()),
+    }
+}
+
+pub fn to_string<T: Serialize>(value: &T) -> Result<String, Error> {
+    serde_json::to_string(value).map_err(|err| Error::SerializeFailed(err.to_string()))
+}
+
+/// A list as ndjson, one document per line
+pub fn to_lines<'a, T, I>(items: I) -> Result<String, Error>
+where
+    T: Serialize + 'a,
+    I: IntoIterator<Item = &'a T>,
+{
+    items
+        .into_iter()
+        .map(|item| Ok(to_string(item)? + "\n"))
+        .collect()
+}
@@ -0,0 +1,722 @@
+/// Report module
+///
+/// Month summaries for sign-off. A report lists every day of a month with weekly subtotals, and
+/// compares the time worked with the norm, carrying over the flex balance from earlier months.
+/// Reports render as markdown or as a standalone HTML page that can be attached to an email, or as
+/// JSON for scripts.
+use std::collections::BTreeMap;
+
+use chrono::DateTime;
+use chrono::Datelike;
+use chrono::Duration;
+use chrono::Months;
+use chrono::NaiveDate;
+use chrono::Utc;
+use chrono::Weekday;
+use serde::Serialize;
+
+use super::Day;
+use super::Id;
+use super::Norm;
+use super::Overlap;
+use super::Session;
+use super::State;
+use super::Stream;
+use super::Submission;
+use super::Zone;
+use super::days;
+use super::overlaps;
+use super::sessions;
+use super::submissions;
+use super::util::hhmm;
+use super::util::signed_hhmm;
+
+const COLUMNS: [&str; 9] = [
+    "Date", "Day", "In", "Out", "Breaks", "Worked", "Expected", "Diff", "Note",
+];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    #[default]
+    Markdown,
+    Html,
+}
+
+pub struct Report {
+    pub name: String,
+    pub month: NaiveDate,
+    pub days: Vec<Day>,
+    /// The flex balance at the start of the month
+    pub carried: Duration,
+    /// Time worked per stream, when the report covers more than one stream
+    pub streams: Vec<(String, Duration)>,
+    /// Time worked per tag, from the tags of sessions and activities
+    pub tags: BTreeMap<String, Duration>,
+    /// Sessions in different streams that ran at the same time
+    pub overlaps: Vec<Overlap>,
+    /// The zone overlaps are shown in, the local one unless given
+    pub tz: Option<Zone>,
+    /// The last locked day of each locked stream
+    pub locked: Vec<(String, NaiveDate)>,
+    /// Submissions of periods in the month, per stream
+    pub submissions: Vec<(String, Submission)>,
+}
+
+/// A report in json output. Durations are in seconds.
+#[derive(Serialize)]
+pub struct Document<'a> {
+    pub name: &'a str,
+    /// The month as YYYY-MM
+    pub month: String,
+    #[serde(with = "super::duration_seconds")]
+    pub worked: Duration,
+    #[serde(with = "super::duration_seconds")]
+    pub expected: Duration,
+    #[serde(with = "super::duration_seconds")]
+    pub carried: Duration,
+    #[serde(with = "super::duration_seconds")]
+    pub balance: Duration,
+    pub absences: usize,
+    /// Seconds worked per stream, when the report covers more than one stream
+    pub streams: BTreeMap<&'a str, i64>,
+    /// Seconds worked per tag
+    pub tags: BTreeMap<&'a str, i64>,
+    pub days: &'a [Day],
+    pub overlaps: &'a [Overlap],
+    /// The last locked day per stream
+    pub locked: BTreeMap<&'a str, NaiveDate>,
+    pub submissions: Vec<Submitted<'a>>,
+}
+
+/// A submission in json output
+#[derive(Serialize)]
+pub struct Submitted<'a> {
+    pub stream: &'a str,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub submitted_at: DateTime<Utc>,
+    /// Seconds worked when the period was submitted
+    pub submitted: i64,
+    /// Seconds worked now
+    pub worked: i64,
+    /// The events that changed the period since, if any
+    pub changes: Vec<&'a Id>,
+}
+
+struct Row {
+    cells: Vec<String>,
+    subtotal: bool,
+}
+
+impl Report {
+    /// Summarize the month starting at `month` across all of `streams`
+    ///
+    /// Work is only expected up until today, so a report for the running month shows the balance
+    /// so far. The carried balance covers every day from the first recorded session up to the
+    /// start of the month. Locks and submissions are read from the `recorded` streams.
+    pub fn new(
+        streams: &[(String, State)],
+        recorded: &[(String, Stream)],
+        month: NaiveDate,
+        norm: &Norm,
+        tz: Option<&Zone>,
+        now: DateTime<Utc>,
+    ) -> Self {
+        let today = tz
+            .copied()
+            .unwrap_or_else(Zone::local)
+            .localize(&now)
+            .date_naive();
+        let first = month.with_day(1).unwrap();
+        let last = first + Months::new(1) - Duration::days(1);
+
+        let expected_until = |mut days: Vec<Day>| {
+            for day in days.iter_mut().filter(|day| day.date > today) {
+                day.norm = Duration::zero();
+            }
+            days
+        };
+
+        let all: Vec<Session> = streams
+            .iter()
+            .flat_map(|(_, state)| sessions(state))
+            .collect();
+
+        let carried = all
+            .iter()
+            .map(|session| session.date(tz))
+            .min()
+            .filter(|start| *start < first)
+            .map(|start| {
+                expected_until(days(&all, start, first.pred_opt().unwrap(), norm, tz, now))
+                    .iter()
+                    .map(Day::diff)
+                    .sum()
+            })
+            .unwrap_or_else(Duration::zero);
+
+        let mut per_stream = vec![];
+        if streams.len() > 1 {
+            for (name, state) in streams {
+                let worked = days(&sessions(state), first, last, norm, tz, now)
+                    .iter()
+                    .map(|day| day.worked)
+                    .sum();
+                per_stream.push((name.clone(), worked));
+            }
+        }
+
+        let mut tags: BTreeMap<String, Duration> = BTreeMap::new();
+        let month_sessions = all.iter().filter(|session| {
+            let date = session.date(tz);
+            first <= date && date <= last
+        });
+        for session in month_sessions {
+            for segment in session.segments() {
+                for tag in segment.tags {
+                    *tags.entry(tag.to_string()).or_insert_with(Duration::zero) +=
+                        segment.end - segment.start;
+                }
+            }
+        }
+
+        let zone = tz.copied().unwrap_or_else(Zone::local);
+        let overlaps = overlaps(streams, now)
+            .into_iter()
+            .filter(|overlap| {
+                let date = zone.localize(&overlap.start).date_naive();
+                first <= date && date <= last
+            })
+            .collect();
+
+        // Locks and periods end at midnight, so their last day is the one before
+        let last_day =
+            |end: DateTime<Utc>| zone.localize(&(end - Duration::seconds(1))).date_naive();
+
+        let locked = recorded
+            .iter()
+            .filter_map(|(name, stream)| Some((name.clone(), last_day(stream.locked_until()?))))
+            .collect();
+
+        let submissions = recorded
+            .iter()
+            .flat_map(|(name, stream)| {
+                submissions(stream, now)
+                    .into_iter()
+                    .map(move |submission| (name.clone(), submission))
+            })
+            .filter(|(_, submission)| {
+                zone.localize(&submission.from).date_naive() <= last
+                    && first <= last_day(submission.to)
+            })
+            .collect();
+
+        let names: Vec<&str> = streams.iter().map(|(name, _)| name.as_str()).collect();
+
+        Self {
+            name: names.join(", "),
+            month: first,
+            days: expected_until(days(&all, first, last, norm, tz, now)),
+            carried,
+            streams: per_stream,
+            tags,
+            overlaps,
+            tz: tz.copied(),
+            locked,
+            submissions,
+        }
+    }
+
+    pub fn worked(&self) -> Duration {
+        self.days.iter().map(|day| day.worked).sum()
+    }
+
+    pub fn expected(&self) -> Duration {
+        self.days.iter().map(|day| day.norm).sum()
+    }
+
+    /// The flex balance at the end of the month
+    pub fn balance(&self) -> Duration {
+        self.carried + self.worked() - self.expected()
+    }
+
+    /// Days where work was expected but none was recorded
+    pub fn absences(&self) -> usize {
+        self.days.iter().filter(|day| day.is_absent()).count()
+    }
+
+    /// The report as one JSON document
+    pub fn document(&self) -> Document<'_> {
+        Document {
+            name: &self.name,
+            month: self.month.format("%Y-%m").to_string(),
+            worked: self.worked(),
+            expected: self.expected(),
+            carried: self.carried,
+            balance: self.balance(),
+            absences: self.absences(),
+            streams: self
+                .streams
+                .iter()
+                .map(|(name, worked)| (name.as_str(), worked.num_seconds()))
+                .collect(),
+            tags: self
+                .tags
+                .iter()
+                .map(|(tag, worked)| (tag.as_str(), worked.num_seconds()))
+                .collect(),
+            days: &self.days,
+            overlaps: &self.overlaps,
+            locked: self
+                .locked
+                .iter()
+                .map(|(name, last)| (name.as_str(), *last))
+                .collect(),
+            submissions: self
+                .submissions
+                .iter()
+                .map(|(name, submission)| Submitted {
+                    stream: name,
+                    from: submission.from,
+                    to: submission.to,
+                    submitted_at: submission.submitted_at,
+                    submitted: submission.submitted.num_seconds(),
+                    worked: submission.worked.num_seconds(),
+                    changes: submission
+                        .changes
+                        .iter()
+                        .map(|change| change.event.id())
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn render(&self, format: Format) -> String {
+        match format {
+            Format::Markdown => self.markdown(),
+            Format::Html => self.html(),
+        }
+    }
+
+    /// Submitted periods that changed since they were submitted
+    fn changed(&self) -> impl Iterator<Item = &(String, Submission)> {
+        self.submissions
+            .iter()
+            .filter(|(_, submission)| submission.is_changed())
+    }
+
+    fn title(&self) -> String {
+        format!("{}: {}", self.name, self.month.format("%B %Y"))
+    }
+
+    fn summary(&self) -> Vec<(String, String)> {
+        let mut summary: Vec<(String, String)> = self
+            .streams
+            .iter()
+            .map(|(name, worked)| (format!("Worked in {}", name), hhmm(*worked)))
+            .collect();
+
+        summary.extend(
+            self.tags
+                .iter()
+                .map(|(tag, worked)| (format!("Tagged +{}", tag), hhmm(*worked))),
+        );
+
+        summary.extend(
+            [
+                ("Worked", hhmm(self.worked())),
+                ("Expected", hhmm(self.expected())),
+                ("Difference", signed_hhmm(self.worked() - self.expected())),
+                ("Carried over", signed_hhmm(self.carried)),
+                ("Flex balance", signed_hhmm(self.balance())),
+                ("Absent days", self.absences().to_string()),
+            ]
+            .map(|(label, value)| (label.to_string(), value)),
+        );
+
+        let in_stream = |label: String, name: &str| {
+            if self.streams.is_empty() {
+                label
+            } else {
+                format!("{} in {}", label, name)
+            }
+        };
+
+        summary.extend(self.locked.iter().map(|(name, last)| {
+            (
+                in_stream("Locked through".to_string(), name),
+                last.to_string(),
+            )
+        }));
+
+        summary.extend(self.submissions.iter().map(|(name, submission)| {
+            let label = format!("Submitted {}", submission.period(self.tz.as_ref()));
+            (in_stream(label, name), hhmm(submission.submitted))
+        }));
+
+        summary
+    }
+
+    /// A row per day, and a subtotal after every Sunday and after the last day of the month
+    fn rows(&self) -> Vec<Row> {
+        let mut rows = vec![];
+        let mut week: Vec<&Day> = vec![];
+
+        for (i, day) in self.days.iter().enumerate() {
+            let blank = |value: String| {
+                if day.is_empty() { String::new() } else { value }
+            };
+
+            rows.push(Row {
+                cells: vec![
+                    day.date.to_string(),
+                    day.date.format("%a").to_string(),
+                    day.in_time(),
+                    day.out_time(),
+                    blank(hhmm(day.breaks)),
+                    blank(hhmm(day.worked)),
+                    blank(hhmm(day.norm)),
+                    blank(signed_hhmm(day.diff())),
+                    if day.is_absent() {
+                        "Absent".to_string()
+                    } else {
+                        day.notes.join("; ")
+                    },
+                ],
+                subtotal: false,
+            });
+            week.push(day);
+
+            if day.date.weekday() == Weekday::Sun || i + 1 == self.days.len() {
+                let sum = |f: fn(&Day) -> Duration| week.iter().map(|day| f(day)).sum::<Duration>();
+                rows.push(Row {
+                    cells: vec![
+                        format!("Week {}", day.date.iso_week().week()),
+                        String::new(),
+                        String::new(),
+                        String::new(),
+                        hhmm(sum(|day| day.breaks)),
+                        hhmm(sum(|day| day.worked)),
+                        hhmm(sum(|day| day.norm)),
+                        signed_hhmm(sum(Day::diff)),
+                        String::new(),
+                    ],
+                    subtotal: true,
+                });
+                week.clear();
+            }
+        }
+
+        rows
+    }
+
+    fn markdown(&self) -> String {
+        let line = |cells: &[String]| {
+            let cells: Vec<String> = cells.iter().map(|cell| escape_cell(cell)).collect();
+            format!("| {} |\n", cells.join(" | "))
+        };
+
+        let mut doc = format!("# {}\n\n", self.title());
+
+        doc += &line(&COLUMNS.map(String::from));
+        doc += "| --- | --- | --- | --- | ---: | ---: | ---: | ---: | --- |\n";
+        for row in self.rows() {
+            let cells: Vec<String> = row
+                .cells
+                .into_iter()
+                .map(|cell| {
+                    if row.subtotal && !cell.is_empty() {
+                        format!("**{}**", cell)
+                    } else {
+                        cell
+                    }
+                })
+                .collect();
+            doc += &line(&cells);
+        }
+
+        doc += "\n| | |\n| --- | ---: |\n";
+        for (label, value) in self.summary() {
+            doc += &line(&[label, value]);
+        }
+
+        if !self.overlaps.is_empty() {
+            doc += "\n## Overlapping sessions\n\n";
+            for overlap in &self.overlaps {
+                doc += &format!("- {}\n", overlap.describe(self.tz.as_ref()));
+            }
+        }
+
+        if self.changed().next().is_some() {
+            doc += "\n## Changed since submitted\n\n";
+            for (name, submission) in self.changed() {
+                doc += &format!("- {}: {}\n", name, submission.describe(self.tz.as_ref()));
+                for change in &submission.changes {
+                    doc += &format!("  - {}\n", change.describe(self.tz.as_ref()));
+                }
+            }
+        }
+
+        doc
+    }
+
+    fn html(&self) -> String {
+        let mut doc = String::new();
+
+        doc += "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n";
+        doc += &format!("<title>{}</title>\n", escape(&self.title()));
+        doc += "<style>\n";
+        doc += "table { border-collapse: collapse; margin-bottom: 1em; }\n";
+        doc += "th, td { border: 1px solid #ccc; padding: 2px 8px; text-align: right; }\n";
+        doc += "tr.week { font-weight: bold; background: #f0f0f0; }\n";
+        doc += "</style>\n</head>\n<body>\n";
+        doc += &format!("<h1>{}</h1>\n", escape(&self.title()));
+
+        doc += "<table>\n<tr>";
+        for column in COLUMNS {
+            doc += &format!("<th>{}</th>", column);
+        }
+        doc += "</tr>\n";
+        for row in self.rows() {
+            doc += if row.subtotal {
+                "<tr class=\"week\">"
+            } else {
+                "<tr>"
+            };
+            for cell in &row.cells {
+                doc += &format!("<td>{}</td>", escape(cell));
+            }
+            doc += "</tr>\n";
+        }
+        doc += "</table>\n";
+
+        doc += "<table>\n";
+        for (label, value) in self.summary() {
+            doc += &format!(
+                "<tr><th>{}</th><td>{}</td></tr>\n",
+                escape(&label),
+                escape(&value)
+            );
+        }
+        doc += "</table>\n";
+
+        if !self.overlaps.is_empty() {
+            doc += "<h2>Overlapping sessions</h2>\n<ul>\n";
+            for overlap in &self.overlaps {
+                doc += &format!("<li>{}</li>\n", escape(&overlap.describe(self.tz.as_ref())));
+            }
+            doc += "</ul>\n";
+        }
+
+        if self.changed().next().is_some() {
+            doc += "<h2>Changed since submitted</h2>\n<ul>\n";
+            for (name, submission) in self.changed() {
+                let text = format!("{}: {}", name, submission.describe(self.tz.as_ref()));
+                doc += &format!("<li>{}\n<ul>\n", escape(&text));
+                for change in &submission.changes {
+                    doc += &format!("<li>{}</li>\n", escape(&change.describe(self.tz.as_ref())));
+                }
+                doc += "</ul>\n</li>\n";
+            }
+            doc += "</ul>\n";
+        }
+
+        doc += "</body>\n</html>\n";
+
+        doc
+    }
+}
+
+/// Keep text from breaking out of a markdown table cell
+fn escape_cell(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('|', "\\|")
+        .replace('\n', " ")
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use chrono::TimeZone as _;
+
+    use super::*;
+    use crate::Entity;
+    use crate::Event;
+    use crate::Id;
+    use crate::Stream;
+    use crate::core::fixtures::*;
+    use crate::replay;
+
+    fn day(m: u32, d: u32, from: u32, to: u32) -> Vec<Entity> {
+        let utc: Zone = "UTC".parse().unwrap();
+        vec![
+            Entity::Login {
+                id: Id::new(),
+                timestamp: Utc.with_ymd_and_hms(2025, m, d, from, 0, 0).unwrap(),
+                tz: Some(utc),
+                note: None,
+                tags: BTreeSet::new(),
+            },
+            Entity::Logout {
+                id: Id::new(),
+                timestamp: Utc.with_ymd_and_hms(2025, m, d, to, 0, 0).unwrap(),
+                tz: Some(utc),
+                note: None,
+            },
+        ]
+    }
+
+    fn report(entities: Vec<Entity>) -> Report {
+        let mut stream = stream(entities);
+        stream
+            .push(Event::Lock {
+                id: Id::new(),
+                created_at: Utc::now(),
+                until: Utc.with_ymd_and_hms(2025, 9, 8, 0, 0, 0).unwrap(),
+            })
+            .unwrap();
+
+        report_of(stream)
+    }
+
+    fn report_of(stream: Stream) -> Report {
+        let norm = Norm {
+            minutes: 480,
+            days: vec![
+                Weekday::Mon,
+                Weekday::Tue,
+                Weekday::Wed,
+                Weekday::Thu,
+                Weekday::Fri,
+            ],
+        };
+        let september = NaiveDate::from_ymd_opt(2025, 9, 1).unwrap();
+        let now = Utc.with_ymd_and_hms(2025, 10, 15, 12, 0, 0).unwrap();
+        let utc: Zone = "UTC".parse().unwrap();
+
+        let streams = [("work".to_string(), replay(&stream))];
+        let recorded = [("work".to_string(), stream)];
+        Report::new(&streams, &recorded, september, &norm, Some(&utc), now)
+    }
+
+    #[test]
+    fn totals_and_carried_balance() {
+        // Friday 29 August, one hour of overtime
+        let mut entities = day(8, 29, 7, 16);
+        // Monday and Tuesday, then nothing for the rest of the month
+        entities.extend(day(9, 1, 8, 16));
+        entities.extend(day(9, 2, 8, 18));
+
+        let report = report(entities);
+
+        assert_eq!(report.days.len(), 30);
+        assert_eq!(report.carried, Duration::hours(1));
+        assert_eq!(report.worked(), Duration::hours(18));
+        // 22 working days in September 2025
+        assert_eq!(report.expected(), Duration::hours(22 * 8));
+        assert_eq!(report.absences(), 20);
+        assert_eq!(report.balance(), Duration::hours(1 + 18 - 22 * 8));
+    }
+
+    #[test]
+    fn markdown_has_weekly_subtotals() {
+        let markdown = report(day(9, 1, 8, 17)).render(Format::Markdown);
+
+        assert!(markdown.starts_with("# work: September 2025\n"));
+        assert!(
+            markdown
+                .contains("| 2025-09-01 | Mon | 08:00 | 17:00 | 0:00 | 9:00 | 8:00 | +1:00 |  |")
+        );
+        assert!(markdown.contains(
+            "| **Week 36** |  |  |  | **0:00** | **9:00** | **40:00** | **-31:00** |  |"
+        ));
+        assert!(markdown.contains("| **Week 40** |"));
+        assert!(markdown.contains("| Flex balance | -167:00 |"));
+        assert!(markdown.contains("| Locked through | 2025-09-07 |"));
+    }
+
+    #[test]
+    fn html_escapes_text() {
+        let mut report = report(vec![]);
+        report.name = "R&D <core>".to_string();
+
+        let html = report.render(Format::Html);
+
+        assert!(html.contains("<h1>R&amp;D &lt;core&gt;: September 2025</h1>"));
+        assert!(html.contains("<tr class=\"week\"><td>Week 36</td>"));
+    }
+
+    #[test]
+    fn markdown_escapes_table_cells() {
+        let mut entities = day(9, 1, 8, 17);
+        if let Entity::Login { note, .. } = &mut entities[0] {
+            *note = Some("a | b".to_string());
+        }
+
+        let markdown = report(entities).render(Format::Markdown);
+
+        assert!(markdown.contains("| 8:00 | +1:00 | a \\| b |\n"));
+    }
+
+    #[test]
+    fn document_for_json() {
+        let report = report(day(9, 1, 8, 17));
+
+        let json = serde_json::to_value(report.document()).unwrap();
+
+        assert_eq!(json["month"], "2025-09");
+        assert_eq!(json["worked"], 9 * 3600);
+        assert_eq!(json["days"][0]["in"], "2025-09-01T08:00:00Z");
+        assert_eq!(json["locked"]["work"], "2025-09-07");
+    }
+
+    #[test]
+    fn changes_since_submission_are_flagged() {
+        let entities = day(9, 1, 8, 17);
+        let logout = entities[1].clone();
+        let mut stream = stream(entities);
+        stream
+            .push(Event::Submit {
+                id: Id::new(),
+                created_at: Utc.with_ymd_and_hms(2025, 9, 8, 9, 0, 0).unwrap(),
+                from: Utc.with_ymd_and_hms(2025, 9, 1, 0, 0, 0).unwrap(),
+                to: Utc.with_ymd_and_hms(2025, 9, 8, 0, 0, 0).unwrap(),
+                worked: Duration::hours(9),
+            })
+            .unwrap();
+        let edit = Id::new();
+        stream
+            .push(Event::Edit {
+                id: edit.clone(),
+                created_at: Utc::now(),
+                entity: Entity::Logout {
+                    id: logout.id().clone(),
+                    timestamp: Utc.with_ymd_and_hms(2025, 9, 1, 16, 0, 0).unwrap(),
+                    tz: logout.tz(),
+                    note: None,
+                },
+            })
+            .unwrap();
+
+        let markdown = report_of(stream).render(Format::Markdown);
+
+        assert!(markdown.contains("| Submitted 2025-09-01 to 2025-09-07 | 9:00 |"));
+        assert!(markdown.contains(
+            "- work: 2025-09-01 to 2025-09-07 changed since it was submitted on 2025-09-08 09:00 \
+             with 9:00, it now has 8:00\n"
+        ));
+        assert!(markdown.contains(&format!(
+            "  - edited Logout @ 2025-09-01 16:00 by event {}\n",
+            edit
+        )));
+    }
+}
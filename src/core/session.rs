@@ -0,0 +1,471 @@
+use std::collections::BTreeSet;
+
+use chrono::DateTime;
+use chrono::Duration;
+use chrono::NaiveDate;
+use chrono::Utc;
+use serde::Serialize;
+
+use super::Entity;
+use super::State;
+use super::Zone;
+
+/// A working session, from a Login to the following Logout, with the breaks and activities that
+/// were recorded while it lasted
+pub struct Session<'a> {
+    pub login: &'a Entity,
+    pub logout: Option<&'a Entity>,
+    /// The next Login, if it came before any Logout. The session is taken to end there.
+    pub interrupted_by: Option<&'a Entity>,
+    pub breaks: Vec<&'a Entity>,
+    pub activities: Vec<&'a Entity>,
+}
+
+/// Sessions in two different streams that were running at the same time
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Overlap {
+    pub streams: [String; 2],
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+/// A stretch of uninterrupted work within a session
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segment<'a> {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub activity: Option<&'a str>,
+    /// The tags of the session and of the activity
+    pub tags: BTreeSet<&'a str>,
+}
+
+impl Overlap {
+    /// e.g. "2025-09-01 09:30-10:00: work and acme"
+    pub fn describe(&self, tz: Option<&Zone>) -> String {
+        let zone = tz.copied().unwrap_or_else(Zone::local);
+        format!(
+            "{}-{}: {} and {}",
+            zone.localize(&self.start).format("%Y-%m-%d %H:%M"),
+            zone.localize(&self.end).format("%H:%M"),
+            self.streams[0],
+            self.streams[1]
+        )
+    }
+}
+
+impl<'a> Session<'a> {
+    pub fn start(&self) -> DateTime<Utc> {
+        *self.login.timestamp()
+    }
+
+    /// When the session ended, or None if it is still running
+    pub fn end(&self) -> Option<DateTime<Utc>> {
+        self.logout
+            .or(self.interrupted_by)
+            .map(|entity| *entity.timestamp())
+    }
+
+    /// The zone the session is shown in: `tz` if given, otherwise the zone it was recorded in
+    pub fn zone(&self, tz: Option<&Zone>) -> Zone {
+        tz.copied().or(self.login.tz()).unwrap_or_else(Zone::local)
+    }
+
+    /// The day the session started, in the zone it was recorded in unless `tz` overrides it
+    pub fn date(&self, tz: Option<&Zone>) -> NaiveDate {
+        self.zone(tz).localize(&self.start()).date_naive()
+    }
+
+    /// The login, breaks, activities and logout of the session
+    pub fn entities(&self) -> impl Iterator<Item = &'a Entity> + '_ {
+        std::iter::once(self.login)
+            .chain(self.breaks.iter().copied())
+            .chain(self.activities.iter().copied())
+            .chain(self.logout)
+    }
+
+    /// The notes recorded on the login, logout, breaks and activities of the session
+    pub fn notes(&self) -> Vec<&'a str> {
+        self.entities().filter_map(Entity::note).collect()
+    }
+
+    pub fn break_time(&self) -> Duration {
+        self.breaks
+            .iter()
+            .filter_map(|entity| match entity {
+                Entity::Break { duration, .. } => Some(*duration),
+                _ => None,
+            })
+            .sum()
+    }
+
+    /// Time between login and logout, minus breaks. Running sessions count until `now`.
+    pub fn worked(&self, now: DateTime<Utc>) -> Duration {
+        let end = self.end().unwrap_or(now);
+        self.segments_until(end)
+            .iter()
+            .map(|segment| segment.end - segment.start)
+            .sum()
+    }
+
+    /// Split a finished session into the stretches worked between breaks, each attributed to the
+    /// activity recorded for it. Where activities overlap the earliest one wins. Running sessions
+    /// have no segments.
+    pub fn segments(&self) -> Vec<Segment<'a>> {
+        match self.end() {
+            Some(end) => self.segments_until(end),
+            None => vec![],
+        }
+    }
+
+    fn segments_until(&self, end: DateTime<Utc>) -> Vec<Segment<'a>> {
+        let start = self.start();
+        let clip = |t: DateTime<Utc>| t.clamp(start, end.max(start));
+
+        let breaks: Vec<(DateTime<Utc>, DateTime<Utc>)> =
+            self.breaks.iter().filter_map(|e| span(e)).collect();
+        let activities: Vec<(DateTime<Utc>, DateTime<Utc>, &'a Entity)> = self
+            .activities
+            .iter()
+            .filter_map(|entity| span(entity).map(|(from, to)| (from, to, *entity)))
+            .collect();
+
+        let mut boundaries = vec![start, end.max(start)];
+        for (from, to) in &breaks {
+            boundaries.extend([clip(*from), clip(*to)]);
+        }
+        for (from, to, _) in &activities {
+            boundaries.extend([clip(*from), clip(*to)]);
+        }
+        boundaries.sort();
+        boundaries.dedup();
+
+        let mut segments: Vec<Segment<'a>> = vec![];
+        for window in boundaries.windows(2) {
+            let (from, to) = (window[0], window[1]);
+
+            if breaks.iter().any(|(b0, b1)| *b0 <= from && to <= *b1) {
+                continue;
+            }
+
+            let entity = activities
+                .iter()
+                .find(|(a0, a1, _)| *a0 <= from && to <= *a1)
+                .map(|(_, _, entity)| *entity);
+            let activity = match entity {
+                Some(Entity::Activity { value, .. }) => Some(value.as_str()),
+                _ => None,
+            };
+            let tags: BTreeSet<&'a str> = std::iter::once(self.login)
+                .chain(entity)
+                .flat_map(|entity| entity.tags().iter().map(String::as_str))
+                .collect();
+
+            match segments.last_mut() {
+                Some(last)
+                    if last.end == from && last.activity == activity && last.tags == tags =>
+                {
+                    last.end = to
+                }
+                _ => segments.push(Segment {
+                    start: from,
+                    end: to,
+                    activity,
+                    tags,
+                }),
+            }
+        }
+
+        segments
+    }
+}
+
+/// Total time worked in `sessions`, excluding breaks. Running sessions count until `now`.
+pub fn worked(sessions: &[Session], now: DateTime<Utc>) -> Duration {
+    sessions.iter().map(|session| session.worked(now)).sum()
+}
+
+/// Keep the sessions that started between `from` and `to`, both inclusive
+pub fn within<'a>(
+    sessions: Vec<Session<'a>>,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    tz: Option<&Zone>,
+) -> Vec<Session<'a>> {
+    sessions
+        .into_iter()
+        .filter(|session| {
+            let date = session.date(tz);
+            from.is_none_or(|from| from <= date) && to.is_none_or(|to| date <= to)
+        })
+        .collect()
+}
+
+/// Find the sessions in different streams that overlap, in order of when the overlap starts.
+/// Running sessions count until `now`.
+pub fn overlaps(streams: &[(String, State)], now: DateTime<Utc>) -> Vec<Overlap> {
+    let mut spans: Vec<(&str, DateTime<Utc>, DateTime<Utc>)> = streams
+        .iter()
+        .flat_map(|(name, state)| {
+            sessions(state)
+                .iter()
+                .map(|session| (name.as_str(), session.start(), session.end().unwrap_or(now)))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    spans.sort_by_key(|(_, start, _)| *start);
+
+    let mut overlaps = vec![];
+    for (i, (first, _, end)) in spans.iter().enumerate() {
+        for (second, other_start, other_end) in &spans[i + 1..] {
+            if other_start >= end {
+                break;
+            }
+            if first != second {
+                overlaps.push(Overlap {
+                    streams: [first.to_string(), second.to_string()],
+                    start: *other_start,
+                    end: (*end).min(*other_end),
+                });
+            }
+        }
+    }
+
+    overlaps.sort_by_key(|overlap| overlap.start);
+    overlaps
+}
+
+/// Pair up logins and logouts. A login that is followed by another login ends where the next one
+/// starts, so only the last session can still be running, and logouts without a preceding login are ignored. Breaks and activities
+/// belong to the session they start in.
+pub fn sessions(state: &State) -> Vec<Session<'_>> {
+    let mut sessions: Vec<Session> = vec![];
+    let mut open = false;
+
+    for entity in state.iter() {
+        match entity {
+            Entity::Login { .. } => {
+                if let (true, Some(session)) = (open, sessions.last_mut()) {
+                    session.interrupted_by = Some(entity);
+                }
+                sessions.push(Session {
+                    login: entity,
+                    logout: None,
+                    interrupted_by: None,
+                    breaks: vec![],
+                    activities: vec![],
+                });
+                open = true;
+            }
+            Entity::Logout { .. } => {
+                if let (true, Some(session)) = (open, sessions.last_mut()) {
+                    session.logout = Some(entity);
+                }
+                open = false;
+            }
+            Entity::Break { .. } => {
+                if let (true, Some(session)) = (open, sessions.last_mut()) {
+                    session.breaks.push(entity);
+                }
+            }
+            Entity::Activity { .. } => {
+                if let (true, Some(session)) = (open, sessions.last_mut()) {
+                    session.activities.push(entity);
+                }
+            }
+        }
+    }
+
+    sessions
+}
+
+fn span(entity: &Entity) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    match entity {
+        Entity::Break {
+            timestamp,
+            duration,
+            ..
+        }
+        | Entity::Activity {
+            timestamp,
+            duration,
+            ..
+        } => Some((*timestamp, *timestamp + *duration)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::*;
+    use crate::Id;
+    use crate::core::fixtures::*;
+    use crate::replay;
+
+    fn login(h: u32, m: u32) -> Entity {
+        Entity::Login {
+            id: Id::new(),
+            timestamp: at(1, h, m),
+            tz: None,
+            note: None,
+            tags: BTreeSet::new(),
+        }
+    }
+
+    fn logout(h: u32, m: u32) -> Entity {
+        Entity::Logout {
+            id: Id::new(),
+            timestamp: at(1, h, m),
+            tz: None,
+            note: None,
+        }
+    }
+
+    fn pause(h: u32, m: u32, minutes: i64) -> Entity {
+        Entity::Break {
+            id: Id::new(),
+            timestamp: at(1, h, m),
+            tz: None,
+            note: None,
+            tags: BTreeSet::new(),
+            duration: Duration::minutes(minutes),
+            autoinsert: false,
+        }
+    }
+
+    fn activity(h: u32, m: u32, minutes: i64, value: &str) -> Entity {
+        Entity::Activity {
+            id: Id::new(),
+            timestamp: at(1, h, m),
+            tz: None,
+            note: None,
+            tags: BTreeSet::new(),
+            duration: Duration::minutes(minutes),
+            value: value.to_string(),
+            autoinsert: false,
+        }
+    }
+
+    #[test]
+    fn pairs_logins_and_logouts() {
+        let stream = stream(vec![
+            login(8, 0),
+            logout(11, 0),
+            logout(11, 30),
+            login(12, 0),
+            login(13, 0),
+            logout(16, 0),
+        ]);
+        let state = replay(&stream);
+
+        let sessions = sessions(&state);
+
+        assert_eq!(sessions.len(), 3);
+        assert_eq!(sessions[0].end(), Some(at(1, 11, 0)));
+        assert_eq!(sessions[1].end(), Some(at(1, 13, 0)));
+        assert_eq!(sessions[2].start(), at(1, 13, 0));
+        assert_eq!(sessions[2].end(), Some(at(1, 16, 0)));
+    }
+
+    #[test]
+    fn only_the_last_of_two_logins_runs_until_now() {
+        let stream = stream(vec![login(8, 0), login(13, 0)]);
+        let state = replay(&stream);
+
+        let sessions = sessions(&state);
+        let now = at(1, 15, 0);
+
+        assert_eq!(sessions[0].end(), Some(at(1, 13, 0)));
+        assert_eq!(sessions[1].end(), None);
+        assert_eq!(sessions[0].worked(now), Duration::hours(5));
+        assert_eq!(worked(&sessions, now), Duration::hours(7));
+    }
+
+    #[test]
+    fn worked_excludes_breaks() {
+        let stream = stream(vec![login(8, 0), pause(11, 30, 30), logout(16, 0)]);
+        let state = replay(&stream);
+
+        let sessions = sessions(&state);
+
+        assert_eq!(sessions[0].break_time(), Duration::minutes(30));
+        assert_eq!(sessions[0].worked(Utc::now()), Duration::minutes(450));
+    }
+
+    #[test]
+    fn segments_split_on_breaks_and_activities() {
+        let stream = stream(vec![
+            login(8, 0),
+            activity(9, 0, 120, "Review"),
+            pause(10, 0, 30),
+            logout(12, 0),
+        ]);
+        let state = replay(&stream);
+
+        let sessions = sessions(&state);
+
+        assert_eq!(
+            sessions[0].segments(),
+            vec![
+                Segment {
+                    start: at(1, 8, 0),
+                    end: at(1, 9, 0),
+                    activity: None,
+                    tags: BTreeSet::new(),
+                },
+                Segment {
+                    start: at(1, 9, 0),
+                    end: at(1, 10, 0),
+                    activity: Some("Review"),
+                    tags: BTreeSet::new(),
+                },
+                Segment {
+                    start: at(1, 10, 30),
+                    end: at(1, 11, 0),
+                    activity: Some("Review"),
+                    tags: BTreeSet::new(),
+                },
+                Segment {
+                    start: at(1, 11, 0),
+                    end: at(1, 12, 0),
+                    activity: None,
+                    tags: BTreeSet::new(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn overlaps_across_streams() {
+        let work = stream(vec![
+            login(8, 0),
+            logout(12, 0),
+            login(13, 0),
+            logout(16, 0),
+        ]);
+        let acme = stream(vec![login(11, 0), logout(13, 30)]);
+        let streams = [
+            ("work".to_string(), replay(&work)),
+            ("acme".to_string(), replay(&acme)),
+        ];
+
+        let overlaps = overlaps(&streams, Utc::now());
+
+        assert_eq!(
+            overlaps,
+            vec![
+                Overlap {
+                    streams: ["work".to_string(), "acme".to_string()],
+                    start: at(1, 11, 0),
+                    end: at(1, 12, 0),
+                },
+                Overlap {
+                    streams: ["acme".to_string(), "work".to_string()],
+                    start: at(1, 13, 0),
+                    end: at(1, 13, 30),
+                },
+            ]
+        );
+    }
+}
@@ -1,56 +1,394 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
 use std::collections::HashMap;
+use std::collections::HashSet;
+
+use chrono::DateTime;
+use chrono::Duration;
+use chrono::Utc;
 
 use super::Entity;
+use super::Error;
 use super::Event;
+use super::Id;
+use super::Rounding;
+use super::RoundingScope;
+use super::Snapshot;
 use super::Stream;
+use super::Zone;
+use super::sessions;
+use super::worked;
 
+#[derive(Clone)]
 pub struct State(Vec<Entity>);
 
 impl State {
     pub fn iter(&self) -> std::slice::Iter<'_, Entity> {
         self.0.iter()
     }
+
+    pub fn get(&self, id: &Id) -> Option<&Entity> {
+        self.0.iter().find(|entity| entity.id() == id)
+    }
+
+    /// Total time worked, excluding breaks. Running sessions count until `now`.
+    pub fn worked(&self, now: DateTime<Utc>) -> Duration {
+        worked(&sessions(self), now)
+    }
+
+    /// The state as it should be reported under `rounding`
+    ///
+    /// Rounding per interval moves every login and logout to the nearest step on the wall clock.
+    /// Rounding per day moves the last logout of each day so that the day's total is rounded. If
+    /// that would move it before its login, the session is left out and the rest is taken from
+    /// the sessions before.
+    /// Days are taken in the zone each session was recorded in, unless `tz` overrides it.
+    pub fn rounded(&self, rounding: &Rounding, tz: Option<&Zone>) -> State {
+        let mut entities = self.0.clone();
+
+        match rounding.per {
+            RoundingScope::Interval => {
+                for entity in entities.iter_mut() {
+                    if let Entity::Login { .. } | Entity::Logout { .. } = entity {
+                        let zone = tz.copied().or(entity.tz()).unwrap_or_else(Zone::local);
+                        let timestamp = rounding.apply_to_timestamp(*entity.timestamp(), &zone);
+                        entity.set_timestamp(timestamp);
+                    }
+                }
+            }
+            RoundingScope::Day => {
+                let mut days: BTreeMap<_, (Duration, Vec<_>)> = BTreeMap::new();
+
+                for session in sessions(self) {
+                    if let Some(logout) = session.logout {
+                        let day = days.entry(session.date(tz)).or_default();
+                        day.0 += session.worked(Utc::now());
+                        let ids: Vec<Id> = session.entities().map(|e| e.id().clone()).collect();
+                        day.1.push((session.start(), logout.id().clone(), ids));
+                    }
+                }
+
+                let mut emptied = HashSet::new();
+                for (worked, sessions) in days.into_values() {
+                    let mut shift = rounding.apply(worked) - worked;
+
+                    // Time is added to the last session of the day. Time taken away is taken from
+                    // the last sessions first, as no logout may move before its login. Sessions
+                    // rounded away entirely are left out.
+                    for (start, logout, ids) in sessions.into_iter().rev() {
+                        if shift.is_zero() {
+                            break;
+                        }
+
+                        if let Some(entity) = entities.iter_mut().find(|e| e.id() == &logout) {
+                            let timestamp = (*entity.timestamp() + shift).max(start);
+                            shift -= timestamp - *entity.timestamp();
+                            entity.set_timestamp(timestamp);
+
+                            if timestamp == start {
+                                emptied.extend(ids);
+                            }
+                        }
+                    }
+                }
+
+                entities.retain(|entity| !emptied.contains(entity.id()));
+            }
+        }
+
+        entities.sort_by_key(chronological);
+
+        State(entities)
+    }
+
+    /// The entities for which `predicate` holds
+    pub fn filter(&self, predicate: impl Fn(&Entity) -> bool) -> State {
+        State(
+            self.0
+                .iter()
+                .filter(|entity| predicate(entity))
+                .cloned()
+                .collect(),
+        )
+    }
+
+    /// The recorded entities tagged with all of `tags`, as shown by `log`
+    ///
+    /// Everything in a session carries the tags of its login besides its own, so a session tagged
+    /// on login is kept whole. Unlike `tagged`, no time is cut out and no entities are made up.
+    pub fn with_tags(&self, tags: &BTreeSet<String>) -> State {
+        let mut session_tags: HashMap<&Id, &BTreeSet<String>> = HashMap::new();
+        for session in sessions(self) {
+            for entity in session.entities() {
+                session_tags.insert(entity.id(), session.login.tags());
+            }
+        }
+
+        self.filter(|entity| {
+            tags.iter().all(|tag| {
+                entity.tags().contains(tag)
+                    || session_tags
+                        .get(entity.id())
+                        .is_some_and(|tags| tags.contains(tag))
+            })
+        })
+    }
+
+    /// The part of the state tagged with all of `tags`
+    ///
+    /// Sessions with the tags are kept whole. Of other sessions, only the time covered by
+    /// activities with the tags is kept, as sessions of their own.
+    pub fn tagged(&self, tags: &BTreeSet<String>) -> State {
+        if tags.is_empty() {
+            return State(self.0.clone());
+        }
+
+        let mut entities = vec![];
+
+        for session in sessions(self) {
+            let login = session.login;
+            let has_tags = |entity: &Entity| {
+                tags.iter()
+                    .all(|tag| login.tags().contains(tag) || entity.tags().contains(tag))
+            };
+
+            if tags.is_subset(login.tags()) {
+                entities.push(login.clone());
+                entities.extend(session.logout.cloned());
+                entities.extend(session.breaks.iter().copied().cloned());
+                entities.extend(session.activities.iter().copied().cloned());
+                continue;
+            }
+
+            let activities: Vec<&Entity> = session
+                .activities
+                .iter()
+                .copied()
+                .filter(|entity| has_tags(entity))
+                .collect();
+
+            let end = session.end().unwrap_or(DateTime::<Utc>::MAX_UTC);
+            let mut spans: Vec<(DateTime<Utc>, DateTime<Utc>)> = vec![];
+            for activity in &activities {
+                let Entity::Activity {
+                    timestamp,
+                    duration,
+                    ..
+                } = activity
+                else {
+                    continue;
+                };
+                let (from, to) = (
+                    *timestamp.max(&session.start()),
+                    (*timestamp + *duration).min(end),
+                );
+
+                match spans.last_mut() {
+                    Some(last) if from <= last.1 => last.1 = last.1.max(to),
+                    _ if from < to => spans.push((from, to)),
+                    _ => {}
+                }
+            }
+
+            // The ids derive from the login, so the sessions keep them from one call to the next
+            for (i, (from, to)) in spans.into_iter().enumerate() {
+                entities.push(Entity::Login {
+                    id: login.id().derive(&format!("login {}", i)),
+                    timestamp: from,
+                    tz: login.tz(),
+                    note: None,
+                    tags: login.tags().clone(),
+                });
+                entities.push(Entity::Logout {
+                    id: login.id().derive(&format!("logout {}", i)),
+                    timestamp: to,
+                    tz: login.tz(),
+                    note: None,
+                });
+            }
+            entities.extend(session.breaks.iter().copied().cloned());
+            entities.extend(activities.into_iter().cloned());
+        }
+
+        entities.sort_by_key(chronological);
+
+        State(entities)
+    }
 }
 
-#[rustfmt::skip]
 pub fn replay(events: &Stream) -> State {
-    let mut state = HashMap::new();
+    apply(HashMap::new(), events.events())
+}
+
+/// Replay the first `events` events of `stream`, i.e. the state as it was back then
+pub fn replay_until(stream: &Stream, events: usize) -> State {
+    apply(HashMap::new(), &stream.events()[..events])
+}
+
+/// Replay the events of `stream` not covered by `snapshot` on top of it
+///
+/// Returns None if the snapshot does not belong to the stream, e.g. because the stream has since
+/// been compacted. Whether its entities are the ones it was taken of is up to the caller to check,
+/// see `Snapshot::is_intact`.
+pub fn replay_since(snapshot: &Snapshot, stream: &Stream) -> Option<State> {
+    let events = stream.events();
+    let last_event_id = match snapshot.events {
+        0 => None,
+        n => Some(events.get(n - 1)?.id()),
+    };
+    if last_event_id != snapshot.last_event_id.as_ref()
+        || snapshot.head.as_deref() != stream.hash_after(snapshot.events)
+    {
+        return None;
+    }
 
+    let state = snapshot
+        .entities
+        .iter()
+        .map(|entity| (entity.id().clone(), entity.clone()))
+        .collect();
+
+    Some(apply(state, &events[snapshot.events..]))
+}
+
+/// Snapshot the replayed `state` of `stream`
+pub fn snapshot(state: &State, stream: &Stream) -> Snapshot {
+    let events = stream.events();
+
+    Snapshot::new(
+        events.len(),
+        events.last().map(|event| event.id().clone()),
+        stream.head().to_string(),
+        state.0.clone(),
+    )
+}
+
+/// Rewrite the history of `stream` into one Create event per entity in its replayed state
+///
+/// Entities that were never edited keep their original Create event. Edited entities get a new
+/// Create event at the time of their last edit.
+pub fn compact(stream: &Stream) -> Stream {
+    let mut last: HashMap<&Id, usize> = HashMap::new();
+    for (i, event) in stream.events().iter().enumerate() {
+        if let Some(entity_id) = event.entity_id() {
+            last.insert(entity_id, i);
+        }
+    }
+
+    // Locks and submissions stay where they were, so that every entity is created under the same
+    // lock it was last changed under, and changes since a submission stay after it
+    let mut compacted = Stream::new();
+    for (i, event) in stream.events().iter().enumerate() {
+        if event
+            .entity_id()
+            .is_some_and(|entity_id| last[entity_id] != i)
+        {
+            continue;
+        }
+
+        let event = match event {
+            Event::Create { .. } => event.clone(),
+            Event::Edit {
+                created_at, entity, ..
+            } => Event::Create {
+                id: Id::new(),
+                created_at: *created_at,
+                entity: entity.clone(),
+            },
+            Event::Delete { .. } => continue,
+            Event::Lock { .. } | Event::Unlock { .. } | Event::Submit { .. } => event.clone(),
+        };
+
+        // Entity ids are unique in the state, so this cannot fail
+        compacted.push(event).unwrap();
+    }
+
+    compacted
+}
+
+/// Check that replacing the history of `stream` with `replacement` keeps its locked period as it
+/// is: the same entities before the lock, and a lock at least as late
+pub fn keeps_locked_period(stream: &Stream, replacement: &Stream) -> Result<(), Error> {
+    let Some(until) = stream.locked_until() else {
+        return Ok(());
+    };
+
+    let locked = |stream: &Stream| -> HashMap<Id, Entity> {
+        replay(stream)
+            .filter(|entity| *entity.timestamp() < until)
+            .0
+            .into_iter()
+            .map(|entity| (entity.id().clone(), entity))
+            .collect()
+    };
+    let (before, after) = (locked(stream), locked(replacement));
+
+    let changed = before
+        .keys()
+        .chain(after.keys())
+        .find(|id| before.get(*id) != after.get(*id));
+    if let Some(id) = changed {
+        return Err(Error::PeriodLocked(format!(
+            "{} is before the lock at {} and would change, unlock the stream first",
+            id,
+            until.to_rfc3339()
+        )));
+    }
+
+    if replacement.locked_until() < Some(until) {
+        return Err(Error::PeriodLocked(format!(
+            "the lock at {} would be lifted, unlock the stream first",
+            until.to_rfc3339()
+        )));
+    }
+
+    Ok(())
+}
+
+fn apply(mut state: HashMap<Id, Entity>, events: &[Event]) -> State {
     for event in events {
         match event {
             Event::Create { entity, .. } | Event::Edit { entity, .. } => {
-                let id = match entity {
-                      Entity::Login    { id, .. }
-                    | Entity::Logout   { id, .. }
-                    | Entity::Break    { id, .. }
-                    | Entity::Activity { id, .. } => id,
-                };
-                state.insert(id.clone(), entity.clone());
+                state.insert(entity.id().clone(), entity.clone());
             }
             Event::Delete { entity_id, .. } => {
-                state.remove(&entity_id);
+                state.remove(entity_id);
             }
+            Event::Lock { .. } | Event::Unlock { .. } | Event::Submit { .. } => {}
         }
     }
     let mut state: Vec<Entity> = state.into_values().collect();
 
-    state.sort_by_key(|entity| match entity {
-          Entity::Login    { timestamp, .. }
-        | Entity::Logout   { timestamp, .. }
-        | Entity::Break    { timestamp, .. }
-        | Entity::Activity { timestamp, .. } => *timestamp,
-    });
+    state.sort_by_key(chronological);
 
     State(state)
 }
 
+/// Order entities by time. Entities at the same instant are ordered so that a session is closed
+/// before the next one opens, and opened before anything that happens within it.
+fn chronological(entity: &Entity) -> (DateTime<Utc>, u8) {
+    let rank = match entity {
+        Entity::Logout { .. } => 0,
+        Entity::Login { .. } => 1,
+        Entity::Break { .. } | Entity::Activity { .. } => 2,
+    };
+
+    (*entity.timestamp(), rank)
+}
+
 #[cfg(test)]
 mod tests {
+    use std::collections::BTreeSet;
+
     use chrono::TimeZone as _;
 
     use crate::Entity;
+    use crate::Error;
     use crate::Event;
     use crate::Id;
+    use crate::Rounding;
+    use crate::RoundingMode;
+    use crate::RoundingScope;
     use crate::Stream;
 
     use super::State;
@@ -61,6 +399,9 @@ mod tests {
         let expected = Entity::Login {
             id: id.clone(),
             timestamp: chrono::Utc.with_ymd_and_hms(2023, 1, 1, 10, 0, 0).unwrap(),
+            tz: None,
+            note: None,
+            tags: BTreeSet::new(),
         };
 
         let create = Event::Create {
@@ -69,6 +410,9 @@ mod tests {
             entity: Entity::Login {
                 id: id.clone(),
                 timestamp: chrono::Utc.with_ymd_and_hms(2023, 1, 1, 9, 0, 0).unwrap(),
+                tz: None,
+                note: None,
+                tags: BTreeSet::new(),
             },
         };
         let edit = Event::Edit {
@@ -94,6 +438,9 @@ mod tests {
         let expected = Entity::Login {
             id: id.clone(),
             timestamp: chrono::Utc.with_ymd_and_hms(2023, 1, 1, 11, 0, 0).unwrap(),
+            tz: None,
+            note: None,
+            tags: BTreeSet::new(),
         };
 
         let create = Event::Create {
@@ -102,6 +449,9 @@ mod tests {
             entity: Entity::Login {
                 id: id.clone(),
                 timestamp: chrono::Utc.with_ymd_and_hms(2023, 1, 1, 9, 0, 0).unwrap(),
+                tz: None,
+                note: None,
+                tags: BTreeSet::new(),
             },
         };
         let edit1 = Event::Edit {
@@ -110,6 +460,9 @@ mod tests {
             entity: Entity::Login {
                 id: id.clone(),
                 timestamp: chrono::Utc.with_ymd_and_hms(2023, 1, 1, 10, 0, 0).unwrap(),
+                tz: None,
+                note: None,
+                tags: BTreeSet::new(),
             },
         };
         let edit2 = Event::Edit {
@@ -130,6 +483,136 @@ mod tests {
         assert_eq!(state.0, expected.0);
     }
 
+    fn create(stream: &mut Stream, entity: Entity) {
+        stream
+            .push(Event::Create {
+                id: Id::new(),
+                created_at: chrono::Utc::now(),
+                entity,
+            })
+            .unwrap();
+    }
+
+    fn day(stream: &mut Stream, d: u32, login: (u32, u32), logout: (u32, u32)) {
+        let tz = Some("UTC".parse().unwrap());
+        create(
+            stream,
+            Entity::Login {
+                id: Id::new(),
+                timestamp: chrono::Utc
+                    .with_ymd_and_hms(2025, 9, d, login.0, login.1, 0)
+                    .unwrap(),
+                tz,
+                note: None,
+                tags: BTreeSet::new(),
+            },
+        );
+        create(
+            stream,
+            Entity::Logout {
+                id: Id::new(),
+                timestamp: chrono::Utc
+                    .with_ymd_and_hms(2025, 9, d, logout.0, logout.1, 0)
+                    .unwrap(),
+                tz,
+                note: None,
+            },
+        );
+    }
+
+    fn rounding(per: RoundingScope) -> Rounding {
+        Rounding {
+            granularity: 15,
+            mode: RoundingMode::Nearest,
+            per,
+        }
+    }
+
+    #[test]
+    fn round_per_interval() {
+        let mut stream = Stream::new();
+        day(&mut stream, 1, (8, 7), (16, 1));
+        day(&mut stream, 2, (8, 8), (12, 0));
+
+        let state = super::replay(&stream);
+        let rounded = state.rounded(&rounding(RoundingScope::Interval), None);
+
+        let timestamps: Vec<String> = rounded
+            .iter()
+            .map(|e| e.timestamp().format("%d %H:%M").to_string())
+            .collect();
+        assert_eq!(timestamps, ["01 08:00", "01 16:00", "02 08:15", "02 12:00"]);
+
+        // The stored state is left alone
+        assert_eq!(
+            state.worked(chrono::Utc::now()),
+            chrono::Duration::minutes(474 + 232)
+        );
+    }
+
+    #[test]
+    fn round_per_day() {
+        let mut stream = Stream::new();
+        day(&mut stream, 1, (8, 0), (11, 5));
+        day(&mut stream, 1, (12, 0), (16, 1));
+
+        let state = super::replay(&stream);
+        let rounded = state.rounded(&rounding(RoundingScope::Day), None);
+
+        let timestamps: Vec<String> = rounded
+            .iter()
+            .map(|e| e.timestamp().format("%H:%M").to_string())
+            .collect();
+        assert_eq!(timestamps, ["08:00", "11:05", "12:00", "15:55"]);
+        assert_eq!(
+            rounded.worked(chrono::Utc::now()),
+            chrono::Duration::hours(7)
+        );
+    }
+
+    #[test]
+    fn round_per_day_never_moves_a_logout_before_its_login() {
+        let mut stream = Stream::new();
+        day(&mut stream, 1, (8, 0), (16, 4));
+        day(&mut stream, 1, (17, 0), (17, 2));
+
+        let state = super::replay(&stream);
+        let rounded = state.rounded(&rounding(RoundingScope::Day), None);
+
+        let timestamps: Vec<String> = rounded
+            .iter()
+            .map(|e| e.timestamp().format("%H:%M").to_string())
+            .collect();
+        assert_eq!(timestamps, ["08:00", "16:00"]);
+        assert_eq!(
+            rounded.worked(chrono::Utc::now()),
+            chrono::Duration::hours(8)
+        );
+    }
+
+    #[test]
+    fn logout_sorts_before_login_at_the_same_time() {
+        // Rounded per interval, the first session ends at 12:00 and the second starts at 12:00
+        let mut stream = Stream::new();
+        day(&mut stream, 1, (12, 7), (16, 0));
+        day(&mut stream, 1, (8, 0), (12, 5));
+
+        let rounded = super::replay(&stream).rounded(&rounding(RoundingScope::Interval), None);
+
+        let kinds: Vec<&str> = rounded
+            .iter()
+            .map(|e| match e {
+                Entity::Login { .. } => "login",
+                _ => "logout",
+            })
+            .collect();
+        assert_eq!(kinds, ["login", "logout", "login", "logout"]);
+        assert_eq!(
+            rounded.worked(chrono::Utc::now()),
+            chrono::Duration::hours(8)
+        );
+    }
+
     #[test]
     fn delete() {
         let id = Id::new();
@@ -139,6 +622,9 @@ mod tests {
             entity: Entity::Login {
                 id: id.clone(),
                 timestamp: chrono::Utc.with_ymd_and_hms(2023, 1, 1, 9, 0, 0).unwrap(),
+                tz: None,
+                note: None,
+                tags: BTreeSet::new(),
             },
         };
         let delete = Event::Delete {
@@ -167,6 +653,9 @@ mod tests {
             entity: Entity::Login {
                 id: id.clone(),
                 timestamp: chrono::Utc.with_ymd_and_hms(2023, 1, 1, 9, 0, 0).unwrap(),
+                tz: None,
+                note: None,
+                tags: BTreeSet::new(),
             },
         };
         let edit = Event::Edit {
@@ -175,6 +664,9 @@ mod tests {
             entity: Entity::Login {
                 id: id.clone(),
                 timestamp: chrono::Utc.with_ymd_and_hms(2023, 1, 1, 10, 0, 0).unwrap(),
+                tz: None,
+                note: None,
+                tags: BTreeSet::new(),
             },
         };
         let delete = Event::Delete {
@@ -193,4 +685,195 @@ mod tests {
 
         assert_eq!(state.0, expected.0);
     }
+
+    #[test]
+    fn tagged_keeps_sessions_and_activities_with_the_tags() {
+        let at = |h: u32, m: u32| chrono::Utc.with_ymd_and_hms(2025, 9, 1, h, m, 0).unwrap();
+        let tags = |tags: &[&str]| tags.iter().map(|tag| tag.to_string()).collect();
+        let activity = |h: u32, minutes: i64, tag: &str| Entity::Activity {
+            id: Id::new(),
+            timestamp: at(h, 0),
+            tz: None,
+            note: None,
+            tags: tags(&[tag]),
+            duration: chrono::Duration::minutes(minutes),
+            value: "Work".to_string(),
+            autoinsert: false,
+        };
+
+        let mut stream = Stream::new();
+        for entity in [
+            Entity::Login {
+                id: Id::new(),
+                timestamp: at(8, 0),
+                tz: None,
+                note: None,
+                tags: tags(&["acme"]),
+            },
+            activity(9, 60, "billable"),
+            activity(13, 90, "billable"),
+            activity(14, 60, "billable"),
+            activity(15, 30, "internal"),
+            Entity::Logout {
+                id: Id::new(),
+                timestamp: at(16, 0),
+                tz: None,
+                note: None,
+            },
+        ] {
+            create(&mut stream, entity);
+        }
+        let state = super::replay(&stream);
+        let now = chrono::Utc::now();
+
+        assert_eq!(
+            state.tagged(&tags(&["acme"])).worked(now),
+            chrono::Duration::hours(8)
+        );
+        assert_eq!(
+            state.tagged(&tags(&["acme", "billable"])).worked(now),
+            chrono::Duration::hours(3)
+        );
+        assert_eq!(
+            state.tagged(&tags(&["other"])).worked(now),
+            chrono::Duration::zero()
+        );
+
+        // The sessions made up from the activities get the same ids every time
+        let ids = |state: State| state.iter().map(|e| e.id().clone()).collect::<Vec<_>>();
+        assert_eq!(
+            ids(state.tagged(&tags(&["billable"]))),
+            ids(state.tagged(&tags(&["billable"])))
+        );
+
+        // The logout belongs to the session tagged on login
+        assert_eq!(state.with_tags(&tags(&["acme"])).iter().count(), 6);
+        assert_eq!(
+            state.with_tags(&tags(&["acme", "billable"])).iter().count(),
+            3
+        );
+        assert_eq!(state.with_tags(&tags(&["billable"])).iter().count(), 3);
+    }
+
+    #[test]
+    fn replay_since_snapshot_matches_full_replay() {
+        let mut stream = Stream::new();
+        day(&mut stream, 1, (8, 0), (16, 0));
+        let snapshot = super::snapshot(&super::replay(&stream), &stream);
+
+        let login = stream.events()[0].entity_id().unwrap().clone();
+        stream
+            .push(Event::Delete {
+                id: Id::new(),
+                created_at: chrono::Utc::now(),
+                entity_id: login,
+            })
+            .unwrap();
+        day(&mut stream, 2, (9, 0), (17, 0));
+
+        let resumed = super::replay_since(&snapshot, &stream).unwrap();
+
+        assert_eq!(resumed.0, super::replay(&stream).0);
+        assert_eq!(resumed.0.len(), 3);
+    }
+
+    #[test]
+    fn replay_since_rejects_snapshot_of_other_history() {
+        let mut stream = Stream::new();
+        day(&mut stream, 1, (8, 0), (16, 0));
+        let snapshot = super::snapshot(&super::replay(&stream), &stream);
+
+        let mut other = Stream::new();
+        day(&mut other, 1, (8, 0), (16, 0));
+
+        assert!(super::replay_since(&snapshot, &other).is_none());
+        assert!(super::replay_since(&snapshot, &Stream::new()).is_none());
+    }
+
+    #[test]
+    fn compact_keeps_state_and_drops_history() {
+        let mut stream = Stream::new();
+        day(&mut stream, 1, (8, 0), (16, 0));
+        day(&mut stream, 2, (8, 0), (16, 0));
+
+        let first = stream.events()[0].clone();
+        let mut edited = match &stream.events()[1] {
+            Event::Create { entity, .. } => entity.clone(),
+            _ => unreachable!(),
+        };
+        edited.set_timestamp(chrono::Utc.with_ymd_and_hms(2025, 9, 1, 17, 0, 0).unwrap());
+        stream
+            .push(Event::Edit {
+                id: Id::new(),
+                created_at: chrono::Utc::now(),
+                entity: edited,
+            })
+            .unwrap();
+        for i in [2, 3] {
+            let entity_id = stream.events()[i].entity_id().unwrap().clone();
+            stream
+                .push(Event::Delete {
+                    id: Id::new(),
+                    created_at: chrono::Utc::now(),
+                    entity_id,
+                })
+                .unwrap();
+        }
+
+        let compacted = super::compact(&stream);
+
+        assert_eq!(compacted.events().len(), 2);
+        assert!(
+            compacted
+                .events()
+                .iter()
+                .all(|event| matches!(event, Event::Create { .. }))
+        );
+        assert_eq!(compacted.events()[0].id(), first.id());
+        assert_eq!(super::replay(&compacted).0, super::replay(&stream).0);
+    }
+
+    #[test]
+    fn replacing_a_history_keeps_its_locked_period() {
+        let mut stream = Stream::new();
+        day(&mut stream, 1, (8, 0), (16, 0));
+        day(&mut stream, 2, (8, 0), (16, 0));
+        let lock = Event::Lock {
+            id: Id::new(),
+            created_at: chrono::Utc::now(),
+            until: chrono::Utc.with_ymd_and_hms(2025, 9, 2, 0, 0, 0).unwrap(),
+        };
+        stream.push(lock.clone()).unwrap();
+
+        assert!(super::keeps_locked_period(&stream, &super::compact(&stream)).is_ok());
+
+        // Without the first day
+        let mut changed = Stream::new();
+        for event in &stream.events()[2..] {
+            changed.push(event.clone()).unwrap();
+        }
+        assert!(matches!(
+            super::keeps_locked_period(&stream, &changed),
+            Err(Error::PeriodLocked(_))
+        ));
+
+        // Without the lock
+        let mut unlocked = Stream::new();
+        for event in &stream.events()[..4] {
+            unlocked.push(event.clone()).unwrap();
+        }
+        assert!(matches!(
+            super::keeps_locked_period(&stream, &unlocked),
+            Err(Error::PeriodLocked(_))
+        ));
+
+        stream
+            .push(Event::Unlock {
+                id: Id::new(),
+                created_at: chrono::Utc::now(),
+                until: None,
+            })
+            .unwrap();
+        assert!(super::keeps_locked_period(&stream, &changed).is_ok());
+    }
 }
@@ -0,0 +1,297 @@
+use std::collections::HashMap;
+
+use chrono::DateTime;
+use chrono::Duration;
+use chrono::Utc;
+
+use super::Entity;
+use super::Event;
+use super::Id;
+use super::State;
+use super::Stream;
+use super::Zone;
+use super::log::describe;
+use super::replay;
+use super::replay_until;
+use super::sessions;
+use super::util::hhmm;
+use super::worked;
+
+/// A period whose hours were handed in, and what changed in it since
+pub struct Submission {
+    pub submitted_at: DateTime<Utc>,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    /// Time worked in the period when it was submitted
+    pub submitted: Duration,
+    /// Time worked in the period now
+    pub worked: Duration,
+    /// The events since the submission that changed entities in the period. Empty if the period
+    /// is as it was submitted.
+    pub changes: Vec<Change>,
+}
+
+/// An event that changed a submitted period, with the entity it added, edited or removed
+pub struct Change {
+    pub event: Event,
+    pub entity: Entity,
+}
+
+impl Submission {
+    pub fn is_changed(&self) -> bool {
+        !self.changes.is_empty()
+    }
+
+    /// The days of the period, e.g. "2025-09-01 to 2025-09-07"
+    pub fn period(&self, tz: Option<&Zone>) -> String {
+        let zone = tz.copied().unwrap_or_else(Zone::local);
+        let first = zone.localize(&self.from).date_naive();
+        // Periods end at midnight, so the last day is the one before
+        let last = zone
+            .localize(&(self.to - Duration::seconds(1)))
+            .date_naive();
+
+        format!("{} to {}", first, last)
+    }
+
+    pub fn describe(&self, tz: Option<&Zone>) -> String {
+        let zone = tz.copied().unwrap_or_else(Zone::local);
+
+        format!(
+            "{} changed since it was submitted on {} with {}, it now has {}",
+            self.period(tz),
+            zone.localize(&self.submitted_at).format("%Y-%m-%d %H:%M"),
+            hhmm(self.submitted),
+            hhmm(self.worked)
+        )
+    }
+}
+
+impl Change {
+    pub fn describe(&self, tz: Option<&Zone>) -> String {
+        let change = match self.event {
+            Event::Create { .. } => "added",
+            Event::Delete { .. } => "removed",
+            _ => "edited",
+        };
+
+        format!(
+            "{} {} by event {}",
+            change,
+            describe(&self.entity, tz),
+            self.event.id()
+        )
+    }
+}
+
+/// Time worked in the sessions of `state` that started in `from..to`. Running sessions count
+/// until `now`.
+pub fn worked_between(
+    state: &State,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> Duration {
+    let sessions: Vec<_> = sessions(state)
+        .into_iter()
+        .filter(|session| (from..to).contains(&session.start()))
+        .collect();
+
+    worked(&sessions, now)
+}
+
+/// The submissions of `stream`, oldest first. A period submitted again was corrected, so only its
+/// latest submission is kept. Running sessions count until `now`.
+pub fn submissions(stream: &Stream, now: DateTime<Utc>) -> Vec<Submission> {
+    let events = stream.events();
+    let state = replay(stream);
+
+    let mut submissions = vec![];
+    for (i, event) in events.iter().enumerate() {
+        let Event::Submit {
+            created_at,
+            from,
+            to,
+            worked,
+            ..
+        } = event
+        else {
+            continue;
+        };
+
+        let resubmitted = events[i + 1..].iter().any(
+            |later| matches!(later, Event::Submit { from: f, to: t, .. } if f == from && t == to),
+        );
+        if resubmitted {
+            continue;
+        }
+
+        let in_period = |entity: &Entity| (*from..*to).contains(entity.timestamp());
+        let then = replay_until(stream, i + 1).filter(in_period);
+        let current = state.filter(in_period);
+
+        let changes = if then.iter().eq(current.iter()) {
+            vec![]
+        } else {
+            changes_since(events, i, in_period)
+        };
+
+        submissions.push(Submission {
+            submitted_at: *created_at,
+            from: *from,
+            to: *to,
+            submitted: *worked,
+            worked: worked_between(&state, *from, *to, now),
+            changes,
+        });
+    }
+
+    submissions
+}
+
+/// The events after the one at `index` that added, edited or removed entities `in_period`,
+/// including edits that moved an entity into or out of it
+fn changes_since(
+    events: &[Event],
+    index: usize,
+    in_period: impl Fn(&Entity) -> bool,
+) -> Vec<Change> {
+    let mut entities: HashMap<&Id, &Entity> = HashMap::new();
+    let mut changes = vec![];
+
+    for (i, event) in events.iter().enumerate() {
+        let (before, after) = match event {
+            Event::Create { entity, .. } | Event::Edit { entity, .. } => {
+                (entities.insert(entity.id(), entity), Some(entity))
+            }
+            Event::Delete { entity_id, .. } => (entities.remove(entity_id), None),
+            Event::Lock { .. } | Event::Unlock { .. } | Event::Submit { .. } => continue,
+        };
+
+        let touched = before
+            .iter()
+            .chain(after.iter())
+            .any(|entity| in_period(entity));
+        if i > index
+            && touched
+            && let Some(entity) = after.or(before)
+        {
+            changes.push(Change {
+                event: event.clone(),
+                entity: entity.clone(),
+            });
+        }
+    }
+
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use chrono::TimeZone as _;
+
+    use super::*;
+
+    fn at(day: u32, hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 9, day, hour, 0, 0).unwrap()
+    }
+
+    fn login(id: &Id, timestamp: DateTime<Utc>) -> Entity {
+        Entity::Login {
+            id: id.clone(),
+            timestamp,
+            tz: None,
+            note: None,
+            tags: BTreeSet::new(),
+        }
+    }
+
+    fn logout(id: &Id, timestamp: DateTime<Utc>) -> Entity {
+        Entity::Logout {
+            id: id.clone(),
+            timestamp,
+            tz: None,
+            note: None,
+        }
+    }
+
+    fn create(stream: &mut Stream, entity: Entity) {
+        stream
+            .push(Event::Create {
+                id: Id::new(),
+                created_at: Utc::now(),
+                entity,
+            })
+            .unwrap();
+    }
+
+    fn submit(stream: &mut Stream) {
+        let (from, to) = (at(1, 0), at(8, 0));
+        let worked = worked_between(&replay(stream), from, to, Utc::now());
+        stream
+            .push(Event::Submit {
+                id: Id::new(),
+                created_at: Utc::now(),
+                from,
+                to,
+                worked,
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn unchanged_period() {
+        let mut stream = Stream::new();
+        create(&mut stream, login(&Id::new(), at(1, 8)));
+        create(&mut stream, logout(&Id::new(), at(1, 16)));
+        submit(&mut stream);
+        // Outside the period
+        create(&mut stream, login(&Id::new(), at(8, 8)));
+
+        let submissions = submissions(&stream, Utc::now());
+
+        assert_eq!(submissions.len(), 1);
+        assert_eq!(submissions[0].submitted, Duration::hours(8));
+        assert!(!submissions[0].is_changed());
+    }
+
+    #[test]
+    fn changes_since_submission_are_listed() {
+        let (logout_id, moved_id) = (Id::new(), Id::new());
+        let mut stream = Stream::new();
+        create(&mut stream, login(&Id::new(), at(1, 8)));
+        create(&mut stream, logout(&logout_id, at(1, 16)));
+        create(&mut stream, login(&moved_id, at(2, 8)));
+        submit(&mut stream);
+
+        stream
+            .push(Event::Edit {
+                id: Id::new(),
+                created_at: Utc::now(),
+                entity: logout(&logout_id, at(1, 15)),
+            })
+            .unwrap();
+        // Moved out of the period
+        stream
+            .push(Event::Edit {
+                id: Id::new(),
+                created_at: Utc::now(),
+                entity: login(&moved_id, at(9, 8)),
+            })
+            .unwrap();
+
+        let submission = submissions(&stream, Utc::now()).remove(0);
+
+        assert_eq!(submission.worked, Duration::hours(7));
+        assert_eq!(submission.changes.len(), 2);
+        assert_eq!(submission.changes[1].entity.id(), &moved_id);
+
+        // Submitting again accepts the changes
+        submit(&mut stream);
+        let submissions = submissions(&stream, Utc::now());
+        assert_eq!(submissions.len(), 1);
+        assert!(!submissions[0].is_changed());
+    }
+}
@@ -1,56 +1,192 @@
+use std::collections::BTreeSet;
 use std::path::PathBuf;
 use std::str::FromStr;
 
 use chrono::DateTime;
-use chrono::Datelike;
-use chrono::Local;
+use chrono::Duration;
 use chrono::NaiveDate;
-use chrono::NaiveTime;
-use chrono::ParseError;
-use chrono::TimeZone as _;
 use chrono::Utc;
 
 use super::Config;
+use super::DateExpr;
+use super::Dst;
+use super::Entity;
 use super::Error;
+use super::Norm;
+use super::State;
+use super::TimeExpr;
+use super::Zone;
 use super::io;
 use super::layout::StorageLayout;
 
-pub fn today() -> NaiveDate {
-    Local::now().date_naive()
+pub fn parse_time(s: &str) -> Result<TimeExpr, String> {
+    TimeExpr::from_str(s)
 }
 
-pub fn clock() -> NaiveTime {
-    Local::now().time()
+pub fn parse_date(s: &str) -> Result<DateExpr, String> {
+    DateExpr::from_str(s)
 }
 
-pub fn parse_time(s: &str) -> Result<NaiveTime, ParseError> {
-    if let Ok(t) = NaiveTime::from_str(s) {
-        return Ok(t);
+/// Parse a tag given as +tag
+pub fn parse_tag(s: &str) -> Result<String, String> {
+    let tag = s
+        .strip_prefix('+')
+        .ok_or_else(|| format!("invalid tag '{}': tags start with +, e.g. +{}", s, s))?;
+    validate_tag(tag)
+}
+
+/// Parse a tag to filter by, with or without the leading +
+pub fn parse_tag_filter(s: &str) -> Result<String, String> {
+    validate_tag(s.strip_prefix('+').unwrap_or(s))
+}
+
+fn validate_tag(tag: &str) -> Result<String, String> {
+    if tag.is_empty() || tag.contains(char::is_whitespace) || tag.starts_with('+') {
+        return Err(format!("invalid tag '+{}': tags are single words", tag));
     }
+    Ok(tag.to_string())
+}
 
-    NaiveTime::parse_from_str(s, "%H:%M")
+/// Split words such as ["Code", "review", "+acme"] into the text "Code review" and the tags
+pub fn split_tags(words: &[String]) -> Result<(String, BTreeSet<String>), String> {
+    let mut text = vec![];
+    let mut tags = BTreeSet::new();
+
+    for word in words {
+        if word.starts_with('+') {
+            tags.insert(parse_tag(word)?);
+        } else {
+            text.push(word.as_str());
+        }
+    }
+
+    Ok((text.join(" "), tags))
 }
 
-pub fn parse_date(s: &str) -> Result<NaiveDate, ParseError> {
-    if let Ok(t) = NaiveDate::from_str(s) {
-        return Ok(t);
+/// Parse a month such as "2025-09" to its first day
+pub fn parse_month(s: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(&format!("{}-01", s.trim()), "%Y-%m-%d")
+        .map_err(|_| format!("invalid month '{}': expected YYYY-MM", s))
+}
+
+/// Resolve a date and time expression in `zone` to a UTC timestamp. Missing parts default to the
+/// current date and time in `zone`. Relative times are offsets from now and cannot be combined with
+/// a date.
+pub fn timestamp(
+    date: Option<DateExpr>,
+    time: Option<TimeExpr>,
+    zone: &Zone,
+    dst: Option<Dst>,
+) -> Result<DateTime<Utc>, Error> {
+    let now = zone.now();
+
+    match (date, time) {
+        (Some(_), Some(time)) if time.is_relative() => Err(Error::InvalidTimeExpression(
+            "a relative --time cannot be combined with --date".to_string(),
+        )),
+        (None, None) => Ok(now.to_utc()),
+        (_, Some(TimeExpr::Relative(offset))) => {
+            now.to_utc().checked_add_signed(offset).ok_or_else(|| {
+                Error::InvalidTimeExpression(format!("{} from now is out of range", hhmm(offset)))
+            })
+        }
+        (date, time) => {
+            let date = match date {
+                Some(date) => date.resolve(now.date_naive())?,
+                None => now.date_naive(),
+            };
+            let time = match time {
+                Some(TimeExpr::Clock(time)) => time,
+                _ => now.time(),
+            };
+
+            zone.resolve(&date.and_time(time), dst)
+        }
+    }
+}
+
+/// Apply --date, --time, --tz, --note and tags given on the command line to `entity`. Whatever was
+/// not given keeps its current value, and a date or time given alone keeps the other part.
+pub fn edit(
+    mut entity: Entity,
+    date: Option<DateExpr>,
+    time: Option<TimeExpr>,
+    tz: Option<Zone>,
+    dst: Option<Dst>,
+    note: Option<String>,
+    tags: Vec<String>,
+) -> Result<Entity, Error> {
+    let zone = tz.or(entity.tz()).unwrap_or_else(Zone::local);
+
+    if date.is_some() || time.is_some() {
+        let current = zone.localize(entity.timestamp());
+        let time = time.unwrap_or(TimeExpr::Clock(current.time()));
+        let date = match date {
+            None if !time.is_relative() => Some(DateExpr::Absolute(current.date_naive())),
+            date => date,
+        };
+
+        entity.set_timestamp(timestamp(date, Some(time), &zone, dst)?);
+        entity.set_tz(Some(zone));
+    } else if tz.is_some() {
+        entity.set_tz(tz);
     }
 
-    let today = Local::now().date_naive();
-    let partial = NaiveDate::parse_from_str(s, "%d.%m")?;
+    if note.is_some() {
+        entity.set_note(note);
+    }
 
-    let date = NaiveDate::from_ymd_opt(today.year(), partial.month(), partial.day()).unwrap();
+    if !tags.is_empty() {
+        entity.set_tags(tags.into_iter().collect());
+    }
 
-    Ok(date)
+    Ok(entity)
 }
 
-pub fn from_naive(date: &NaiveDate, time: &NaiveTime) -> DateTime<Utc> {
-    let naive = date.and_time(*time);
-    let local_dt = Local
-        .from_local_datetime(&naive)
-        .single()
-        .expect("Ambiguous or invalid local datetime (DST issue?)");
-    local_dt.with_timezone(&Utc)
+/// Resolve an optional date expression against today's date in `zone`
+pub fn date(date: Option<DateExpr>, zone: &Zone) -> Result<Option<NaiveDate>, Error> {
+    date.map(|date| date.resolve(zone.now().date_naive()))
+        .transpose()
+}
+
+/// Format a duration as hours and minutes, e.g. "7:30" or "-0:15"
+pub fn hhmm(duration: Duration) -> String {
+    let minutes = duration.num_minutes();
+    let sign = if minutes < 0 { "-" } else { "" };
+
+    format!("{}{}:{:02}", sign, minutes.abs() / 60, minutes.abs() % 60)
+}
+
+/// Like hhmm, but with an explicit sign on positive durations, e.g. "+0:15"
+pub fn signed_hhmm(duration: Duration) -> String {
+    if duration.num_minutes() > 0 {
+        format!("+{}", hhmm(duration))
+    } else {
+        hhmm(duration)
+    }
+}
+
+/// Tell how rounding changed the time worked, e.g. "Rounded 7:54 recorded to 8:00 reported (+6 min)"
+pub fn rounding_note(recorded: Duration, reported: Duration) -> String {
+    format!(
+        "Rounded {} recorded to {} reported ({:+} min)",
+        hhmm(recorded),
+        hhmm(reported),
+        (reported - recorded).num_minutes()
+    )
+}
+
+/// Tell that a stream's hash chain was rewritten, so that hashes noted down before no longer
+/// verify it, along with its new last hash
+pub fn rewritten(head: &str) -> String {
+    format!(
+        "The hash chain was rewritten, hashes noted down before no longer verify it. Last hash: {}",
+        head
+    )
+}
+
+pub fn parse_zone(s: &str) -> Result<Zone, String> {
+    Zone::from_str(s)
 }
 
 pub fn validate_stream(s: &str) -> Result<String, String> {
@@ -76,20 +212,103 @@ pub fn parse_existing_file(s: &str) -> Result<PathBuf, String> {
     Ok(path)
 }
 
-pub fn storage(preference: Option<PathBuf>) -> Result<io::Storage, Error> {
+pub fn storage(preference: Option<PathBuf>) -> Result<Box<dyn io::Storage>, Error> {
+    let layout = layout(preference)?;
+    let backend = io::Backend::detect(&layout);
+
+    io::open(layout, backend)
+}
+
+pub fn layout(preference: Option<PathBuf>) -> Result<StorageLayout, Error> {
     let storage_path = io::Settings::storage_path()?;
-    let layout = StorageLayout::coalesce(preference, Some(storage_path));
 
-    Ok(io::Storage::new(layout))
+    Ok(StorageLayout::coalesce(preference, Some(storage_path)))
+}
+
+/// The secret of an encrypted storage from `keyfile` or the environment, or else asked for. With
+/// `confirm`, a new passphrase is asked for twice.
+pub fn secret(keyfile: Option<PathBuf>, confirm: bool) -> Result<Vec<u8>, Error> {
+    if let Some(secret) = io::secret(keyfile.as_deref())? {
+        return Ok(secret);
+    }
+
+    let prompt = |prompt: &str| {
+        rpassword::prompt_password(prompt).map_err(|err| Error::KeyMissing(err.to_string()))
+    };
+
+    let passphrase = prompt("Passphrase: ")?;
+    if passphrase.is_empty() {
+        return Err(Error::KeyMissing("the passphrase is empty".to_string()));
+    }
+    if confirm && prompt("Repeat passphrase: ")? != passphrase {
+        return Err(Error::KeyMissing("the passphrases don't match".to_string()));
+    }
+
+    Ok(passphrase.into_bytes())
+}
+
+/// Replay the named streams, or every stream with `all`. Defaults to the current stream.
+pub fn replay_streams(
+    storage: &dyn io::Storage,
+    names: &[String],
+    all: bool,
+) -> Result<Vec<(String, State)>, Error> {
+    let names = match (all, names.is_empty()) {
+        (true, _) => storage.streams()?,
+        (false, true) => vec![storage.coalease_stream_name(&None)?],
+        (false, false) => names.to_vec(),
+    };
+
+    names
+        .into_iter()
+        .map(|name| {
+            let state = storage.state(&Some(name.clone()))?;
+            Ok((name, state))
+        })
+        .collect()
+}
+
+/// Replay the named streams like `replay_streams`, each with the config that applies to it
+pub fn replay_streams_with_config(
+    preference: Option<PathBuf>,
+    storage: &dyn io::Storage,
+    names: &[String],
+    all: bool,
+) -> Result<Vec<(String, State, Config)>, Error> {
+    replay_streams(storage, names, all)?
+        .into_iter()
+        .map(|(name, state)| {
+            let config = config(preference.clone(), storage, &Some(name.clone()))?;
+            Ok((name, state, config))
+        })
+        .collect()
+}
+
+/// The norm of streams that are reported together, which their configs must agree on
+pub fn shared_norm(streams: &[(String, State, Config)]) -> Result<Norm, Error> {
+    let mut norms = streams
+        .iter()
+        .map(|(name, _, config)| (name, config.norm.clone().unwrap_or_default()));
+
+    let Some((first, norm)) = norms.next() else {
+        return Ok(Norm::default());
+    };
+    match norms.find(|(_, other)| *other != norm) {
+        Some((name, _)) => Err(Error::ConflictingConfig(format!(
+            "{} and {} expect different working time, set the norm in the global config instead",
+            first, name
+        ))),
+        None => Ok(norm),
+    }
 }
 
 pub fn config(
     preference: Option<PathBuf>,
-    storage: &io::Storage,
+    storage: &dyn io::Storage,
     stream: &Option<String>,
 ) -> Result<Config, Error> {
     let preferenced_config = match preference {
-        Some(path) => io::json::read(&path)?,
+        Some(path) => Some(Config::from_buffer(io::text::read(&path)?.as_bytes())?),
         None => None,
     };
 
@@ -101,3 +320,83 @@ pub fn config(
 
     Ok(config)
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveTime;
+
+    use super::*;
+    use crate::Id;
+    use crate::core::fixtures::*;
+
+    #[test]
+    fn editing_the_time_keeps_the_rest() {
+        let login = Entity::Login {
+            id: Id::new(),
+            timestamp: at(1, 8, 0),
+            tz: Some("UTC".parse().unwrap()),
+            note: Some("early start".to_string()),
+            tags: BTreeSet::from(["acme".to_string()]),
+        };
+        let time = TimeExpr::Clock(NaiveTime::from_hms_opt(7, 30, 0).unwrap());
+
+        let edited = edit(login.clone(), None, Some(time), None, None, None, vec![]).unwrap();
+
+        assert_eq!(edited.timestamp(), &at(1, 7, 30));
+        assert_eq!(edited.note(), login.note());
+        assert_eq!(edited.tags(), login.tags());
+
+        let edited = edit(
+            login,
+            None,
+            None,
+            None,
+            None,
+            None,
+            vec!["initech".to_string()],
+        )
+        .unwrap();
+        assert_eq!(edited.timestamp(), &at(1, 8, 0));
+        assert_eq!(edited.note(), Some("early start"));
+        assert_eq!(edited.tags(), &BTreeSet::from(["initech".to_string()]));
+    }
+
+    #[test]
+    fn rounding_note_compares_recorded_and_reported_time() {
+        let zone: Zone = "UTC".parse().unwrap();
+        let state = crate::replay(&stream(vec![
+            Entity::Login {
+                id: Id::new(),
+                timestamp: at(1, 8, 7),
+                tz: Some(zone),
+                note: None,
+                tags: BTreeSet::new(),
+            },
+            Entity::Logout {
+                id: Id::new(),
+                timestamp: at(1, 16, 1),
+                tz: Some(zone),
+                note: None,
+            },
+        ]));
+        let rounding = crate::Rounding {
+            granularity: 15,
+            mode: crate::RoundingMode::Up,
+            per: crate::RoundingScope::Day,
+        };
+        let rounded = state.rounded(&rounding, None);
+
+        let now = chrono::Utc::now();
+        let recorded = crate::worked(&crate::sessions(&state), now);
+        let reported = crate::worked(&crate::sessions(&rounded), now);
+
+        assert_eq!(
+            rounding_note(recorded, reported),
+            "Rounded 7:54 recorded to 8:00 reported (+6 min)"
+        );
+        assert_eq!(
+            rounding_note(reported, recorded),
+            "Rounded 8:00 recorded to 7:54 reported (-6 min)"
+        );
+    }
+}
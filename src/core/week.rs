@@ -0,0 +1,388 @@
+use std::collections::BTreeMap;
+
+use chrono::DateTime;
+use chrono::Duration;
+use chrono::NaiveDate;
+use chrono::Utc;
+use colored::Colorize;
+use serde::Serialize;
+
+use super::Day;
+use super::Norm;
+use super::Overlap;
+use super::Session;
+use super::State;
+use super::Zone;
+use super::days;
+use super::overlaps;
+use super::sessions;
+use super::util::hhmm;
+use super::util::signed_hhmm;
+
+/// A Monday to Sunday timesheet, laid out like the grids of corporate timesheet systems
+pub struct Week {
+    pub days: Vec<Day>,
+    /// Time worked per stream and day, when the week covers more than one stream
+    pub streams: BTreeMap<String, Vec<Duration>>,
+    /// Time worked per activity and day
+    pub activities: BTreeMap<String, Vec<Duration>>,
+    /// Time worked per tag and day, from the tags of sessions and activities
+    pub tags: BTreeMap<String, Vec<Duration>>,
+    /// Sessions in different streams that ran at the same time
+    pub overlaps: Vec<Overlap>,
+}
+
+/// One day of a week in json and ndjson output. Durations are in seconds.
+#[derive(Serialize)]
+pub struct WeekDay<'a> {
+    #[serde(flatten)]
+    pub day: &'a Day,
+    /// Time worked per stream, when the week covers more than one stream
+    pub streams: BTreeMap<String, i64>,
+    pub activities: BTreeMap<String, i64>,
+    pub tags: BTreeMap<String, i64>,
+    /// Sessions in different streams that ran at the same time, starting this day
+    pub overlaps: Vec<&'a Overlap>,
+}
+
+impl Week {
+    /// Summarize the sessions started in the week beginning `monday`, across all of `streams`.
+    /// Days are taken in the zone each session was recorded in unless `tz` overrides it, and
+    /// running sessions count until `now`.
+    pub fn new(
+        streams: &[(String, State)],
+        monday: NaiveDate,
+        norm: &Norm,
+        tz: Option<&Zone>,
+        now: DateTime<Utc>,
+    ) -> Self {
+        let sunday = monday + Duration::days(6);
+        let all: Vec<Session> = streams
+            .iter()
+            .flat_map(|(_, state)| sessions(state))
+            .collect();
+
+        let mut per_stream: BTreeMap<String, Vec<Duration>> = BTreeMap::new();
+        if streams.len() > 1 {
+            for (name, state) in streams {
+                let worked = days(&sessions(state), monday, sunday, norm, tz, now)
+                    .iter()
+                    .map(|day| day.worked)
+                    .collect();
+                per_stream.insert(name.clone(), worked);
+            }
+        }
+
+        let mut activities: BTreeMap<String, Vec<Duration>> = BTreeMap::new();
+        let mut tags: BTreeMap<String, Vec<Duration>> = BTreeMap::new();
+        for session in &all {
+            let index = (session.date(tz) - monday).num_days();
+            if !(0..7).contains(&index) {
+                continue;
+            }
+
+            for segment in session.segments() {
+                let duration = segment.end - segment.start;
+                if let Some(activity) = segment.activity {
+                    add(&mut activities, activity.to_string(), index, duration);
+                }
+                for tag in &segment.tags {
+                    add(&mut tags, format!("+{}", tag), index, duration);
+                }
+            }
+        }
+
+        let zone = tz.copied().unwrap_or_else(Zone::local);
+        let overlaps = overlaps(streams, now)
+            .into_iter()
+            .filter(|overlap| {
+                let date = zone.localize(&overlap.start).date_naive();
+                monday <= date && date <= sunday
+            })
+            .collect();
+
+        Self {
+            days: days(&all, monday, sunday, norm, tz, now),
+            streams: per_stream,
+            activities,
+            tags,
+            overlaps,
+        }
+    }
+
+    /// The week a day at a time, for json and ndjson output
+    pub fn days(&self, tz: Option<&Zone>) -> Vec<WeekDay<'_>> {
+        let zone = tz.copied().unwrap_or_else(Zone::local);
+        let on = |breakdown: &BTreeMap<String, Vec<Duration>>, i: usize| {
+            breakdown
+                .iter()
+                .filter(|(_, durations)| !durations[i].is_zero())
+                .map(|(label, durations)| (label.clone(), durations[i].num_seconds()))
+                .collect()
+        };
+
+        self.days
+            .iter()
+            .enumerate()
+            .map(|(i, day)| WeekDay {
+                day,
+                streams: on(&self.streams, i),
+                activities: on(&self.activities, i),
+                tags: on(&self.tags, i),
+                overlaps: self
+                    .overlaps
+                    .iter()
+                    .filter(|overlap| zone.localize(&overlap.start).date_naive() == day.date)
+                    .collect(),
+            })
+            .collect()
+    }
+
+    /// Render the week as an aligned table, with a column per day and one for the week's total.
+    /// Overlapping sessions are listed below the table. Differences and the overlap heading are
+    /// coloured if `color` is set.
+    pub fn render(&self, tz: Option<&Zone>, color: bool) -> String {
+        let total = |f: fn(&Day) -> Duration| self.days.iter().map(f).sum::<Duration>();
+        let cells = |f: fn(&Day) -> String| self.days.iter().map(f).collect::<Vec<_>>();
+
+        let mut header = vec![String::new()];
+        header.extend(
+            self.days
+                .iter()
+                .map(|day| day.date.format("%a %d.%m").to_string()),
+        );
+        header.push("Total".to_string());
+
+        let mut rows: Vec<Vec<String>> = vec![header];
+        let mut row = |label: &str, mut values: Vec<String>, total: String| {
+            values.insert(0, label.to_string());
+            values.push(total);
+            rows.push(values);
+        };
+
+        row("In", cells(Day::in_time), String::new());
+        row("Out", cells(Day::out_time), String::new());
+        row(
+            "Breaks",
+            cells(|day| duration(day, day.breaks)),
+            hhmm(total(|day| day.breaks)),
+        );
+        row(
+            "Worked",
+            cells(|day| duration(day, day.worked)),
+            hhmm(total(|day| day.worked)),
+        );
+        row(
+            "Norm",
+            cells(|day| duration(day, day.norm)),
+            hhmm(total(|day| day.norm)),
+        );
+        row(
+            "Diff",
+            cells(|day| {
+                if day.is_empty() {
+                    String::new()
+                } else {
+                    signed_hhmm(day.diff())
+                }
+            }),
+            signed_hhmm(total(|day| day.diff())),
+        );
+
+        let diff = rows.len() - 1;
+        let mut sections = vec![];
+        for breakdown in [&self.streams, &self.activities, &self.tags] {
+            sections.push(rows.len());
+            for (label, durations) in breakdown {
+                let mut values = vec![label.clone()];
+                values.extend(
+                    durations
+                        .iter()
+                        .map(|d| if d.is_zero() { String::new() } else { hhmm(*d) }),
+                );
+                values.push(hhmm(durations.iter().sum()));
+                rows.push(values);
+            }
+        }
+
+        let columns = rows[0].len();
+        let widths: Vec<usize> = (0..columns)
+            .map(|i| rows.iter().map(|row| row[i].chars().count()).max().unwrap())
+            .collect();
+
+        let mut table = String::new();
+        for (i, row) in rows.iter().enumerate() {
+            if sections.contains(&i) {
+                table.push('\n');
+            }
+
+            let mut line = format!("{:<width$}", row[0], width = widths[0]);
+            for (cell, width) in row.iter().zip(&widths).skip(1) {
+                let cell = format!("{:>width$}", cell, width = width);
+                let cell = match (color && i == diff, cell.trim_start().chars().next()) {
+                    (true, Some('-')) => cell.red().to_string(),
+                    (true, Some('+')) => cell.green().to_string(),
+                    _ => cell,
+                };
+                line += "  ";
+                line += &cell;
+            }
+            table += line.trim_end();
+            table.push('\n');
+        }
+
+        if !self.overlaps.is_empty() {
+            let heading = "Overlapping sessions:";
+            if color {
+                table += &format!("\n{}\n", heading.yellow());
+            } else {
+                table += &format!("\n{}\n", heading);
+            }
+            for overlap in &self.overlaps {
+                table += &format!("  {}\n", overlap.describe(tz));
+            }
+        }
+
+        table
+    }
+}
+
+fn add(breakdown: &mut BTreeMap<String, Vec<Duration>>, label: String, day: i64, d: Duration) {
+    breakdown
+        .entry(label)
+        .or_insert_with(|| vec![Duration::zero(); 7])[day as usize] += d;
+}
+
+fn duration(day: &Day, duration: Duration) -> String {
+    if day.is_empty() {
+        String::new()
+    } else {
+        hhmm(duration)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::*;
+    use crate::Entity;
+    use crate::Id;
+    use crate::core::fixtures::*;
+    use crate::replay;
+
+    fn state(entities: Vec<Entity>) -> State {
+        replay(&stream(entities))
+    }
+
+    fn utc() -> Zone {
+        "UTC".parse().unwrap()
+    }
+
+    fn login(timestamp: DateTime<Utc>) -> Entity {
+        Entity::Login {
+            id: Id::new(),
+            timestamp,
+            tz: Some(utc()),
+            note: None,
+            tags: BTreeSet::new(),
+        }
+    }
+
+    fn logout(timestamp: DateTime<Utc>) -> Entity {
+        Entity::Logout {
+            id: Id::new(),
+            timestamp,
+            tz: Some(utc()),
+            note: None,
+        }
+    }
+
+    #[test]
+    fn days_breaks_and_activities() {
+        let state = state(vec![
+            login(at(1, 8, 0)),
+            Entity::Break {
+                id: Id::new(),
+                timestamp: at(1, 11, 30),
+                tz: Some(utc()),
+                note: None,
+                tags: BTreeSet::new(),
+                duration: Duration::minutes(30),
+                autoinsert: false,
+            },
+            Entity::Activity {
+                id: Id::new(),
+                timestamp: at(1, 9, 0),
+                tz: Some(utc()),
+                note: None,
+                tags: BTreeSet::new(),
+                duration: Duration::hours(2),
+                value: "Review".to_string(),
+                autoinsert: false,
+            },
+            logout(at(1, 16, 0)),
+            login(at(3, 7, 0)),
+            logout(at(3, 12, 0)),
+            login(at(3, 13, 0)),
+            logout(at(3, 15, 0)),
+            login(at(8, 8, 0)),
+            logout(at(8, 9, 0)),
+        ]);
+        let monday = NaiveDate::from_ymd_opt(2025, 9, 1).unwrap();
+
+        let week = Week::new(
+            &[("work".to_string(), state)],
+            monday,
+            &Norm::default(),
+            None,
+            Utc::now(),
+        );
+
+        let monday = &week.days[0];
+        assert_eq!(
+            monday.first_in.unwrap().format("%H:%M").to_string(),
+            "08:00"
+        );
+        assert_eq!(
+            monday.last_out.unwrap().format("%H:%M").to_string(),
+            "16:00"
+        );
+        assert_eq!(monday.breaks, Duration::minutes(30));
+        assert_eq!(monday.worked, Duration::minutes(450));
+        assert_eq!(monday.diff(), Duration::zero());
+
+        let wednesday = &week.days[2];
+        assert_eq!(wednesday.worked, Duration::hours(7));
+        assert_eq!(wednesday.diff(), Duration::minutes(-30));
+
+        assert!(week.days[1].first_in.is_none());
+        let total: Duration = week.days.iter().map(|day| day.worked).sum();
+        assert_eq!(total, Duration::minutes(870));
+        assert_eq!(week.activities["Review"][0], Duration::hours(2));
+
+        let days = week.days(None);
+        assert_eq!(days[0].day.worked, Duration::minutes(450));
+        assert_eq!(days[0].activities["Review"], 2 * 3600);
+        assert!(days[1].activities.is_empty() && days[0].streams.is_empty());
+    }
+
+    #[test]
+    fn render_aligns_columns() {
+        let state = state(vec![login(at(1, 8, 0)), logout(at(1, 16, 15))]);
+        let monday = NaiveDate::from_ymd_opt(2025, 9, 1).unwrap();
+
+        let streams = [("work".to_string(), state)];
+        let table =
+            Week::new(&streams, monday, &Norm::default(), None, Utc::now()).render(None, false);
+        let lines: Vec<&str> = table.lines().collect();
+
+        let header =
+            "Mon 01.09  Tue 02.09  Wed 03.09  Thu 04.09  Fri 05.09  Sat 06.09  Sun 07.09   Total";
+        assert_eq!(lines[0].trim(), header);
+        assert!(lines[1].starts_with("In          08:00"));
+        assert!(lines[4].starts_with("Worked       8:15       0:00"));
+        assert!(lines[6].starts_with("Diff        +0:45      -7:30"));
+        assert!(lines[6].ends_with("-29:15"));
+    }
+}
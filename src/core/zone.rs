@@ -0,0 +1,229 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use chrono::DateTime;
+use chrono::FixedOffset;
+use chrono::Local;
+use chrono::LocalResult;
+use chrono::NaiveDateTime;
+use chrono::Offset as _;
+use chrono::TimeZone as _;
+use chrono::Utc;
+use chrono_tz::Tz;
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
+
+use super::Error;
+
+/// The timezone an entity was recorded in
+///
+/// Stored as the IANA name (e.g. "Europe/Oslo") when it is known, and as a fixed UTC offset (e.g.
+/// "+02:00") otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Zone {
+    Named(Tz),
+    Fixed(FixedOffset),
+}
+
+/// How to resolve a local time that occurs twice because of a daylight saving transition
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Dst {
+    Earlier,
+    Later,
+}
+
+impl Zone {
+    /// The timezone of this machine
+    ///
+    /// Honours the TZ environment variable, then falls back to the system configuration and
+    /// finally to the current UTC offset if the zone name cannot be determined. A TZ that isn't a
+    /// zone name, e.g. a POSIX rule, is skipped.
+    pub fn local() -> Self {
+        std::env::var("TZ")
+            .ok()
+            .and_then(|name| named(&name))
+            .or_else(|| named(&iana_time_zone::get_timezone().ok()?))
+            .map(Zone::Named)
+            .unwrap_or_else(|| Zone::Fixed(Local::now().offset().fix()))
+    }
+
+    pub fn now(&self) -> DateTime<FixedOffset> {
+        self.localize(&Utc::now())
+    }
+
+    pub fn localize(&self, timestamp: &DateTime<Utc>) -> DateTime<FixedOffset> {
+        match self {
+            Zone::Named(tz) => timestamp.with_timezone(tz).fixed_offset(),
+            Zone::Fixed(offset) => timestamp.with_timezone(offset),
+        }
+    }
+
+    /// Interpret a wall-clock time in this zone
+    ///
+    /// Times that fall into a daylight saving gap do not exist and are rejected. Times that occur
+    /// twice are rejected unless `dst` says which of the two is meant.
+    pub fn resolve(&self, naive: &NaiveDateTime, dst: Option<Dst>) -> Result<DateTime<Utc>, Error> {
+        let resolved = match self {
+            Zone::Named(tz) => tz
+                .from_local_datetime(naive)
+                .map(|dt| dt.with_timezone(&Utc)),
+            Zone::Fixed(offset) => offset
+                .from_local_datetime(naive)
+                .map(|dt| dt.with_timezone(&Utc)),
+        };
+
+        match (resolved, dst) {
+            (LocalResult::Single(dt), _) => Ok(dt),
+            (LocalResult::Ambiguous(earlier, _), Some(Dst::Earlier)) => Ok(earlier),
+            (LocalResult::Ambiguous(_, later), Some(Dst::Later)) => Ok(later),
+            (LocalResult::Ambiguous(..), None) => Err(Error::AmbiguousLocalTime(format!(
+                "{} is ambiguous in {}, use --dst earlier|later",
+                naive, self
+            ))),
+            (LocalResult::None, _) => Err(Error::NonexistentLocalTime(format!(
+                "{} does not exist in {}",
+                naive, self
+            ))),
+        }
+    }
+}
+
+impl FromStr for Zone {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(tz) = s.parse::<Tz>() {
+            return Ok(Zone::Named(tz));
+        }
+
+        if let Ok(offset) = s.parse::<FixedOffset>() {
+            return Ok(Zone::Fixed(offset));
+        }
+
+        Err(format!(
+            "'{}' is neither an IANA timezone nor a UTC offset such as +02:00",
+            s
+        ))
+    }
+}
+
+impl Display for Zone {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Zone::Named(tz) => write!(f, "{}", tz.name()),
+            Zone::Fixed(offset) => write!(f, "{}", offset),
+        }
+    }
+}
+
+impl Serialize for Zone {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Zone {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// A zone name as found in TZ, which may start with a colon, e.g. ":Europe/Oslo"
+fn named(name: &str) -> Option<Tz> {
+    name.trim_start_matches(':').parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use chrono::TimeZone as _;
+    use chrono::Utc;
+
+    use super::*;
+
+    fn oslo() -> Zone {
+        "Europe/Oslo".parse().unwrap()
+    }
+
+    #[test]
+    fn parse_named_and_fixed() {
+        assert_eq!(oslo(), Zone::Named(chrono_tz::Europe::Oslo));
+        assert_eq!(
+            "+02:00".parse::<Zone>().unwrap(),
+            Zone::Fixed(FixedOffset::east_opt(2 * 3600).unwrap())
+        );
+        assert!("Mars/Olympus".parse::<Zone>().is_err());
+    }
+
+    #[test]
+    fn display_roundtrips() {
+        for s in ["Europe/Oslo", "-05:30"] {
+            assert_eq!(s.parse::<Zone>().unwrap().to_string(), s);
+        }
+    }
+
+    #[test]
+    fn zone_names_from_tz() {
+        assert_eq!(named("Europe/Oslo"), Some(chrono_tz::Europe::Oslo));
+        assert_eq!(named(":Europe/Oslo"), Some(chrono_tz::Europe::Oslo));
+        // POSIX rules aren't names, so the system zone is used instead
+        assert_eq!(named("CET-1CEST,M3.5.0,M10.5.0/3"), None);
+    }
+
+    #[test]
+    fn resolve_single() {
+        let naive = NaiveDate::from_ymd_opt(2025, 9, 1)
+            .unwrap()
+            .and_hms_opt(9, 0, 0)
+            .unwrap();
+
+        let expected = Utc.with_ymd_and_hms(2025, 9, 1, 7, 0, 0).unwrap();
+        assert_eq!(oslo().resolve(&naive, None).unwrap(), expected);
+    }
+
+    #[test]
+    fn resolve_ambiguous_requires_dst() {
+        // Clocks in Oslo go back from 03:00 to 02:00 on the last Sunday of October
+        let naive = NaiveDate::from_ymd_opt(2025, 10, 26)
+            .unwrap()
+            .and_hms_opt(2, 30, 0)
+            .unwrap();
+
+        match oslo().resolve(&naive, None).unwrap_err() {
+            Error::AmbiguousLocalTime(_) => {}
+            other => panic!("expected AmbiguousLocalTime, got {:?}", other),
+        }
+
+        let earlier = oslo().resolve(&naive, Some(Dst::Earlier)).unwrap();
+        let later = oslo().resolve(&naive, Some(Dst::Later)).unwrap();
+
+        assert_eq!(
+            earlier,
+            Utc.with_ymd_and_hms(2025, 10, 26, 0, 30, 0).unwrap()
+        );
+        assert_eq!(later, Utc.with_ymd_and_hms(2025, 10, 26, 1, 30, 0).unwrap());
+    }
+
+    #[test]
+    fn resolve_gap_is_rejected() {
+        // Clocks in Oslo skip from 02:00 to 03:00 on the last Sunday of March
+        let naive = NaiveDate::from_ymd_opt(2025, 3, 30)
+            .unwrap()
+            .and_hms_opt(2, 30, 0)
+            .unwrap();
+
+        match oslo().resolve(&naive, Some(Dst::Earlier)).unwrap_err() {
+            Error::NonexistentLocalTime(_) => {}
+            other => panic!("expected NonexistentLocalTime, got {:?}", other),
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use clap::Parser;
-use colored;
+use skuff::output;
+use skuff::output::Format;
 
 mod cmd;
 
@@ -7,30 +8,49 @@ const ABOUT: &str = r#"Skuff "#;
 
 fn main() -> Result<(), skuff::Error> {
     let cli = Cli::parse();
-
-    // Enable colored output
-    //
-    // This is needed to force colored output on when used in combination with the pager crate
-    // which spawns a child process and pipes the output to it.
-    //
-    // https://github.com/colored-rs/colored/issues/69
-    colored::control::set_override(true);
+    let format = cli.format;
+    let terminal = cli.terminal;
 
     use Command::*;
     match cli.command {
-        New(args) => cmd::new::command(args),
-        In(args) => cmd::r#in::command(args),
-        Out(args) => cmd::out::command(args),
-        Log(args) => cmd::log::command(args),
-        Ls(args) => cmd::ls::command(args),
-        Rm(args) => cmd::rm::command(args),
-        Switch(args) => cmd::switch::command(args),
+        New(args) => cmd::new::command(args, format),
+        Activity(args) => cmd::activity::command(args, format),
+        Backup(args) => cmd::backup::command(args, format),
+        Compact(args) => cmd::compact::command(args, format),
+        Convert(args) => cmd::convert::command(args, format),
+        Decrypt(args) => cmd::decrypt::command(args, format),
+        Edit(args) => cmd::edit::command(args, format),
+        Encrypt(args) => cmd::encrypt::command(args, format),
+        In(args) => cmd::r#in::command(args, format),
+        Import(args) => cmd::import::command(args, format),
+        Out(args) => cmd::out::command(args, format),
+        Log(args) => cmd::log::command(args, format, terminal),
+        Export(args) => cmd::export::command(args, format),
+        Lock(args) => cmd::lock::command(args, format),
+        Ls(args) => cmd::ls::command(args, format),
+        Merge(args) => cmd::merge::command(args, format),
+        Report(args) => cmd::report::command(args, format, terminal),
+        Rm(args) => cmd::rm::command(args, format),
+        Status(args) => cmd::status::command(args, format, terminal),
+        Submit(args) => cmd::submit::command(args, format),
+        Switch(args) => cmd::switch::command(args, format),
+        Sync(args) => cmd::sync::command(args, format),
+        Unlock(args) => cmd::unlock::command(args, format),
+        Verify(args) => cmd::verify::command(args, format),
+        Week(args) => cmd::week::command(args, format, terminal),
     }
 }
 
 #[derive(Parser)]
 #[command(author, version, about = ABOUT, long_about = None)]
 struct Cli {
+    /// Print json or ndjson for use in scripts
+    #[arg(long, global = true, value_enum, default_value_t)]
+    format: Format,
+
+    #[command(flatten)]
+    terminal: output::Terminal,
+
     #[command(subcommand)]
     command: Command,
 }
@@ -38,10 +58,28 @@ struct Cli {
 #[derive(clap::Subcommand)]
 enum Command {
     New(cmd::new::Args),
+    Activity(cmd::activity::Args),
+    Backup(cmd::backup::Args),
+    Compact(cmd::compact::Args),
+    Convert(cmd::convert::Args),
+    Decrypt(cmd::decrypt::Args),
+    Edit(cmd::edit::Args),
+    Encrypt(cmd::encrypt::Args),
     In(cmd::r#in::Args),
+    Import(cmd::import::Args),
     Out(cmd::out::Args),
     Log(cmd::log::Args),
+    Export(cmd::export::Args),
+    Lock(cmd::lock::Args),
     Ls(cmd::ls::Args),
+    Merge(cmd::merge::Args),
+    Report(cmd::report::Args),
     Rm(cmd::rm::Args),
+    Status(cmd::status::Args),
+    Submit(cmd::submit::Args),
     Switch(cmd::switch::Args),
+    Sync(cmd::sync::Args),
+    Unlock(cmd::unlock::Args),
+    Verify(cmd::verify::Args),
+    Week(cmd::week::Args),
 }